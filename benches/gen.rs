@@ -0,0 +1,42 @@
+//! `cargo bench --bench gen --features gen`, on graphs from `GraphGen`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use dep_res::{DepRes, GraphGen};
+
+fn add_generated(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_generated");
+    group.sample_size(10);
+    for n in [10_000, 100_000] {
+        let items = GraphGen::new(n)
+            .edge_density(4.0 / n as f64)
+            .seed(42)
+            .items();
+        group.bench_with_input(BenchmarkId::from_parameter(n), &items, |b, items| {
+            b.iter(|| {
+                let dr = DepRes::new();
+                dr.add(items);
+                dr
+            })
+        });
+    }
+    group.finish();
+}
+
+fn resolve_generated(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resolve_generated");
+    group.sample_size(10);
+    for depth in [10, 1000] {
+        let mut dr = GraphGen::new(100_000)
+            .edge_density(0.00005)
+            .max_depth(depth)
+            .seed(42)
+            .build();
+        group.bench_with_input(BenchmarkId::new("depth", depth), &depth, |b, _| {
+            b.iter(|| dr.resolve().unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_generated, resolve_generated);
+criterion_main!(benches);
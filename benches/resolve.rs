@@ -0,0 +1,169 @@
+//! `cargo bench --bench resolve`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use dep_res::{DepMeta, DepRes};
+
+struct Item {
+    id: String,
+    deps: Vec<String>,
+}
+
+impl DepMeta for Item {
+    type Id = String;
+
+    fn get_id(&self) -> Self::Id {
+        self.id.clone()
+    }
+
+    fn get_deps(&self) -> &[Self::Id] {
+        &self.deps
+    }
+}
+
+/// Random DAG with crate-path-like string ids
+fn string_graph(n: usize, max_deps: usize) -> Vec<Item> {
+    let name = |i: usize| format!("registry/index/crates/some-crate-name-{i:08}/version");
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+    (0..n)
+        .map(|i| Item {
+            id: name(i),
+            deps: if i == 0 {
+                vec![]
+            } else {
+                (0..next() % (max_deps + 1))
+                    .map(|_| name(next() % i))
+                    .collect()
+            },
+        })
+        .collect()
+}
+
+fn resolve_strings(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resolve_strings");
+    group.sample_size(10);
+    for n in [10_000, 100_000] {
+        let items = string_graph(n, 4);
+        let mut dr = DepRes::new();
+        dr.add(&items);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, _| {
+            b.iter(|| dr.resolve().unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Deep and narrow, one level per node
+fn resolve_chain(c: &mut Criterion) {
+    let name = |i: usize| format!("registry/index/crates/chain-{i:08}");
+    let items = (0..100_000)
+        .map(|i| Item {
+            id: name(i),
+            deps: if i == 0 { vec![] } else { vec![name(i - 1)] },
+        })
+        .collect::<Vec<_>>();
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let mut group = c.benchmark_group("resolve_chain");
+    group.sample_size(10);
+    group.bench_function("100000", |b| b.iter(|| dr.resolve().unwrap()));
+    group.finish();
+}
+
+/// Many shallow levels, dominated by per-level overhead
+fn resolve_layered(c: &mut Criterion) {
+    let (levels, width) = (10_000, 4);
+    let name = |i: usize| format!("registry/index/crates/layered-{i:08}");
+    let items = (0..levels * width)
+        .map(|i| Item {
+            id: name(i),
+            deps: match i / width {
+                0 => vec![],
+                lv => ((lv - 1) * width..lv * width).map(name).collect(),
+            },
+        })
+        .collect::<Vec<_>>();
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let mut group = c.benchmark_group("resolve_layered");
+    group.sample_size(10);
+    group.bench_function("10000x4", |b| b.iter(|| dr.resolve().unwrap()));
+    group.finish();
+}
+
+/// Few items with thousands of deps each, dominated by the per-item dep set insertion
+fn add_high_fanout(c: &mut Criterion) {
+    let deps = (0..5000)
+        .map(|i| format!("registry/index/crates/dep-{i:08}"))
+        .collect::<Vec<_>>();
+    let items = (0..64)
+        .map(|i| Item {
+            id: format!("registry/index/crates/wide-{i:08}"),
+            deps: deps.clone(),
+        })
+        .collect::<Vec<_>>();
+    c.bench_function("add_high_fanout", |b| {
+        b.iter(|| {
+            let dr = DepRes::new();
+            dr.add(&items);
+            dr
+        })
+    });
+}
+
+/// `add` and `resolve` of a small graph, on the calling thread versus fanned out
+fn small_graph(c: &mut Criterion) {
+    let items = string_graph(200, 4);
+    let mut group = c.benchmark_group("small_graph");
+    for (name, threshold) in [("sequential", usize::MAX), ("parallel", 0)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut dr = DepRes::new();
+                dr.set_parallel_threshold(threshold);
+                dr.add(&items);
+                dr.resolve().unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Reading the levels of a wide resolution, through the slices and through the set view
+fn iterate_wide(c: &mut Criterion) {
+    let items = string_graph(100_000, 1);
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve().unwrap();
+    let mut group = c.benchmark_group("iterate_wide");
+    group.bench_function("iter_level_shared", |b| {
+        b.iter(|| {
+            (r.iter_level_shared())
+                .map(|l| l.deps.iter().map(|id| id.len()).sum::<usize>())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("iter_level_sets", |b| {
+        b.iter(|| {
+            (r.iter_level_sets())
+                .map(|l| l.deps.iter().map(|id| id.len()).sum::<usize>())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    resolve_strings,
+    resolve_chain,
+    resolve_layered,
+    add_high_fanout,
+    small_graph,
+    iterate_wide
+);
+criterion_main!(benches);
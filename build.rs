@@ -0,0 +1,13 @@
+use std::env;
+
+fn main() {
+    // `cfg(parallel)`: the `parallel` feature on a target where rayon can actually spawn threads,
+    // wasm32 without atomics quietly falls back to the sequential implementation
+    println!("cargo::rustc-check-cfg=cfg(parallel)");
+    let wasm = env::var("CARGO_CFG_TARGET_ARCH").is_ok_and(|arch| arch == "wasm32");
+    let atomics = env::var("CARGO_CFG_TARGET_FEATURE")
+        .is_ok_and(|features| features.split(',').any(|f| f == "atomics"));
+    if env::var_os("CARGO_FEATURE_PARALLEL").is_some() && (!wasm || atomics) {
+        println!("cargo::rustc-cfg=parallel");
+    }
+}
@@ -0,0 +1,90 @@
+use crate::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `{"nodes": [...], "edges": {"id": ["dep", ...]}}`
+#[derive(Serialize)]
+struct AdjacencySorted<Id: Ord> {
+    nodes: Vec<Id>,
+    edges: BTreeMap<Id, Vec<Id>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "Id: DeserializeOwned + Eq + Hash")]
+struct AdjacencyOwned<Id: Eq + Hash> {
+    nodes: Vec<Id>,
+    #[serde(default)]
+    edges: HashMap<Id, Vec<Id>>,
+}
+
+#[derive(Error, Debug)]
+pub enum AdjacencyJsonError<Id> {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("node {0:?} is listed more than once")]
+    DuplicateNode(Id),
+    #[error("edges are given for {0:?} which is not in nodes")]
+    UnknownNode(Id),
+    #[error("{id:?} depends on {dep:?} which is not in nodes")]
+    DanglingEdge { id: Id, dep: Id },
+}
+
+impl<
+        Id: Sync + Send + Eq + Hash + Clone + Ord + Serialize,
+        S: BuildHasher + Clone + Default + Send + Sync,
+    > DepRes<Id, S>
+{
+    /// Exports the graph in the JSON adjacency shape, nodes and deps sorted so output is diff-friendly
+    ///
+    /// Returns [`AdjacencyJsonError::Json`] if a node with deps has an id that can't be a JSON
+    /// object key, e.g. a tuple
+    pub fn to_adjacency_json(&self) -> Result<String, AdjacencyJsonError<Id>> {
+        let mut nodes = self.ids().collect::<Vec<_>>();
+        nodes.sort();
+        let mut edges = BTreeMap::new();
+        self.deps.for_each(|id, set| {
+            let mut deps = set
+                .to_vec()
+                .iter()
+                .map(|a| (**a).clone())
+                .collect::<Vec<_>>();
+            deps.sort();
+            edges.insert((**id).clone(), deps);
+        });
+        Ok(serde_json::to_string(&AdjacencySorted { nodes, edges })?)
+    }
+}
+
+impl<
+        Id: Sync + Send + Eq + Hash + Clone + DeserializeOwned,
+        S: BuildHasher + Clone + Default + Send + Sync,
+    > DepRes<Id, S>
+{
+    /// Imports the JSON adjacency shape
+    ///
+    /// Every dep has to be listed in `nodes` too, dangling edge targets are rejected
+    pub fn from_adjacency_json(json: &str) -> Result<Self, AdjacencyJsonError<Id>> {
+        let repr: AdjacencyOwned<Id> = serde_json::from_str(json)?;
+        let dr = DepRes::<Id, S>::default();
+        for id in repr.nodes {
+            if !dr.insert_id(&id) {
+                return Err(AdjacencyJsonError::DuplicateNode(id));
+            }
+        }
+        for (id, deps) in repr.edges {
+            if !dr.ids.contains(&id) {
+                return Err(AdjacencyJsonError::UnknownNode(id));
+            }
+            if let Some(dep) = deps.iter().find(|dep| !dr.ids.contains(*dep)) {
+                return Err(AdjacencyJsonError::DanglingEdge {
+                    dep: dep.clone(),
+                    id,
+                });
+            }
+            if !deps.is_empty() {
+                dr.insert_deps(&id, &deps);
+            }
+        }
+        Ok(dr)
+    }
+}
@@ -0,0 +1,151 @@
+use crate::*;
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Makes deps on `alias` count as deps on `target` when resolving, e.g. for virtual
+    /// capabilities a package provides
+    ///
+    /// Many aliases may point to one target, `target` may itself be an alias. A node named
+    /// `alias` wins over the alias, and deps on an alias whose chain ends nowhere are handled
+    /// like any other missing dep. Returns [`DepResolveError::AliasConflict`] if `alias`
+    /// already points to another target and [`DepResolveError::AliasCycle`] if the chain
+    /// from `target` leads back to `alias`, counting aliases a node shadows
+    pub fn add_alias(&self, alias: Id, target: Id) -> Result<(), DepResolveError<Id>> {
+        let (alias, target) = (self.normalize(&alias), self.normalize(&target));
+        if let Some(existing) = self.aliases.get_cloned(&alias) {
+            return match *existing == target {
+                true => Ok(()),
+                false => Err(DepResolveError::AliasConflict {
+                    alias,
+                    existing: (*existing).clone(),
+                }),
+            };
+        }
+        if self.expands_to(&target, &alias) {
+            return Err(DepResolveError::AliasCycle(alias));
+        }
+        self.aliases
+            .insert(self.intern(&alias), self.intern(&target));
+        self.touch();
+        Ok(())
+    }
+
+    /// Drops `alias`, returning what it pointed to
+    pub fn remove_alias(&self, alias: &Id) -> Option<Id> {
+        let alias = &*self.canon(alias);
+        let (key, target) = self.aliases.remove(alias)?;
+        let target_id = (*target).clone();
+        drop((key, target));
+        self.release(alias);
+        self.release(&target_id);
+        self.touch();
+        Some(target_id)
+    }
+
+    /// Direct deps of `id` as they were added
+    pub fn deps_of<Q>(&self, id: &Q) -> Vec<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        self.find(id).map_or(vec![], |id| self.direct_deps(&id))
+    }
+
+    /// Direct deps of `id` with aliases followed to their targets and groups expanded to
+    /// their members, as resolving sees them
+    pub fn resolved_deps_of<Q>(&self, id: &Q) -> Vec<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        self.find(id).map_or(vec![], |id| self.target_deps(&id))
+    }
+
+    /// [`resolved_deps_of`](Self::resolved_deps_of) for an id that is already canonical
+    pub(crate) fn target_deps(&self, id: &Id) -> Vec<Id> {
+        let mut deps = vec![];
+        for dep in self.direct_deps(id) {
+            self.for_each_target(&dep, &mut |dep| {
+                if !deps.contains(dep) {
+                    deps.push(dep.clone());
+                }
+            });
+        }
+        deps
+    }
+
+    /// Everything resolving levels `id` after: its [resolved deps](Self::resolved_deps_of)
+    /// and its soft deps expanded the same way, less the soft edges in `dropped`
+    ///
+    /// Deps that are no node are kept, callers only count the ones among their nodes
+    pub(crate) fn leveled_deps(&self, id: &Id, dropped: &[(Id, Id)]) -> Vec<Id> {
+        let mut deps = self.target_deps(id);
+        // dropped edges name what the soft dep stands for, not the alias or group itself
+        let mut push = |dep: &Id| {
+            let is_dropped = dropped.iter().any(|(node, to)| node == id && to == dep);
+            if !is_dropped && !deps.contains(dep) {
+                deps.push(dep.clone());
+            }
+        };
+        let soft = self.soft.read(id, |_, soft| soft.to_vec()).unwrap_or_default();
+        for dep in soft {
+            self.for_each_target(&dep, &mut push);
+        }
+        deps
+    }
+
+    /// Calls `f` with what a dep on `id` stands for: `id` itself unless it is an alias or a
+    /// group that isn't shadowed by a node, then whatever its target or members stand for
+    ///
+    /// An alias or group reached a second time stands for nothing more, so a cycle left
+    /// behind by removing the node that shadowed it ends the expansion instead of recursing
+    pub(crate) fn for_each_target(&self, id: &Id, f: &mut dyn FnMut(&Id)) {
+        if self.aliases.is_empty() && self.groups.is_empty() {
+            return f(id);
+        }
+        self.expand(id, &mut HashSet::new(), f)
+    }
+
+    fn expand(&self, id: &Id, seen: &mut HashSet<Id>, f: &mut dyn FnMut(&Id)) {
+        if self.ids.contains(id) {
+            return f(id);
+        }
+        if !seen.insert(id.clone()) {
+            return;
+        }
+        // copied out before recursing, a nested read could block behind a writer
+        if let Some(target) = self.aliases.get_cloned(id) {
+            return self.expand(&target, seen, f);
+        }
+        if let Some(members) = self.groups.get_cloned(id) {
+            for member in members.iter() {
+                self.expand(member, seen, f);
+            }
+            return;
+        }
+        f(id)
+    }
+
+    /// Whether following aliases and group members from `from` can reach `to`, also through
+    /// the ones shadowed by a node since removing the node brings them back
+    pub(crate) fn expands_to(&self, from: &Id, to: &Id) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![from.clone()];
+        while let Some(id) = stack.pop() {
+            if id == *to {
+                return true;
+            }
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+            if let Some(target) = self.aliases.get_cloned(&id) {
+                stack.push((*target).clone());
+            }
+            if let Some(members) = self.groups.get_cloned(&id) {
+                stack.extend(members.iter().map(|member| (**member).clone()));
+            }
+        }
+        false
+    }
+}
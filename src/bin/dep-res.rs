@@ -0,0 +1,206 @@
+//! `dep-res resolve graph.json`, resolves a graph read from a file and prints its levels
+//!
+//! Graphs are either JSON, in the adjacency shape of [`DepRes::from_adjacency_json`], the shape
+//! [`DepRes`] serializes to or an array of [`Dep`] items, or one `id: dep1 dep2` line per node
+//! with `#` starting a comment
+//!
+//! Exits with 1 when the graph doesn't resolve, listing every problem found on stderr,
+//! and with 2 when the input can't be read or parsed
+
+use clap::{Parser, Subcommand, ValueEnum};
+use dep_res::{Dep, DepRes, ResolvedDeps};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, Read},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+#[derive(Parser)]
+#[command(name = "dep-res", version, about = "Resolves dependency graphs read from files")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Resolves a graph and prints the result
+    Resolve {
+        /// Graph file, `-` reads stdin
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Levels)]
+        format: Format,
+        #[arg(long, value_enum, default_value_t = Input::Auto)]
+        input: Input,
+        /// Only resolve these ids and what they transitively depend on
+        #[arg(long, value_delimiter = ',')]
+        targets: Option<Vec<String>>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// `level 0: a, b` lines
+    Levels,
+    /// One id per line, deps first
+    Topo,
+    /// Sorted levels as a JSON array of arrays
+    Json,
+    /// Graphviz DOT with one `rank=same` subgraph per level
+    Dot,
+    /// Mermaid flowchart
+    Mermaid,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Input {
+    /// JSON when the file starts with `{` or `[`, lines otherwise
+    Auto,
+    Json,
+    Lines,
+}
+
+fn main() -> ExitCode {
+    let Command::Resolve {
+        file,
+        format,
+        input,
+        targets,
+    } = Cli::parse().command;
+    let text = match read(&file) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("error: can't read {}: {e}", file.display());
+            return ExitCode::from(2);
+        }
+    };
+    let graph = match parse(&text, input) {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("error: can't parse {}: {e}", file.display());
+            return ExitCode::from(2);
+        }
+    };
+    let graph = match targets {
+        Some(targets) => {
+            let unknown = targets
+                .iter()
+                .filter(|id| !graph.contains(*id))
+                .collect::<Vec<_>>();
+            if !unknown.is_empty() {
+                eprintln!("error: unknown targets: {unknown:?}");
+                return ExitCode::from(1);
+            }
+            graph.subgraph(&targets)
+        }
+        None => graph,
+    };
+    match graph.resolve_with_report() {
+        Ok(resolved) => {
+            print!("{}", render(&graph, &resolved.into_deterministic(), format));
+            ExitCode::SUCCESS
+        }
+        Err(report) => {
+            eprintln!("error: {report}");
+            let mut problems = report
+                .problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<_>>();
+            problems.sort();
+            for problem in problems {
+                eprintln!("  {problem}");
+            }
+            ExitCode::from(1)
+        }
+    }
+}
+
+fn read(file: &PathBuf) -> io::Result<String> {
+    if file.as_os_str() == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        return Ok(text);
+    }
+    std::fs::read_to_string(file)
+}
+
+fn parse(text: &str, input: Input) -> Result<DepRes<String>, String> {
+    let json = match input {
+        Input::Auto => text.trim_start().starts_with(['{', '[']),
+        Input::Json => true,
+        Input::Lines => false,
+    };
+    if !json {
+        return parse_lines(text);
+    }
+    if text.trim_start().starts_with('[') {
+        let items = serde_json::from_str::<Vec<Dep<String>>>(text).map_err(|e| e.to_string())?;
+        let graph = DepRes::new();
+        graph.add(&items);
+        return Ok(graph);
+    }
+    let value = serde_json::from_str::<serde_json::Value>(text).map_err(|e| e.to_string())?;
+    if value.get("nodes").is_some() {
+        return DepRes::from_adjacency_json(text).map_err(|e| e.to_string());
+    }
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+fn parse_lines(text: &str) -> Result<DepRes<String>, String> {
+    let mut items = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (id, deps) = line.split_once(':').unwrap_or((line, ""));
+        let id = id.trim();
+        if id.is_empty() || id.contains(char::is_whitespace) {
+            return Err(format!("line {}: expected `id: dep1 dep2`, got `{line}`", i + 1));
+        }
+        items.push(Dep::with_deps(
+            id.to_string(),
+            deps.split_whitespace().map(str::to_string),
+        ));
+    }
+    let graph = DepRes::new();
+    graph.add(&items);
+    Ok(graph)
+}
+
+fn render(graph: &DepRes<String>, resolved: &ResolvedDeps<String>, format: Format) -> String {
+    match format {
+        Format::Levels => format!("{}\n", resolved.render().max_ids(usize::MAX)),
+        Format::Topo => resolved.iter_topo().map(|id| id + "\n").collect(),
+        Format::Json => serde_json::to_string(resolved).unwrap() + "\n",
+        Format::Dot => resolved.to_dot(graph),
+        Format::Mermaid => mermaid(graph, resolved),
+    }
+}
+
+/// Nodes are numbered in topological order and labeled with their id, edges point at deps
+/// like they do in the DOT output
+fn mermaid(graph: &DepRes<String>, resolved: &ResolvedDeps<String>) -> String {
+    let ids = resolved.iter_topo().collect::<Vec<_>>();
+    let index = ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (id, i))
+        .collect::<HashMap<_, _>>();
+    let mut out = String::from("flowchart TD\n");
+    for (i, id) in ids.iter().enumerate() {
+        writeln!(out, "    n{i}[\"{}\"]", id.replace('"', "#quot;")).unwrap();
+    }
+    let mut edges = graph
+        .edges()
+        .filter_map(|(id, dep)| Some((*index.get(&id)?, *index.get(&dep)?)))
+        .collect::<Vec<_>>();
+    edges.sort();
+    for (id, dep) in edges {
+        writeln!(out, "    n{id} --> n{dep}").unwrap();
+    }
+    out
+}
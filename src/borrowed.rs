@@ -0,0 +1,56 @@
+use crate::*;
+
+/// The key of `map` equal to `key`, found by its hash since the `Arc<Id>` keys only borrow as
+/// `Id` for a plain lookup
+pub(crate) fn find_key<
+    Id: Eq + Hash + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    V,
+    S: BuildHasher + Clone,
+>(
+    map: &Map<Arc<Id>, V, S>,
+    key: &Q,
+) -> Option<Arc<Id>> {
+    // `Id: Borrow<Q>` promises equal hashes, and the map hashes with its own hasher
+    let hash = map.hasher().hash_one(key);
+    map.read_hashed(
+        hash,
+        |k| Borrow::<Q>::borrow(&**k) == key,
+        |found, _| found.clone(),
+    )
+}
+
+/// `key` in `map` like [`find_key`]
+pub(crate) fn get_in<Id: Eq + Hash + Borrow<Q>, Q: Hash + Eq + ?Sized, V: Copy, S: BuildHasher>(
+    map: &hashbrown::HashMap<Arc<Id>, V, S>,
+    key: &Q,
+) -> Option<V> {
+    entry_in(map, key).map(|(_, &value)| value)
+}
+
+/// The stored key and value for `key` in `map`
+pub(crate) fn entry_in<'a, Id: Eq + Hash + Borrow<Q>, Q: Hash + Eq + ?Sized, V, S: BuildHasher>(
+    map: &'a hashbrown::HashMap<Arc<Id>, V, S>,
+    key: &Q,
+) -> Option<(&'a Arc<Id>, &'a V)> {
+    let hash = map.hasher().hash_one(key);
+    map.raw_entry()
+        .from_hash(hash, |k| Borrow::<Q>::borrow(&**k) == key)
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> DepRes<Id, S> {
+    /// The interned id `id` stands for, by a lookup that allocates nothing unless the
+    /// normalizer has to map an id it didn't see before
+    pub(crate) fn find<Q>(&self, id: &Q) -> Option<Arc<Id>>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        match &self.normalizer {
+            Some(normalizer) => {
+                find_key::<Id, Id, _, _>(&self.pool, &normalizer.canonical_borrowed(id))
+            }
+            None => find_key(&self.pool, id),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::*;
+use std::fmt::Display;
+
+/// Name of the aggregate target depending on every leaf, no node ever gets it
+const ALL: &str = "all";
+
+/// One target of a generated build file
+struct Target {
+    name: String,
+    /// The original id, debug quoted so control characters can't end the comment
+    comment: String,
+    prerequisites: Vec<String>,
+    /// `None` for aliases and groups, which only stand for their targets
+    command: Option<String>,
+}
+
+/// Keeps `[A-Za-z0-9_.+/-]` and turns everything else into `_`, those are plain in
+/// both Make and ninja. A leading `.` gets a `_` in front, Make takes names like `.PHONY`
+/// as special targets
+fn sanitize(id: &str) -> String {
+    let s = id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' | '+' | '/' | '-' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    match s.is_empty() || s.starts_with('.') {
+        true => format!("_{s}"),
+        false => s,
+    }
+}
+
+impl<
+        Id: Sync + Send + Eq + Hash + Clone + Display,
+        S: BuildHasher + Clone + Default + Send + Sync,
+    > DepRes<Id, S>
+{
+    /// Makefile with a target per node, its deps as prerequisites and `cmd` as the recipe,
+    /// `all` comes first and depends on every node nothing depends on
+    ///
+    /// Target names are the `Display` of the id with everything outside
+    /// `[A-Za-z0-9_.+/-]` replaced by `_` and a `_` in front of a leading `.`, a comment
+    /// above each target keeps the original. Names that end up equal get `_2`, `_3`, ... in
+    /// the order of the original ids. Every line of `cmd` becomes a recipe line with `$`
+    /// escaped, so it reaches the shell as written. Aliases and groups become phony targets,
+    /// missing deps get no target
+    pub fn to_makefile(&self, cmd: impl Fn(&Id) -> String) -> String {
+        let (targets, leaves) = self.build_targets(cmd);
+        let mut out = String::from("# generated by dep-res\n");
+        let mut phony = vec![ALL];
+        phony.extend(
+            targets
+                .iter()
+                .filter(|target| target.command.is_none())
+                .map(|target| target.name.as_str()),
+        );
+        out.push_str(&format!(".PHONY: {}\n", phony.join(" ")));
+        out.push_str(&format!("{ALL}:{}\n", prefixed(&leaves)));
+        for target in &targets {
+            out.push_str(&format!(
+                "\n# {}\n{}:{}\n",
+                target.comment,
+                target.name,
+                prefixed(&target.prerequisites)
+            ));
+            for line in target.command.iter().flat_map(|cmd| cmd.lines()) {
+                out.push_str(&format!("\t{}\n", line.replace('$', "$$")));
+            }
+        }
+        out
+    }
+
+    /// build.ninja with a rule and a build statement per node, its deps as inputs and
+    /// `cmd` as the command, the default is `all`, which depends on every node nothing
+    /// depends on
+    ///
+    /// Names work like in [`to_makefile`](Self::to_makefile). `$` in `cmd` is escaped and
+    /// its lines are joined with ` && ` since ninja commands are a single line
+    pub fn to_ninja(&self, cmd: impl Fn(&Id) -> String) -> String {
+        let (targets, leaves) = self.build_targets(cmd);
+        let mut out = String::from("# generated by dep-res\n");
+        for (i, target) in targets.iter().enumerate() {
+            out.push_str(&format!("\n# {}\n", target.comment));
+            let rule = match &target.command {
+                Some(cmd) => {
+                    let cmd = cmd.lines().collect::<Vec<_>>().join(" && ");
+                    out.push_str(&format!(
+                        "rule cmd{i}\n  command = {}\n",
+                        cmd.replace('$', "$$")
+                    ));
+                    format!("cmd{i}")
+                }
+                None => "phony".into(),
+            };
+            out.push_str(&format!(
+                "build {}: {rule}{}\n",
+                target.name,
+                prefixed(&target.prerequisites)
+            ));
+        }
+        out.push_str(&format!(
+            "\nbuild {ALL}: phony{}\ndefault {ALL}\n",
+            prefixed(&leaves)
+        ));
+        out
+    }
+
+    /// Targets sorted by name and the names of all leaves
+    fn build_targets(&self, cmd: impl Fn(&Id) -> String) -> (Vec<Target>, Vec<String>) {
+        let nodes = self.ids().collect::<Vec<_>>();
+        let edges = self.edges().collect::<Vec<_>>();
+        // everything that needs a name: nodes, then whatever their deps point at
+        let mut named = nodes.iter().cloned().collect::<HashSet<_, S>>();
+        let mut others = vec![];
+        for (_, dep) in &edges {
+            if named.insert(dep.clone()) {
+                others.push(dep.clone());
+            }
+        }
+        // aliases and groups get phony targets listing what they stand for, which may
+        // need further names
+        let mut stands_for = vec![];
+        let mut i = 0;
+        while let Some(dep) = others.get(i).cloned() {
+            i += 1;
+            let mut targets = vec![];
+            self.for_each_target(&dep, &mut |target| targets.push(target.clone()));
+            if targets.len() == 1 && targets[0] == dep {
+                continue;
+            }
+            for target in &targets {
+                if named.insert(target.clone()) {
+                    others.push(target.clone());
+                }
+            }
+            stands_for.push((dep, targets));
+        }
+
+        let mut by_text = named
+            .into_iter()
+            .map(|id| (id.to_string(), id))
+            .collect::<Vec<_>>();
+        by_text.sort_by(|a, b| a.0.cmp(&b.0));
+        let bases = by_text
+            .iter()
+            .map(|(text, _)| sanitize(text))
+            .collect::<HashSet<_>>();
+        let mut used = HashSet::from([ALL.to_string()]);
+        let mut names = HashMap::<Id, String, S>::with_hasher(self.hasher.clone());
+        for (text, id) in by_text {
+            let base = sanitize(&text);
+            let mut name = base.clone();
+            let mut n = 2;
+            while used.contains(&name) || (name != base && bases.contains(&name)) {
+                name = format!("{base}_{n}");
+                n += 1;
+            }
+            used.insert(name.clone());
+            names.insert(id, name);
+        }
+
+        let mut prerequisites = HashMap::<Id, Vec<String>, S>::with_hasher(self.hasher.clone());
+        let mut depended_on = HashSet::<&Id, S>::with_hasher(self.hasher.clone());
+        for (id, dep) in &edges {
+            prerequisites
+                .entry(id.clone())
+                .or_default()
+                .push(names[dep].clone());
+            depended_on.insert(dep);
+        }
+        let mut leaves = nodes
+            .iter()
+            .filter(|id| !depended_on.contains(id))
+            .map(|id| names[id].clone())
+            .collect::<Vec<_>>();
+        leaves.sort();
+
+        let mut targets = nodes
+            .iter()
+            .map(|id| Target {
+                name: names[id].clone(),
+                comment: format!("{:?}", id.to_string()),
+                prerequisites: prerequisites.remove(id).unwrap_or_default(),
+                command: Some(cmd(id)),
+            })
+            .chain(stands_for.iter().map(|(id, stands_for)| Target {
+                name: names[id].clone(),
+                comment: format!("{:?}", id.to_string()),
+                prerequisites: stands_for.iter().map(|id| names[id].clone()).collect(),
+                command: None,
+            }))
+            .collect::<Vec<_>>();
+        for target in &mut targets {
+            target.prerequisites.sort();
+        }
+        targets.sort_by(|a, b| a.name.cmp(&b.name));
+        (targets, leaves)
+    }
+}
+
+/// ` a b c`, empty for no names
+fn prefixed(names: &[String]) -> String {
+    names.iter().map(|name| format!(" {name}")).collect()
+}
@@ -0,0 +1,189 @@
+use crate::*;
+use serde::Deserialize;
+
+/// How [`DepRes::from_cargo_metadata`] turns packages into nodes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoMetadataOptions {
+    /// Key nodes by package id instead of name, external packages of the same name but
+    /// different versions are one node otherwise
+    pub by_package_id: bool,
+    /// Also add everything the workspace members transitively depend on as nodes, instead
+    /// of only the edges between members
+    pub include_external: bool,
+    /// Dependency kinds that make an edge, `EdgeKind::Normal`, `Build` and `Dev` are the ones
+    /// cargo knows, edges are labeled with the kinds they were kept for
+    pub kinds: Vec<EdgeKind>,
+}
+
+/// Members by name, normal and build deps only, i.e. what building the workspace needs
+impl Default for CargoMetadataOptions {
+    fn default() -> Self {
+        Self {
+            by_package_id: false,
+            include_external: false,
+            kinds: vec![EdgeKind::Normal, EdgeKind::Build],
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CargoMetadataError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("workspace member {0} is not among the packages")]
+    UnknownMember(String),
+    #[error("including external packages needs the resolve graph, which --no-deps leaves out")]
+    MissingResolve,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    resolve: Option<Resolve>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    id: String,
+    name: String,
+    #[serde(default)]
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct Dependency {
+    name: String,
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Resolve {
+    nodes: Vec<ResolveNode>,
+}
+
+#[derive(Deserialize)]
+struct ResolveNode {
+    id: String,
+    #[serde(default)]
+    deps: Vec<NodeDep>,
+}
+
+#[derive(Deserialize)]
+struct NodeDep {
+    pkg: String,
+    /// Missing before cargo 1.41, the edge then counts as a normal one
+    #[serde(default)]
+    dep_kinds: Vec<DepKindInfo>,
+}
+
+#[derive(Deserialize)]
+struct DepKindInfo {
+    kind: Option<String>,
+}
+
+/// `null` is a normal dep, kinds cargo may add later are skipped
+fn edge_kind(kind: Option<&str>) -> Option<EdgeKind> {
+    match kind {
+        None => Some(EdgeKind::Normal),
+        Some("build") => Some(EdgeKind::Build),
+        Some("dev") => Some(EdgeKind::Dev),
+        Some(_) => None,
+    }
+}
+
+impl DepRes<String> {
+    /// Graph of the packages in `cargo metadata --format-version 1` output
+    ///
+    /// Every workspace member becomes a node and its deps on other members become edges, or
+    /// on any package with [`CargoMetadataOptions::include_external`]. Platform specific deps
+    /// count like the others. Without the resolve graph (`--no-deps`) deps are matched to
+    /// members by name
+    pub fn from_cargo_metadata(
+        json: &str,
+        options: &CargoMetadataOptions,
+    ) -> Result<Self, CargoMetadataError> {
+        let metadata: Metadata = serde_json::from_str(json)?;
+        let names = metadata
+            .packages
+            .iter()
+            .map(|package| (package.id.as_str(), package.name.as_str()))
+            .collect::<HashMap<_, _>>();
+        let key = |id: &str| match options.by_package_id {
+            true => id.to_string(),
+            false => names.get(id).copied().unwrap_or(id).to_string(),
+        };
+        for member in &metadata.workspace_members {
+            if !names.contains_key(member.as_str()) {
+                return Err(CargoMetadataError::UnknownMember(member.clone()));
+            }
+        }
+        let kept = |kind: Option<&str>| edge_kind(kind).filter(|kind| options.kinds.contains(kind));
+        let dr = DepRes::default();
+
+        let Some(resolve) = metadata.resolve else {
+            if options.include_external {
+                return Err(CargoMetadataError::MissingResolve);
+            }
+            let members = (metadata.packages.iter())
+                .filter(|package| metadata.workspace_members.contains(&package.id))
+                .map(|package| (package.name.as_str(), package.id.as_str()))
+                .collect::<HashMap<_, _>>();
+            for package in &metadata.packages {
+                if !members.contains_key(package.name.as_str()) {
+                    continue;
+                }
+                let id = key(&package.id);
+                dr.insert_id(&id);
+                for dependency in &package.dependencies {
+                    let (Some(dep), Some(kind)) = (
+                        members.get(dependency.name.as_str()),
+                        kept(dependency.kind.as_deref()),
+                    ) else {
+                        continue;
+                    };
+                    dr.add_edge_kind(id.clone(), key(dep), kind);
+                }
+            }
+            return Ok(dr);
+        };
+
+        let nodes = resolve
+            .nodes
+            .iter()
+            .map(|node| (node.id.as_str(), node))
+            .collect::<HashMap<_, _>>();
+        let members = metadata
+            .workspace_members
+            .iter()
+            .map(String::as_str)
+            .collect::<HashSet<_>>();
+        let mut queue = members.iter().copied().collect::<Vec<_>>();
+        let mut seen = members.clone();
+        while let Some(package) = queue.pop() {
+            let id = key(package);
+            dr.insert_id(&id);
+            let Some(node) = nodes.get(package) else {
+                continue;
+            };
+            for dep in &node.deps {
+                if !options.include_external && !members.contains(dep.pkg.as_str()) {
+                    continue;
+                }
+                let kinds = match dep.dep_kinds.is_empty() {
+                    true => kept(None).into_iter().collect(),
+                    false => (dep.dep_kinds.iter())
+                        .filter_map(|info| kept(info.kind.as_deref()))
+                        .collect::<Vec<_>>(),
+                };
+                for &kind in &kinds {
+                    dr.add_edge_kind(id.clone(), key(&dep.pkg), kind);
+                }
+                if !kinds.is_empty() && seen.insert(dep.pkg.as_str()) {
+                    queue.push(dep.pkg.as_str());
+                }
+            }
+        }
+        Ok(dr)
+    }
+}
@@ -0,0 +1,85 @@
+use crate::*;
+
+/// A way in which a [`ResolvedDeps`] is not a legal leveling of a graph, see [`check_resolution`]
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResolutionViolation<Id> {
+    #[error("levels are not contiguous, level {0} is missing")]
+    LevelGap(usize),
+    #[error("{id:?} is in both level {first} and level {second}")]
+    Duplicate { id: Id, first: usize, second: usize },
+    #[error("{id:?} at level {level} is not a node of the graph")]
+    Unknown { id: Id, level: usize },
+    #[error("{0:?} is not in any level")]
+    Missing(Id),
+    #[error("{id:?} at level {level} depends on {dep:?} which is at level {dep_level:?}")]
+    DepNotBefore {
+        id: Id,
+        level: usize,
+        dep: Id,
+        dep_level: Option<usize>,
+    },
+}
+
+/// Verifies `resolved` is a legal leveling of `graph`, returning the first violation found
+///
+/// That is, levels are contiguous starting at 0, though they may be empty like the ones pins
+/// leave, every node of the graph is in exactly one level, and every dep of a node sits at a
+/// strictly lower level than the node
+///
+/// Deps are followed through aliases and groups as [`DepRes::resolved_deps_of`] does, deps on
+/// no node are skipped and soft deps aren't checked since resolving may drop them
+pub fn check_resolution<
+    Id: Sync + Send + Eq + Hash + Clone,
+    S: BuildHasher + Clone + Default + Send + Sync,
+>(
+    graph: &DepRes<Id, S>,
+    resolved: &ResolvedDeps<Id, S>,
+) -> Result<(), ResolutionViolation<Id>> {
+    let mut levels = resolved.iter_level().collect::<Vec<_>>();
+    levels.sort_by_key(|l| l.level);
+    if let Some(i) = levels.iter().enumerate().position(|(i, l)| l.level != i) {
+        return Err(ResolutionViolation::LevelGap(i));
+    }
+
+    let mut level_of = HashMap::new();
+    for level in &levels {
+        for id in level.deps.iter().map(|id| &**id) {
+            if let Some(first) = level_of.insert(id.clone(), level.level) {
+                return Err(ResolutionViolation::Duplicate {
+                    id: id.clone(),
+                    first,
+                    second: level.level,
+                });
+            }
+            if !graph.contains(id) {
+                return Err(ResolutionViolation::Unknown {
+                    id: id.clone(),
+                    level: level.level,
+                });
+            }
+        }
+    }
+
+    if let Some(id) = graph.ids().find(|id| !level_of.contains_key(id)) {
+        return Err(ResolutionViolation::Missing(id));
+    }
+
+    for id in graph.ids() {
+        let level = level_of[&id];
+        for dep in graph.resolved_deps_of(&id) {
+            // a dep on no node is ignored by resolving too
+            let Some(&dep_level) = level_of.get(&dep) else {
+                continue;
+            };
+            if dep_level >= level {
+                return Err(ResolutionViolation::DepNotBefore {
+                    id,
+                    level,
+                    dep,
+                    dep_level: Some(dep_level),
+                });
+            }
+        }
+    }
+    Ok(())
+}
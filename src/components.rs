@@ -0,0 +1,156 @@
+use crate::*;
+use std::collections::BTreeMap;
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Weakly connected components: nodes joined by a hard or soft edge in either direction
+    /// end up together, isolated nodes on their own
+    ///
+    /// Deps on aliases and groups join what they stand for, deps on ids that are no node join
+    /// nothing. Components come in no particular order, but the same one for an unchanged graph,
+    /// see [`connected_components_sorted`](Self::connected_components_sorted)
+    pub fn connected_components(&self) -> Vec<Vec<Id>> {
+        self.components()
+            .into_iter()
+            .map(|component| component.into_iter().map(|id| (*id).clone()).collect())
+            .collect()
+    }
+
+    /// Resolves every weakly connected component on its own, components in parallel, so a
+    /// cycle or a missing dep only fails the component it is in
+    ///
+    /// Components come in the order of [`connected_components`](Self::connected_components).
+    /// Once all of them succeeded, [`ResolvedDeps::merge`] gives back the leveling of the
+    /// whole graph
+    pub fn resolve_per_component(&self) -> PerComponent<Id, S> {
+        self.components()
+            .into_par_iter()
+            .map(|component| {
+                let nodes = Set::with_capacity_and_hasher(component.len(), self.hasher.clone());
+                for id in &component {
+                    nodes.insert(id.clone());
+                }
+                let resolved = self.resolve_nodes(&nodes, false);
+                let ids = component.into_iter().map(|id| (*id).clone()).collect();
+                (ids, resolved)
+            })
+            .collect()
+    }
+
+    /// Union-find over the nodes in `ids` iteration order, a component's ids are in that order
+    /// too and components are ordered by their first id
+    pub(crate) fn components(&self) -> Vec<Vec<Arc<Id>>> {
+        let ids = self.ids.to_vec();
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (&**id, i))
+            .collect::<HashMap<_, _, S>>();
+        let mut sets = UnionFind::new(ids.len());
+        for adjacency in [&self.deps, &self.soft] {
+            adjacency.for_each(|id, deps| {
+                let Some(&i) = index.get(&**id) else {
+                    return;
+                };
+                deps.for_each(|dep| {
+                    self.for_each_target(dep, &mut |target| {
+                        if let Some(&j) = index.get(target) {
+                            sets.union(i, j);
+                        }
+                    });
+                });
+            });
+        }
+        // component of each root, numbered by first appearance
+        let mut slot = vec![usize::MAX; ids.len()];
+        let mut components = Vec::<Vec<Arc<Id>>>::new();
+        for (i, id) in ids.into_iter().enumerate() {
+            let root = sets.find(i);
+            if slot[root] == usize::MAX {
+                slot[root] = components.len();
+                components.push(vec![]);
+            }
+            components[slot[root]].push(id);
+        }
+        components
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone + Ord, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Like [`connected_components`](Self::connected_components), with the ids of each
+    /// component sorted and components ordered by their smallest id
+    pub fn connected_components_sorted(&self) -> Vec<Vec<Id>> {
+        let mut components = self.connected_components();
+        for component in &mut components {
+            component.sort();
+        }
+        components.sort_by(|a, b| a[0].cmp(&b[0]));
+        components
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    /// Stacks the levels of resolutions of disjoint parts of a graph, level `n` of the result
+    /// holds level `n` of every part
+    ///
+    /// Merging the results of [`DepRes::resolve_per_component`] gives what resolving the whole
+    /// graph would. Parts sharing ids would end up with those ids in several levels
+    pub fn merge(parts: impl IntoIterator<Item = Self>) -> Self {
+        let mut levels = BTreeMap::<usize, Arc<Vec<Arc<Id>>>>::new();
+        let mut isolated = vec![];
+        for part in parts {
+            for (lv, ids) in part.levels {
+                match levels.entry(lv) {
+                    std::collections::btree_map::Entry::Vacant(entry) => {
+                        entry.insert(ids);
+                    }
+                    std::collections::btree_map::Entry::Occupied(mut entry) => {
+                        Arc::make_mut(entry.get_mut()).extend(ids.iter().cloned());
+                    }
+                }
+            }
+            isolated.extend(part.isolated);
+        }
+        let mut merged = Self::new(levels);
+        merged.isolated = isolated;
+        merged
+    }
+}
+
+/// Disjoint sets with union by size and path halving, near O(α(n)) per operation
+struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+    }
+}
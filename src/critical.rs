@@ -0,0 +1,48 @@
+use crate::*;
+
+impl<Id: Sync + Send + Eq + Hash + Clone + Ord, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// A longest dependency chain, deps first, see [`critical_path_weighted`](Self::critical_path_weighted)
+    pub fn critical_path(&self) -> Result<Vec<Id>, DepResolveError<Id>> {
+        Ok(self.critical_path_weighted(|_| 1)?.0)
+    }
+
+    /// The dependency chain with the highest total `cost`, deps first, along with that total
+    ///
+    /// Runs once over the levels in O(V + E), ties go to the smallest id, both for where the
+    /// chain ends and for every hop along it. Hops follow deps the way resolving does, through
+    /// aliases and groups and along the soft deps it kept
+    pub fn critical_path_weighted(
+        &self,
+        cost: impl Fn(&Id) -> u64,
+    ) -> Result<(Vec<Id>, u64), DepResolveError<Id>> {
+        let (resolved, dropped) = self.resolve_nodes_with(&self.ids, Default::default(), |_| {})?;
+        // id -> (total of the heaviest chain ending at id, previous hop of that chain)
+        let mut best = HashMap::<Id, (u64, Option<Id>), S>::with_hasher(self.hasher.clone());
+        for level in resolved.iter_level() {
+            for id in level.deps.iter() {
+                let id = (**id).clone();
+                let prev = self
+                    .leveled_deps(&id, &dropped)
+                    .into_iter()
+                    .filter_map(|dep| best.get(&dep).map(|&(total, _)| (total, dep)))
+                    .max_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.cmp(&a.1)));
+                let total = cost(&id).saturating_add(prev.as_ref().map_or(0, |p| p.0));
+                best.insert(id, (total, prev.map(|p| p.1)));
+            }
+        }
+        let last = best
+            .iter()
+            .max_by(|a, b| (a.1).0.cmp(&(b.1).0).then_with(|| b.0.cmp(a.0)));
+        let Some((last, &(total, _))) = last else {
+            return Ok((vec![], 0));
+        };
+        let mut path = vec![last.clone()];
+        while let Some((_, Some(prev))) = best.get(path.last().unwrap()) {
+            path.push(prev.clone());
+        }
+        path.reverse();
+        Ok((path, total))
+    }
+}
@@ -0,0 +1,157 @@
+use crate::*;
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::io;
+use std::str::FromStr;
+
+/// Why [`ResolvedDeps::from_csv`] rejected its input, lines count from 1
+#[derive(Error, Debug)]
+pub enum CsvError<Id, E> {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The row isn't two fields, or its quoting is broken
+    #[error("line {line}: {message}")]
+    Malformed { line: usize, message: String },
+    #[error("line {line}: level {level:?} is no non-negative integer")]
+    Level { line: usize, level: String },
+    #[error("line {line}: the id doesn't parse: {error}")]
+    Id { line: usize, error: E },
+    #[error("line {line}: {id:?} is listed again, first on line {first}")]
+    Duplicate { id: Id, line: usize, first: usize },
+    /// Levels have to be `0..=max` without holes
+    #[error("no row has level {0} while higher levels do")]
+    LevelGap(usize),
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    /// Writes a `level,id` header and then one such row per id, levels ascending and ids
+    /// sorted inside each
+    ///
+    /// Ids are written through `Display` and quoted when they contain commas, quotes or line
+    /// breaks. Separated isolated nodes are not written
+    pub fn to_csv<W: io::Write>(&self, mut w: W) -> io::Result<()>
+    where
+        Id: Display + Ord,
+    {
+        writeln!(w, "level,id")?;
+        for level in self.iter_level_shared() {
+            let mut ids = level.deps.iter().collect::<Vec<_>>();
+            ids.sort_unstable();
+            for id in ids {
+                writeln!(w, "{},{}", level.level, quote(&id.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads what [`to_csv`](Self::to_csv) writes, ids parsed through `FromStr`
+    ///
+    /// The header is optional and rows may come in any order, ids keep the order of their
+    /// rows inside a level. Both `\n` and `\r\n` end rows, blank lines are skipped. Fails on
+    /// the first malformed row, level or id, on an id listed twice and on levels with holes
+    pub fn from_csv<R: io::Read>(mut r: R) -> Result<Self, CsvError<Id, Id::Err>>
+    where
+        Id: FromStr,
+    {
+        let mut text = String::new();
+        r.read_to_string(&mut text)?;
+        let records = records(&text).map_err(|(line, message)| CsvError::Malformed {
+            line,
+            message: message.to_string(),
+        })?;
+        let mut first_line = HashMap::<Id, usize>::new();
+        let mut levels = BTreeMap::<usize, Vec<Arc<Id>>>::new();
+        for (i, (line, fields)) in records.into_iter().enumerate() {
+            if fields == [""] || (i == 0 && fields == ["level", "id"]) {
+                continue;
+            }
+            let [level, id] = <[String; 2]>::try_from(fields).map_err(|fields| {
+                let message = format!("expected 2 fields, found {}", fields.len());
+                CsvError::Malformed { line, message }
+            })?;
+            let Ok(lv) = level.parse::<usize>() else {
+                return Err(CsvError::Level { line, level });
+            };
+            let id = id
+                .parse::<Id>()
+                .map_err(|error| CsvError::Id { line, error })?;
+            if let Some(&first) = first_line.get(&id) {
+                return Err(CsvError::Duplicate { id, line, first });
+            }
+            first_line.insert(id.clone(), line);
+            levels.entry(lv).or_default().push(Arc::new(id));
+        }
+        if let Some((missing, _)) = (0..).zip(levels.keys()).find(|(i, lv)| i != *lv) {
+            return Err(CsvError::LevelGap(missing));
+        }
+        Ok(Self::new(
+            (levels.into_iter())
+                .map(|(lv, ids)| (lv, Arc::new(ids)))
+                .collect(),
+        ))
+    }
+}
+
+/// `field` as is, or in quotes with its quotes doubled if it needs them
+fn quote(field: &str) -> Cow<'_, str> {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => Cow::Owned(format!("\"{}\"", field.replace('"', "\"\""))),
+        false => Cow::Borrowed(field),
+    }
+}
+
+/// The line a row starts on with its fields
+type Record = (usize, Vec<String>);
+
+/// Every row, quoted fields may span lines
+fn records(text: &str) -> Result<Vec<Record>, (usize, &'static str)> {
+    let mut records = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while chars.peek().is_some() {
+        let start = line;
+        let mut fields = vec![];
+        loop {
+            let mut field = String::new();
+            if chars.next_if_eq(&'"').is_some() {
+                loop {
+                    match chars.next() {
+                        None => return Err((start, "a quoted field is never closed")),
+                        Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                        Some('"') => break,
+                        Some(c) => {
+                            line += usize::from(c == '\n');
+                            field.push(c);
+                        }
+                    }
+                }
+                if chars.peek().is_some_and(|c| ![',', '\n', '\r'].contains(c)) {
+                    return Err((line, "text after the closing quote of a field"));
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| ![',', '\n', '\r'].contains(c)) {
+                    if c == '"' {
+                        return Err((line, "a quote inside an unquoted field"));
+                    }
+                    field.push(c);
+                }
+            }
+            fields.push(field);
+            match chars.next() {
+                Some(',') => {}
+                Some('\r') => {
+                    chars.next_if_eq(&'\n');
+                    line += 1;
+                    break;
+                }
+                Some(_) => {
+                    line += 1;
+                    break;
+                }
+                None => break,
+            }
+        }
+        records.push((start, fields));
+    }
+    Ok(records)
+}
@@ -0,0 +1,44 @@
+use crate::*;
+
+/// A ready-made item, an id and its deps
+///
+/// With `serde` it (de)serializes as `{"id": ..., "deps": [...]}`, `deps` may be left out
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dep<Id> {
+    pub id: Id,
+    #[cfg_attr(feature = "serde", serde(default = "Vec::new"))]
+    pub deps: Vec<Id>,
+}
+
+impl<Id> Dep<Id> {
+    /// An item without deps
+    pub fn new(id: Id) -> Self {
+        Self { id, deps: vec![] }
+    }
+
+    pub fn with_deps(id: Id, deps: impl IntoIterator<Item = Id>) -> Self {
+        Self {
+            id,
+            deps: deps.into_iter().collect(),
+        }
+    }
+}
+
+impl<Id> From<(Id, Vec<Id>)> for Dep<Id> {
+    fn from((id, deps): (Id, Vec<Id>)) -> Self {
+        Self { id, deps }
+    }
+}
+
+impl<Id: Eq + Hash + Clone> DepMeta for Dep<Id> {
+    type Id = Id;
+
+    fn get_id(&self) -> Self::Id {
+        self.id.clone()
+    }
+
+    fn get_deps(&self) -> &[Self::Id] {
+        &self.deps
+    }
+}
@@ -0,0 +1,129 @@
+use crate::*;
+
+/// Structural changes going from one graph to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DepResDiff<Id> {
+    pub added_nodes: Vec<Id>,
+    pub removed_nodes: Vec<Id>,
+    pub added_edges: Vec<(Id, Id)>,
+    pub removed_edges: Vec<(Id, Id)>,
+}
+
+impl<Id> DepResDiff<Id> {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// What changed from `self` (before) to `other` (after)
+    ///
+    /// Nodes whose deps changed show up only through the edge lists
+    pub fn diff(&self, other: &DepRes<Id, S>) -> DepResDiff<Id> {
+        DepResDiff {
+            added_nodes: missing_nodes(other, self),
+            removed_nodes: missing_nodes(self, other),
+            added_edges: missing_edges(other, self),
+            removed_edges: missing_edges(self, other),
+        }
+    }
+}
+
+/// How ids moved going from one resolution to another, see [`ResolvedDeps::diff`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResolutionDiff<Id> {
+    pub added: Vec<Id>,
+    pub removed: Vec<Id>,
+    /// `(id, old level, new level)`
+    pub moved: Vec<(Id, usize, usize)>,
+}
+
+impl<Id> ResolutionDiff<Id> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+impl<Id: Ord> ResolutionDiff<Id> {
+    /// Sorts every list by id, making the diff independent of the order of the levels
+    pub fn into_deterministic(mut self) -> Self {
+        self.added.sort();
+        self.removed.sort();
+        self.moved.sort();
+        self
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// Which ids are new, gone or at another level going from `self` (before) to `other` (after)
+    ///
+    /// Every list is in the level order of the resolution its ids come from, so diffing two
+    /// deterministic resolutions gives the same result on every run
+    pub fn diff(&self, other: &ResolvedDeps<Id, S>) -> ResolutionDiff<Id> {
+        let (before, after) = (self.level_map(), other.level_map());
+        let mut removed = vec![];
+        let mut moved = vec![];
+        for level in self.iter_level_shared() {
+            for id in level.deps {
+                match after.get(&**id) {
+                    None => removed.push((**id).clone()),
+                    Some(&lv) if lv != level.level => moved.push(((**id).clone(), level.level, lv)),
+                    Some(_) => {}
+                }
+            }
+        }
+        let added = other
+            .iter_level_shared()
+            .flat_map(|level| level.deps)
+            .filter(|id| !before.contains_key(&***id))
+            .map(|id| (**id).clone())
+            .collect();
+        ResolutionDiff {
+            added,
+            removed,
+            moved,
+        }
+    }
+}
+
+/// Nodes of `a` that `b` doesn't have
+fn missing_nodes<
+    Id: Sync + Send + Eq + Hash + Clone,
+    S: BuildHasher + Clone + Default + Send + Sync,
+>(
+    a: &DepRes<Id, S>,
+    b: &DepRes<Id, S>,
+) -> Vec<Id> {
+    (a.ids.to_vec().into_par_iter())
+        .filter(|id| !b.ids.contains(id))
+        .map(|id| (*id).clone())
+        .collect()
+}
+
+/// Edges of `a` that `b` doesn't have
+fn missing_edges<
+    Id: Sync + Send + Eq + Hash + Clone,
+    S: BuildHasher + Clone + Default + Send + Sync,
+>(
+    a: &DepRes<Id, S>,
+    b: &DepRes<Id, S>,
+) -> Vec<(Id, Id)> {
+    (a.deps.keys().into_par_iter())
+        .flat_map_iter(|id| {
+            let mut deps = (a.deps.read(&id, |_, deps| deps.to_vec())).unwrap_or_default();
+            b.deps
+                .read(&id, |_, other| deps.retain(|dep| !other.contains(dep)));
+            (deps.into_iter())
+                .map(|dep| ((*id).clone(), (*dep).clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
@@ -0,0 +1,68 @@
+use crate::*;
+use std::fmt::{self, Display, Formatter};
+
+/// Human readable rendering of a [`ResolvedDeps`], see [`ResolvedDeps::render`]
+#[derive(Debug, Clone, Copy)]
+pub struct Render<'a, Id: Eq + Hash + Clone, S: BuildHasher + Clone = RandomState> {
+    resolved: &'a ResolvedDeps<Id, S>,
+    max_ids: usize,
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> Render<'_, Id, S> {
+    /// How many ids to print per level before cutting off with `… (+N more)`
+    pub fn max_ids(mut self, max_ids: usize) -> Self {
+        self.max_ids = max_ids;
+        self
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> ResolvedDeps<Id, S> {
+    /// One line per level in ascending order, e.g. `level 0: a, c, d`, with ids sorted inside a level
+    ///
+    /// The alternate flag (`{:#}`) prints one id per line under each level header instead
+    pub fn render(&self) -> Render<'_, Id, S> {
+        Render {
+            resolved: self,
+            max_ids: 32,
+        }
+    }
+}
+
+impl<Id: Eq + Hash + Clone + Ord + Display, S: BuildHasher + Clone> Display for Render<'_, Id, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (lv, level)) in self.resolved.levels.iter().enumerate() {
+            let mut ids = level.iter().map(|id| &**id).collect::<Vec<_>>();
+            ids.sort();
+            let more = ids.len().saturating_sub(self.max_ids);
+            ids.truncate(self.max_ids);
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "level {lv}:")?;
+            if f.alternate() {
+                for id in &ids {
+                    write!(f, "\n  {id}")?;
+                }
+                if more > 0 {
+                    write!(f, "\n  … (+{more} more)")?;
+                }
+            } else {
+                for (i, id) in ids.iter().enumerate() {
+                    write!(f, "{} {id}", if i == 0 { "" } else { "," })?;
+                }
+                if more > 0 {
+                    write!(f, " … (+{more} more)")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<Id: Eq + Hash + Clone + Ord + Display, S: BuildHasher + Clone> Display
+    for ResolvedDeps<Id, S>
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.render(), f)
+    }
+}
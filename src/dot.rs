@@ -0,0 +1,112 @@
+use crate::*;
+use std::fmt::{Display, Write};
+
+/// Which way edges point in DOT output
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeDirection {
+    /// `dependent -> dependency`, exactly like the stored deps
+    #[default]
+    ToDependency,
+    /// `dependency -> dependent`, i.e. the order things get built in
+    ToDependent,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct DotOptions {
+    pub direction: EdgeDirection,
+}
+
+/// Quotes `id` as a DOT string
+fn quote(id: &impl Display) -> String {
+    let mut s = String::from("\"");
+    for c in id.to_string().chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => {}
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+impl<
+        Id: Sync + Send + Eq + Hash + Clone + Display,
+        S: BuildHasher + Clone + Default + Send + Sync,
+    > DepRes<Id, S>
+{
+    /// Graphviz DOT of the graph, labels come from `Display`
+    ///
+    /// Lines are sorted so the output doesn't depend on the iteration order of the storage
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::default())
+    }
+
+    pub fn to_dot_with(&self, options: &DotOptions) -> String {
+        graph_dot(self.ids(), self.edges(), options)
+    }
+}
+
+/// DOT of a graph given its nodes and `(node, dep)` edges, lines sorted
+pub(crate) fn graph_dot<Id: Display>(
+    ids: impl Iterator<Item = Id>,
+    edges: impl Iterator<Item = (Id, Id)>,
+    options: &DotOptions,
+) -> String {
+    let mut nodes = ids
+        .map(|id| format!("    {};\n", quote(&id)))
+        .collect::<Vec<_>>();
+    nodes.sort();
+    let mut out = String::from("digraph {\n");
+    out.extend(nodes);
+    write_dot_edges(&mut out, edges, options);
+    out.push_str("}\n");
+    out
+}
+
+fn write_dot_edges<Id: Display>(
+    out: &mut String,
+    edges: impl Iterator<Item = (Id, Id)>,
+    options: &DotOptions,
+) {
+    let mut edges = edges
+        .map(|(id, dep)| {
+            let (from, to) = match options.direction {
+                EdgeDirection::ToDependency => (id, dep),
+                EdgeDirection::ToDependent => (dep, id),
+            };
+            format!("    {} -> {};\n", quote(&from), quote(&to))
+        })
+        .collect::<Vec<_>>();
+    edges.sort();
+    out.extend(edges);
+}
+
+impl<
+        Id: Sync + Send + Eq + Hash + Clone + Display,
+        S: BuildHasher + Clone + Default + Send + Sync,
+    > ResolvedDeps<Id, S>
+{
+    /// Like [`DepRes::to_dot`], with the nodes of each level grouped into a `rank=same` subgraph
+    pub fn to_dot(&self, graph: &DepRes<Id, S>) -> String {
+        self.to_dot_with(graph, &DotOptions::default())
+    }
+
+    pub fn to_dot_with(&self, graph: &DepRes<Id, S>, options: &DotOptions) -> String {
+        let mut out = String::from("digraph {\n");
+        for level in self.levels.values() {
+            let mut ids = level.iter().map(|id| quote(&**id)).collect::<Vec<_>>();
+            ids.sort();
+            write!(out, "    {{ rank=same; ").unwrap();
+            for id in ids {
+                write!(out, "{id}; ").unwrap();
+            }
+            writeln!(out, "}}").unwrap();
+        }
+        write_dot_edges(&mut out, graph.edges(), options);
+        out.push_str("}\n");
+        out
+    }
+}
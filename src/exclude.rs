@@ -0,0 +1,78 @@
+use crate::*;
+
+/// What [`DepRes::resolve_excluding`] could still schedule, and what it had to leave out
+#[derive(Debug, Clone)]
+pub struct ExcludedResolution<Id: Eq + Hash + Clone, S: BuildHasher + Clone = RandomState> {
+    /// Levels of the nodes left
+    pub resolved: ResolvedDeps<Id, S>,
+    /// Every node left out with an excluded node it transitively depends on, one of the
+    /// nearest ones, excluded nodes map to themselves
+    pub skipped: HashMap<Id, Id>,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Resolves the graph without the nodes in `excluded` and everything that transitively
+    /// depends on one of them
+    ///
+    /// Deps count the way resolving sees them, a dep on an alias or a group of an excluded
+    /// node excludes the dependent too, soft deps don't. Returns
+    /// [`DepResolveError::UnknownTargets`] if some of `excluded` are no nodes
+    pub fn resolve_excluding(
+        &self,
+        excluded: &HashSet<Id>,
+    ) -> Result<ExcludedResolution<Id, S>, DepResolveError<Id>> {
+        let excluded = excluded
+            .iter()
+            .map(|id| self.canon(id).into_owned())
+            .collect::<Vec<_>>();
+        let unknown = (excluded.iter())
+            .filter(|id| !self.ids.contains(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            return Err(DepResolveError::UnknownTargets(unknown));
+        }
+
+        // target -> dependents, like the reverse index with aliases and groups followed
+        let rev = Map::<Arc<Id>, Vec<Arc<Id>>, S>::with_hasher(self.hasher.clone());
+        self.deps.par_for_each(|id, deps| {
+            deps.for_each(|dep| {
+                self.for_each_target(dep, &mut |target| {
+                    rev.upsert(self.shared(target), vec![id.clone()], |dependents, id| {
+                        dependents.extend(id)
+                    });
+                });
+            });
+        });
+
+        // breadth first from all excluded nodes at once, so each node gets a nearest cause
+        let mut skipped = HashMap::new();
+        let mut queue = VecDeque::new();
+        for id in excluded {
+            skipped.insert(id.clone(), id.clone());
+            queue.push_back(id);
+        }
+        while let Some(id) = queue.pop_front() {
+            let cause = skipped[&id].clone();
+            for dependent in rev.get_cloned(&id).into_iter().flatten() {
+                if !skipped.contains_key(&*dependent) && self.ids.contains(&dependent) {
+                    skipped.insert((*dependent).clone(), cause.clone());
+                    queue.push_back((*dependent).clone());
+                }
+            }
+        }
+
+        let nodes = Set::with_hasher(self.hasher.clone());
+        self.ids.par_for_each(|id| {
+            if !skipped.contains_key(&**id) {
+                nodes.insert(id.clone());
+            }
+        });
+        Ok(ExcludedResolution {
+            resolved: self.resolve_nodes(&nodes, false)?,
+            skipped,
+        })
+    }
+}
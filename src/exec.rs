@@ -0,0 +1,49 @@
+use crate::*;
+use std::convert::Infallible;
+
+/// Nodes that failed in [`ResolvedDeps::for_each_level`], later levels were not started
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{} node(s) failed at level {level}", .failures.len())]
+pub struct LevelError<Id, E> {
+    /// The level that was running when the failures happened
+    pub level: usize,
+    /// Every failed id of that level with its error, in no particular order
+    pub failures: Vec<(Id, E)>,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// Runs `f` on every id, level by level in ascending order with the ids of a level in parallel
+    ///
+    /// A level only starts once the previous one fully completed. If any id of a level fails
+    /// the rest of that level still runs, then this stops and reports all failures of the level
+    pub fn for_each_level<E: Send>(
+        &self,
+        f: impl Fn(&Id) -> Result<(), E> + Sync,
+    ) -> Result<(), LevelError<Id, E>> {
+        for level in self.iter_level() {
+            let failures = level
+                .deps
+                .par_iter()
+                .filter_map(|id| f(id).err().map(|e| ((**id).clone(), e)))
+                .collect::<Vec<_>>();
+            if !failures.is_empty() {
+                return Err(LevelError {
+                    level: level.level,
+                    failures,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Infallible [`for_each_level`](Self::for_each_level)
+    pub fn for_each_level_ok(&self, f: impl Fn(&Id) + Sync) {
+        // nothing can fail, so every level runs
+        let _ = self.for_each_level(|id| {
+            f(id);
+            Ok::<(), Infallible>(())
+        });
+    }
+}
@@ -0,0 +1,73 @@
+use crate::*;
+use std::cmp::Reverse;
+use std::fmt::{self, Display, Formatter};
+
+/// Why a node sits at its level, see [`ResolvedDeps::explain`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Explanation<Id> {
+    /// Level of the node explained
+    pub level: usize,
+    /// Ids with their levels from a root up to the node explained, each depending on the one
+    /// before it
+    pub chain: Vec<(Id, usize)>,
+}
+
+/// One line, e.g. `root(0) -> a(1) -> b(2)`
+impl<Id: Display> Display for Explanation<Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, (id, level)) in self.chain.iter().enumerate() {
+            write!(f, "{}{id}({level})", if i == 0 { "" } else { " -> " })?;
+        }
+        Ok(())
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone + Ord, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// The level of `id` with a longest chain of deps in `graph` leading up to it, `None` if
+    /// `id` is not part of this resolution
+    ///
+    /// Walks down from `id`, each time to the dep at the highest level below, so the chain
+    /// is what keeps `id` from an earlier level. Without pins it starts at level 0 and has a
+    /// node at every level up to `id`. Deps count the way resolving sees them, aliases and
+    /// groups followed, soft deps too unless dropped for a cycle. Ties go to the smallest id
+    pub fn explain(&self, graph: &DepRes<Id, S>, id: &Id) -> Option<Explanation<Id>> {
+        let levels = self.level_map();
+        let (id, &level) = levels.get_key_value(&*normalize::canon(&self.normalizer, id))?;
+        let mut chain = vec![(id.clone(), level)];
+        loop {
+            let (at, at_level) = chain.last().unwrap();
+            let mut deps = vec![];
+            for adjacency in [&graph.deps, &graph.soft] {
+                if let Some(set) = adjacency.read(at, |_, set| set.to_vec()) {
+                    deps.extend(set);
+                }
+            }
+            let mut next = None::<(&Arc<Id>, usize)>;
+            for dep in deps {
+                graph.for_each_target(&dep, &mut |target| {
+                    let Some((target, &level)) = levels.get_key_value(target) else {
+                        return;
+                    };
+                    let better = next.is_none_or(|(best, best_level)| {
+                        (level, Reverse(&**target)) > (best_level, Reverse(&**best))
+                    });
+                    if level < *at_level && better {
+                        next = Some((target, level));
+                    }
+                });
+            }
+            match next {
+                Some((dep, level)) => chain.push((dep.clone(), level)),
+                None => break,
+            }
+        }
+        Some(Explanation {
+            level,
+            chain: (chain.into_iter().rev())
+                .map(|(id, level)| ((*id).clone(), level))
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,60 @@
+use crate::*;
+
+/// One-shot resolving straight from an iterator of items, e.g.
+/// `packages.iter().resolve_deps()`
+pub trait ResolveExt: Sized {
+    type Id: Sync + Send + Eq + Hash + Clone;
+
+    /// A graph of all items, for when it's needed for queries afterwards
+    fn collect_dep_res(self) -> DepRes<Self::Id>;
+
+    fn resolve_deps(self) -> Result<ResolvedDeps<Self::Id>, DepResolveError<Self::Id>> {
+        self.collect_dep_res().resolve()
+    }
+}
+
+impl<I: IntoIterator> ResolveExt for I
+where
+    I::Item: DepMeta,
+    <I::Item as DepMeta>::Id: Sync + Send,
+{
+    type Id = <I::Item as DepMeta>::Id;
+
+    fn collect_dep_res(self) -> DepRes<Self::Id> {
+        let dr = DepRes::new();
+        dr.add_iter(self);
+        dr
+    }
+}
+
+/// [`ResolveExt`] for rayon's parallel iterators, items are added in parallel
+#[cfg(parallel)]
+pub trait ParResolveExt: Sized {
+    type Id: Sync + Send + Eq + Hash + Clone;
+
+    fn collect_dep_res(self) -> DepRes<Self::Id>;
+
+    fn resolve_deps(self) -> Result<ResolvedDeps<Self::Id>, DepResolveError<Self::Id>> {
+        self.collect_dep_res().resolve()
+    }
+}
+
+#[cfg(parallel)]
+impl<I: ParallelIterator> ParResolveExt for I
+where
+    I::Item: DepMeta,
+    <I::Item as DepMeta>::Id: Sync + Send,
+{
+    type Id = <I::Item as DepMeta>::Id;
+
+    fn collect_dep_res(self) -> DepRes<Self::Id> {
+        let dr = DepRes::new();
+        let span = crate::trace::AddSpan::enter();
+        self.for_each(|item| {
+            span.count(&item);
+            dr.add_item(item);
+        });
+        dr.touch();
+        dr
+    }
+}
@@ -0,0 +1,83 @@
+use crate::*;
+use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+
+/// Tags keeping the kinds of entries from hashing alike, e.g. an edge `(a, b)` and an
+/// alias `a -> b`
+#[derive(Hash)]
+enum Entry {
+    Node,
+    Edge,
+    SoftEdge,
+    Alias,
+    GroupMember,
+    Pin,
+    Placed,
+    Graph,
+    Resolution,
+}
+
+/// Hash of one entry, with fixed keys so it is the same in every process
+fn entry_hash(value: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Sums entry hashes so the order they are visited in doesn't matter
+#[derive(Default)]
+struct Fingerprint {
+    sum: u64,
+    count: u64,
+}
+
+impl Fingerprint {
+    fn add(&mut self, value: impl Hash) {
+        self.sum = self.sum.wrapping_add(entry_hash(value));
+        self.count += 1;
+    }
+
+    fn finish(self, kind: Entry) -> u64 {
+        entry_hash((kind, self.sum, self.count))
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Content hash over the nodes, hard and soft edges, aliases, groups and pins, i.e.
+    /// everything a resolve looks at, independent of insertion order and threading
+    ///
+    /// Equal graphs hash equal within a build of the crate, the value is not meant to be
+    /// persisted across Rust versions. This is no cryptographic commitment, collisions can
+    /// be constructed
+    pub fn fingerprint(&self) -> u64 {
+        let mut fp = Fingerprint::default();
+        self.ids.for_each(|id| fp.add((Entry::Node, &**id)));
+        for (adjacency, kind) in [(&self.deps, Entry::Edge), (&self.soft, Entry::SoftEdge)] {
+            adjacency.for_each(|id, deps| deps.for_each(|dep| fp.add((&kind, &**id, &**dep))));
+        }
+        (self.aliases).for_each(|alias, target| fp.add((Entry::Alias, &**alias, &**target)));
+        self.groups.for_each(|group, members| {
+            for member in members.iter() {
+                fp.add((Entry::GroupMember, &**group, &**member));
+            }
+        });
+        self.pins
+            .for_each(|id, &level| fp.add((Entry::Pin, &**id, level)));
+        fp.finish(Entry::Graph)
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    /// Content hash over the `(id, level)` pairs, independent of the order inside a level,
+    /// see [`DepRes::fingerprint`] for its guarantees
+    pub fn fingerprint(&self) -> u64 {
+        let mut fp = Fingerprint::default();
+        for level in self.iter_level_shared() {
+            for id in level.deps {
+                fp.add((Entry::Placed, &**id, level.level));
+            }
+        }
+        fp.finish(Entry::Resolution)
+    }
+}
@@ -0,0 +1,59 @@
+use crate::*;
+
+/// Ids whose deps [`DepRes::try_from_fn`] couldn't look up
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("looking up the deps of {} id(s) failed", .failures.len())]
+pub struct FromFnError<Id, E> {
+    /// Every failed id with its error, in no particular order
+    pub failures: Vec<(Id, E)>,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone> DepRes<Id> {
+    /// A graph of `ids` with the deps `deps` gives for each, called once per id in parallel
+    ///
+    /// Deps outside of `ids` are missing deps like with [`add`](DepRes::add)
+    pub fn from_fn(
+        ids: impl IntoParallelIterator<Item = Id>,
+        deps: impl Fn(&Id) -> Vec<Id> + Sync,
+    ) -> Self {
+        let dr = Self::new();
+        let span = trace::AddSpan::enter();
+        ids.into_par_iter().for_each(|id| {
+            let item = Dep {
+                deps: deps(&id),
+                id,
+            };
+            span.count(&item);
+            dr.add_item(item);
+        });
+        dr.touch();
+        dr
+    }
+
+    /// Like [`from_fn`](DepRes::from_fn) with a lookup that can fail, every id is looked up
+    /// and all failures are reported together
+    pub fn try_from_fn<E: Send>(
+        ids: impl IntoParallelIterator<Item = Id>,
+        deps: impl Fn(&Id) -> Result<Vec<Id>, E> + Sync,
+    ) -> Result<Self, FromFnError<Id, E>> {
+        let dr = Self::new();
+        let span = trace::AddSpan::enter();
+        let failures = ids
+            .into_par_iter()
+            .filter_map(|id| match deps(&id) {
+                Ok(deps) => {
+                    let item = Dep { id, deps };
+                    span.count(&item);
+                    dr.add_item(item);
+                    None
+                }
+                Err(e) => Some((id, e)),
+            })
+            .collect::<Vec<_>>();
+        dr.touch();
+        match failures.is_empty() {
+            true => Ok(dr),
+            false => Err(FromFnError { failures }),
+        }
+    }
+}
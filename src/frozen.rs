@@ -0,0 +1,481 @@
+use crate::*;
+use std::fmt::Display;
+
+/// An immutable snapshot of a [`DepRes`], see [`DepRes::freeze`]
+///
+/// Adjacency is laid out as flat arrays over dense node indices, so resolving and querying
+/// take no locks and only hash the ids a query is given. Clones share the snapshot
+///
+/// Aliases and groups are followed once while freezing, later changes to the live graph
+/// don't show up here
+pub struct FrozenDepRes<Id, S = RandomState> {
+    inner: Arc<Frozen<Id, S>>,
+}
+
+impl<Id, S> Clone for FrozenDepRes<Id, S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct Frozen<Id, S> {
+    ids: Vec<Arc<Id>>,
+    /// hashbrown maps for the lookups by borrowed ids
+    index: hashbrown::HashMap<Arc<Id>, u32, S>,
+    /// Deps as they were added, deps on ids that are no node included
+    deps: Csr<Arc<Id>>,
+    /// Whether a node has hard deps at all, a node whose deps are all missing is no root
+    has_hard: Vec<bool>,
+    /// Hard deps with aliases and groups followed, like [`DepRes::resolved_deps_of`]
+    targets: Csr<Arc<Id>>,
+    /// The `targets` that are nodes
+    hard: Csr<u32>,
+    /// Soft deps on nodes, aliases and groups followed
+    soft: Csr<u32>,
+    /// dep -> its list in `rev`, deps that are no node included
+    rev_index: hashbrown::HashMap<Arc<Id>, u32, S>,
+    /// Nodes depending on a dep, also through an alias or group like
+    /// [`DepRes::dependents_of`]
+    rev: Csr<u32>,
+    pins: Option<Vec<usize>>,
+    max_levels: usize,
+    parallel_threshold: usize,
+    normalizer: Option<Arc<normalize::Normalizer<Id, S>>>,
+}
+
+/// Per node lists in one allocation, the list of `i` is `items[at[i]..at[i + 1]]`
+struct Csr<T> {
+    at: Vec<usize>,
+    items: Vec<T>,
+}
+
+impl<T> Csr<T> {
+    fn new(lists: impl Iterator<Item = Vec<T>>) -> Self {
+        let mut at = vec![0];
+        let mut items = vec![];
+        for list in lists {
+            items.extend(list);
+            at.push(items.len());
+        }
+        Self { at, items }
+    }
+
+    fn get(&self, i: usize) -> &[T] {
+        &self.items[self.at[i]..self.at[i + 1]]
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Snapshots the graph into a [`FrozenDepRes`], one pass over nodes and edges
+    ///
+    /// Safe to call while other threads add: a node becomes visible only once its deps are
+    /// linked, so every node of the snapshot comes with the deps it was added with. Nodes
+    /// added during the call may or may not make it in
+    pub fn freeze(&self) -> FrozenDepRes<Id, S> {
+        let ids = self.ids.to_vec();
+        let mut index =
+            hashbrown::HashMap::with_capacity_and_hasher(ids.len(), self.hasher.clone());
+        for (i, id) in ids.iter().enumerate() {
+            index.insert(id.clone(), i as u32);
+        }
+        let nodes = ids
+            .par_iter()
+            .map(|id| {
+                let deps = self.deps.read(id, |_, deps| deps.to_vec());
+                let mut targets = Vec::<Arc<Id>>::new();
+                for dep in deps.iter().flatten() {
+                    self.for_each_target(dep, &mut |target| {
+                        if !targets.iter().any(|t| **t == *target) {
+                            targets.push(self.shared(target));
+                        }
+                    });
+                }
+                let hard = targets.iter().filter_map(|t| index.get(t).copied());
+                let hard = hard.collect::<Vec<_>>();
+                let mut soft = vec![];
+                self.soft.read(id, |_, deps| {
+                    deps.for_each(|dep| {
+                        self.for_each_target(dep, &mut |t| soft.extend(index.get(t).copied()));
+                    })
+                });
+                (deps, targets, hard, soft)
+            })
+            .collect::<Vec<_>>();
+        let has_hard = nodes.iter().map(|(deps, ..)| deps.is_some()).collect();
+        // a dep lists its node under itself and under what it stands for, as the live reverse
+        // index does
+        let mut rev_index = hashbrown::HashMap::with_hasher(self.hasher.clone());
+        let mut rev = Vec::<Vec<u32>>::new();
+        for (i, (deps, targets, ..)) in nodes.iter().enumerate() {
+            for dep in deps.iter().flatten().chain(targets) {
+                let at = *rev_index.entry(dep.clone()).or_insert_with(|| {
+                    rev.push(vec![]);
+                    rev.len() as u32 - 1
+                });
+                let dependents = &mut rev[at as usize];
+                if dependents.last() != Some(&(i as u32)) {
+                    dependents.push(i as u32);
+                }
+            }
+        }
+        let pins = (!self.pins.is_empty()).then(|| {
+            ids.iter()
+                .map(|id| self.pins.get_cloned(id).unwrap_or(0))
+                .collect()
+        });
+        let mut deps = vec![];
+        let mut targets = vec![];
+        let mut hard = vec![];
+        let mut soft = vec![];
+        for (d, t, h, s) in nodes {
+            deps.push(d.unwrap_or_default());
+            targets.push(t);
+            hard.push(h);
+            soft.push(s);
+        }
+        FrozenDepRes {
+            inner: Arc::new(Frozen {
+                ids,
+                index,
+                deps: Csr::new(deps.into_iter()),
+                has_hard,
+                targets: Csr::new(targets.into_iter()),
+                hard: Csr::new(hard.into_iter()),
+                soft: Csr::new(soft.into_iter()),
+                rev_index,
+                rev: Csr::new(rev.into_iter()),
+                pins,
+                max_levels: self.max_levels(),
+                parallel_threshold: self.parallel_threshold(),
+                normalizer: self.normalizer.clone(),
+            }),
+        }
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    FrozenDepRes<Id, S>
+{
+    pub fn node_count(&self) -> usize {
+        self.inner.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.ids.is_empty()
+    }
+
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        self.node(id).is_some()
+    }
+
+    /// The stored key and value for `id` in `map`, through the normalizer if there is one
+    fn entry<'a, Q, V>(
+        &self,
+        map: &'a hashbrown::HashMap<Arc<Id>, V, S>,
+        id: &Q,
+    ) -> Option<(&'a Arc<Id>, &'a V)>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        match &self.inner.normalizer {
+            Some(normalizer) => {
+                borrowed::entry_in::<Id, Id, _, _>(map, &normalizer.canonical_borrowed(id))
+            }
+            None => borrowed::entry_in(map, id),
+        }
+    }
+
+    fn node<Q>(&self, id: &Q) -> Option<usize>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let (_, &i) = self.entry(&self.inner.index, id)?;
+        Some(i as usize)
+    }
+
+    /// The nodes depending on `id`, `id` need not be a node
+    fn dependents<Q>(&self, id: &Q) -> Option<(&Arc<Id>, &[u32])>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let (id, &at) = self.entry(&self.inner.rev_index, id)?;
+        Some((id, self.inner.rev.get(at as usize)))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.inner.ids.iter().map(|id| (**id).clone())
+    }
+
+    /// Number of distinct (node, dep) edges
+    pub fn edge_count(&self) -> usize {
+        self.inner.deps.items.len()
+    }
+
+    /// Every distinct `(node, dep)` edge
+    pub fn edges(&self) -> impl Iterator<Item = (Id, Id)> + '_ {
+        let f = &*self.inner;
+        (0..f.ids.len()).flat_map(move |i| {
+            f.deps
+                .get(i)
+                .iter()
+                .map(move |dep| ((*f.ids[i]).clone(), (**dep).clone()))
+        })
+    }
+
+    /// Direct deps of `id` as they were added, see [`DepRes::deps_of`]
+    pub fn deps_of<Q>(&self, id: &Q) -> Vec<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        self.list(id, &self.inner.deps)
+    }
+
+    /// Direct deps of `id` with aliases and groups followed, see [`DepRes::resolved_deps_of`]
+    pub fn resolved_deps_of<Q>(&self, id: &Q) -> Vec<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        self.list(id, &self.inner.targets)
+    }
+
+    fn list<Q>(&self, id: &Q, csr: &Csr<Arc<Id>>) -> Vec<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        match self.node(id) {
+            Some(i) => csr.get(i).iter().map(|a| (**a).clone()).collect(),
+            None => vec![],
+        }
+    }
+
+    /// See [`DepRes::dependents_of`], dependents through an alias or group count as well
+    pub fn dependents_of<Q>(&self, id: &Q) -> Vec<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let f = &*self.inner;
+        match self.dependents(id) {
+            Some((_, dependents)) => (dependents.iter())
+                .map(|&i| (*f.ids[i as usize]).clone())
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Everything that transitively depends on `id`, not including `id` itself
+    pub fn transitive_dependents<Q>(&self, id: &Q) -> HashSet<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let f = &*self.inner;
+        let Some((id, dependents)) = self.dependents(id) else {
+            return HashSet::new();
+        };
+        let mut seen = vec![false; f.ids.len()];
+        let mut queue = dependents.to_vec();
+        while let Some(i) = queue.pop() {
+            if std::mem::replace(&mut seen[i as usize], true) {
+                continue;
+            }
+            if let Some((_, next)) = self.dependents::<Id>(&f.ids[i as usize]) {
+                queue.extend(next);
+            }
+        }
+        (seen.iter().enumerate())
+            .filter(|&(i, &seen)| seen && f.ids[i] != *id)
+            .map(|(i, _)| (*f.ids[i]).clone())
+            .collect()
+    }
+
+    /// Everything `id` transitively depends on, see [`DepRes::transitive_deps`]
+    ///
+    /// Returns [`DepResolveError::IslandsOrCircular`] if the walk runs into a cycle
+    pub fn transitive_deps<Q>(&self, id: &Q) -> Result<HashSet<Id>, DepResolveError<Id>>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let f = &*self.inner;
+        let Some(start) = self.node(id) else {
+            return Ok(HashSet::new());
+        };
+        let mut found = HashSet::new();
+        let mut done = vec![false; f.ids.len()];
+        let mut on_path = vec![false; f.ids.len()];
+        on_path[start] = true;
+        // (node, position of the next dep to look at)
+        let mut stack = vec![(start, 0)];
+        while let Some(&(i, k)) = stack.last() {
+            let Some(dep) = f.targets.get(i).get(k) else {
+                stack.pop();
+                on_path[i] = false;
+                done[i] = true;
+                continue;
+            };
+            stack.last_mut().unwrap().1 += 1;
+            let Some(&j) = f.index.get(&**dep) else {
+                // deps on ids that are no node have none of their own
+                found.insert((**dep).clone());
+                continue;
+            };
+            let j = j as usize;
+            if on_path[j] {
+                return Err(DepResolveError::IslandsOrCircular);
+            }
+            if !done[j] {
+                on_path[j] = true;
+                stack.push((j, 0));
+            }
+        }
+        done[start] = false;
+        found.extend((0..f.ids.len()).filter(|&i| done[i]).map(|i| (*f.ids[i]).clone()));
+        Ok(found)
+    }
+
+    /// Whether `a` transitively depends on `b`, see [`DepRes::depends_on`]
+    pub fn depends_on<Q>(&self, a: &Q, b: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let f = &*self.inner;
+        // nothing depends on `b` unless it has dependents
+        let (Some(start), Some((b, _))) = (self.node(a), self.dependents(b)) else {
+            return false;
+        };
+        let mut seen = vec![false; f.ids.len()];
+        let mut queue = vec![start];
+        while let Some(i) = queue.pop() {
+            for dep in f.targets.get(i) {
+                if dep == b {
+                    return true;
+                }
+                if let Some(&j) = f.index.get(&**dep) {
+                    if !std::mem::replace(&mut seen[j as usize], true) {
+                        queue.push(j as usize);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// A shortest dependency chain `from -> .. -> to`, see [`DepRes::path_between`]
+    ///
+    /// When `from == to` this is the shortest cycle through `from`, and `None` if there is none
+    pub fn path_between<Q>(&self, from: &Q, to: &Q) -> Option<Vec<Id>>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let f = &*self.inner;
+        let (from, (to, _)) = (self.node(from)?, self.dependents(to)?);
+        let mut parents = HashMap::<usize, usize>::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(i) = queue.pop_front() {
+            for dep in f.targets.get(i) {
+                if dep == to {
+                    let mut path = vec![(**dep).clone()];
+                    let mut at = Some(i);
+                    while let Some(i) = at {
+                        path.push((*f.ids[i]).clone());
+                        at = parents.get(&i).copied();
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                let Some(&j) = f.index.get(&**dep) else {
+                    continue;
+                };
+                let j = j as usize;
+                if j != from && !parents.contains_key(&j) {
+                    parents.insert(j, i);
+                    queue.push_back(j);
+                }
+            }
+        }
+        None
+    }
+
+    /// Same leveling and errors as [`DepRes::resolve`] at the time of the snapshot
+    pub fn resolve(&self) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        self.resolve_with_dropped().map(|(resolved, _)| resolved)
+    }
+
+    /// Like [`DepRes::resolve_with_dropped`], soft edges closing a cycle get dropped
+    pub fn resolve_with_dropped(&self) -> Result<WithDropped<Id, S>, DepResolveError<Id>> {
+        let f = &*self.inner;
+        let resolve = |dropped: &HashSet<(u32, u32)>| {
+            let deps = (0..f.ids.len())
+                .into_par_iter()
+                .map(|i| {
+                    let mut inner = f.hard.get(i).to_vec();
+                    let soft = f.soft.get(i).iter().copied();
+                    inner.extend(soft.filter(|&dep| !dropped.contains(&(i as u32, dep))));
+                    (f.has_hard[i], inner)
+                })
+                .collect();
+            let interned =
+                interned::Interned::from_deps(f.ids.clone(), deps, false, f.pins.clone());
+            let opts = ResolveOpts::default();
+            let mut resolved =
+                interned.resolve(|_| {}, &opts, f.max_levels, f.parallel_threshold)?;
+            resolved.normalizer = f.normalizer.clone();
+            Ok(resolved)
+        };
+        match resolve(&HashSet::new()) {
+            Err(DepResolveError::IslandsOrCircular) if !f.soft.items.is_empty() => {
+                let fwd = (0..f.ids.len())
+                    .map(|i| {
+                        let hard = f.hard.get(i).iter().map(|&dep| (dep as usize, false));
+                        let soft = f.soft.get(i).iter().map(|&dep| (dep as usize, true));
+                        hard.chain(soft).collect()
+                    })
+                    .collect();
+                let dropped = soft::soft_cycle_drops(fwd);
+                if dropped.is_empty() {
+                    return Err(DepResolveError::IslandsOrCircular);
+                }
+                let skip = dropped.iter().map(|&(i, dep)| (i as u32, dep as u32));
+                let resolved = resolve(&skip.collect())?;
+                let dropped = dropped
+                    .into_iter()
+                    .map(|(i, dep)| ((*f.ids[i]).clone(), (*f.ids[dep]).clone()))
+                    .collect();
+                Ok((resolved, dropped))
+            }
+            r => r.map(|resolved| (resolved, vec![])),
+        }
+    }
+}
+
+impl<
+        Id: Sync + Send + Eq + Hash + Clone + Display,
+        S: BuildHasher + Clone + Default + Send + Sync,
+    > FrozenDepRes<Id, S>
+{
+    /// Same output as [`DepRes::to_dot`] at the time of the snapshot
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::default())
+    }
+
+    pub fn to_dot_with(&self, options: &DotOptions) -> String {
+        dot::graph_dot(self.ids(), self.edges(), options)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::*;
+
+/// Builds random graphs for benchmarks and negative tests, the same settings and seed always
+/// give the same graph
+///
+/// Without defects the graph is a DAG: nodes get a depth and only depend on nodes of lower
+/// depths, so it resolves into at most `max_depth` levels. Defects are added on top
+///
+/// ```ignore
+/// let dr = GraphGen::new(10_000).edge_density(0.01).max_depth(50).seed(42).build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct GraphGen {
+    nodes: u32,
+    edge_density: f64,
+    max_depth: Option<u32>,
+    seed: u64,
+    cycles: usize,
+    self_loops: usize,
+    missing_deps: usize,
+}
+
+impl GraphGen {
+    /// `nodes` nodes with ids `0..nodes`
+    pub fn new(nodes: u32) -> Self {
+        Self {
+            nodes,
+            edge_density: 0.01,
+            max_depth: None,
+            seed: 0,
+            cycles: 0,
+            self_loops: 0,
+            missing_deps: 0,
+        }
+    }
+
+    /// Chance of each node to depend on each node of a lower depth, 0.01 by default
+    pub fn edge_density(mut self, edge_density: f64) -> Self {
+        self.edge_density = edge_density.clamp(0.0, 1.0);
+        self
+    }
+
+    /// How many depths nodes are spread over, by default every node has its own
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth.max(1));
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Adds `cycles` rings of 2 to 4 random nodes, needs at least 2 nodes
+    pub fn cycles(mut self, cycles: usize) -> Self {
+        self.cycles = cycles;
+        self
+    }
+
+    /// Makes `self_loops` distinct random nodes depend on themselves
+    pub fn self_loops(mut self, self_loops: usize) -> Self {
+        self.self_loops = self_loops;
+        self
+    }
+
+    /// Adds `missing_deps` deps on ids past the last node, each to a random node
+    pub fn missing_deps(mut self, missing_deps: usize) -> Self {
+        self.missing_deps = missing_deps;
+        self
+    }
+
+    /// The graph as items, e.g. to benchmark [`DepRes::add`] itself
+    pub fn items(&self) -> Vec<Dep<u32>> {
+        assert!(
+            self.cycles == 0 || self.nodes >= 2,
+            "cycles need at least 2 nodes"
+        );
+        let n = self.nodes as usize;
+        let mut rng = Rng::new(self.seed);
+        let mut items = (0..self.nodes).map(Dep::new).collect::<Vec<_>>();
+
+        // by depth, then id, each node draws its deps from the prefix of lower depths
+        let depth = (0..n)
+            .map(|i| match self.max_depth {
+                Some(max) => rng.below(max as usize),
+                None => i,
+            })
+            .collect::<Vec<_>>();
+        let mut order = (0..n).collect::<Vec<_>>();
+        order.sort_by_key(|&i| (depth[i], i));
+        let mut lower = 0;
+        for p in 0..n {
+            if depth[order[p]] != depth[order[lower]] {
+                lower = p;
+            }
+            let expected = self.edge_density * lower as f64;
+            let k = (expected as usize + (rng.unit() < expected.fract()) as usize).min(lower);
+            items[order[p]].deps = rng
+                .distinct(lower, k)
+                .into_iter()
+                .map(|q| order[q] as u32)
+                .collect();
+        }
+
+        for i in rng.distinct(n, self.self_loops.min(n)) {
+            items[i].deps.push(i as u32);
+        }
+        for _ in 0..self.cycles {
+            let len = (2 + rng.below(3)).min(n);
+            let ring = rng.distinct(n, len);
+            for (j, &i) in ring.iter().enumerate() {
+                items[i].deps.push(ring[(j + 1) % ring.len()] as u32);
+            }
+        }
+        if n > 0 {
+            for m in 0..self.missing_deps {
+                let i = rng.below(n);
+                items[i].deps.push(self.nodes + m as u32);
+            }
+        }
+        items
+    }
+
+    pub fn build(&self) -> DepRes<u32> {
+        let dr = DepRes::new();
+        dr.add(&self.items());
+        dr
+    }
+}
+
+/// xorshift64, seeded through splitmix64 so nearby seeds give unrelated graphs
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((z ^ (z >> 31)).max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// In `[0, 1)`
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `k` distinct values below `n` in a deterministic order, Floyd's algorithm
+    fn distinct(&mut self, n: usize, k: usize) -> Vec<usize> {
+        let mut picked = Vec::with_capacity(k);
+        let mut seen = HashSet::with_capacity(k);
+        for j in n - k..n {
+            let t = self.below(j + 1);
+            let v = if seen.insert(t) { t } else { j };
+            seen.insert(v);
+            picked.push(v);
+        }
+        picked
+    }
+}
@@ -0,0 +1,66 @@
+use crate::*;
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Makes a dep on `group` count as deps on all of `members` when resolving, the group
+    /// itself never shows up in a resolution
+    ///
+    /// Members may be groups or aliases themselves and are expanded transitively, calling
+    /// this again adds to the members. As with aliases a node named `group` wins over the
+    /// group, and an alias of the same id wins over the group. Returns
+    /// [`DepResolveError::GroupCycle`] if a member would expand back to `group`
+    pub fn define_group(
+        &self,
+        group: Id,
+        members: impl IntoIterator<Item = Id>,
+    ) -> Result<(), DepResolveError<Id>> {
+        let group = self.normalize(&group);
+        let members = members
+            .into_iter()
+            .map(|member| self.normalize(&member))
+            .collect::<Vec<_>>();
+        if members.iter().any(|member| self.expands_to(member, &group)) {
+            return Err(DepResolveError::GroupCycle(group));
+        }
+        let group = self.intern(&group);
+        let mut known = (self.groups)
+            .read(&group, |_, members| members.as_ref().clone())
+            .unwrap_or_default();
+        for member in members {
+            if !known.iter().any(|known| **known == member) {
+                known.push(self.intern(&member));
+            }
+        }
+        self.groups.insert(group, Arc::new(known));
+        self.touch();
+        Ok(())
+    }
+
+    /// Direct members of `group` in the order they were added, empty if it's no group
+    pub fn group_members(&self, group: &Id) -> Vec<Id> {
+        match self.groups.get_cloned(&*self.canon(group)) {
+            Some(members) => members.iter().map(|member| (**member).clone()).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Drops `group`, returns whether it was one
+    pub fn remove_group(&self, group: &Id) -> bool {
+        let group = &*self.canon(group);
+        let Some((key, shared)) = self.groups.remove(group) else {
+            return false;
+        };
+        let members = shared
+            .iter()
+            .map(|member| (**member).clone())
+            .collect::<Vec<_>>();
+        drop((key, shared));
+        self.release(group);
+        for member in &members {
+            self.release(member);
+        }
+        self.touch();
+        true
+    }
+}
@@ -0,0 +1,208 @@
+use crate::*;
+
+/// Returned by [`DepRes::try_add_edge`] for an edge that would close a cycle
+#[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
+#[error("the edge would close the cycle {path:?}")]
+pub struct CycleError<Id> {
+    /// `[id, dep, .., id]`, each id depending on the next one, `[id, id]` for a self loop
+    pub path: Vec<Id>,
+}
+
+/// A topological order of every id with deps or dependents, kept valid across
+/// [`DepRes::try_add_edge`] calls with the Pearce–Kelly algorithm
+#[derive(Debug)]
+pub(crate) struct TopoOrder<Id, S> {
+    /// Graph generation the order is valid for
+    generation: u64,
+    /// Deps sort below their dependents, values don't need to be contiguous
+    ord: HashMap<Arc<Id>, i64, S>,
+    /// dep -> dependents, kept up to date here since the reverse index is rebuilt per generation
+    rev: HashMap<Arc<Id>, Vec<Arc<Id>>, S>,
+    min: i64,
+    max: i64,
+}
+
+impl<Id: Eq + Hash, S: BuildHasher + Clone> TopoOrder<Id, S> {
+    /// Kahn's algorithm over all deps, `None` if they already contain a cycle
+    fn build(graph: &DepRes<Id, S>, generation: u64) -> Option<Self>
+    where
+        Id: Clone,
+    {
+        let mut rev = HashMap::<Arc<Id>, Vec<Arc<Id>>, S>::with_hasher(graph.hasher.clone());
+        let mut pending = HashMap::<Arc<Id>, usize, S>::with_hasher(graph.hasher.clone());
+        graph.deps.for_each(|id, deps| {
+            *pending.entry(id.clone()).or_default() += deps.len();
+            deps.for_each(|dep| {
+                pending.entry(dep.clone()).or_default();
+                rev.entry(dep.clone()).or_default().push(id.clone());
+            });
+        });
+        let mut queue = (pending.iter())
+            .filter(|(_, &n)| n == 0)
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        let mut ord = HashMap::with_capacity_and_hasher(pending.len(), graph.hasher.clone());
+        while let Some(id) = queue.pop() {
+            for dependent in rev.get(&id).into_iter().flatten() {
+                let n = pending.get_mut(dependent).unwrap();
+                *n -= 1;
+                if *n == 0 {
+                    queue.push(dependent.clone());
+                }
+            }
+            ord.insert(id, ord.len() as i64);
+        }
+        (ord.len() == pending.len()).then(|| Self {
+            generation,
+            max: ord.len() as i64 - 1,
+            min: 0,
+            ord,
+            rev,
+        })
+    }
+
+    /// Makes room for `id -> dep` in the order, or returns the cycle it would close
+    ///
+    /// Only ids ordered between the two ends are searched: forward from `id` over dependents
+    /// for `dep`, backward from `dep` over deps. The ids found swap their order slots so the
+    /// backward ones come first
+    fn check(&mut self, graph: &DepRes<Id, S>, id: &Id, dep: &Id) -> Result<(), Vec<Id>>
+    where
+        Id: Clone,
+    {
+        let (Some((id, &lb)), Some((dep, &ub))) =
+            (self.ord.get_key_value(id), self.ord.get_key_value(dep))
+        else {
+            // an id nothing depends on or a dep without deps closes no cycle
+            return Ok(());
+        };
+        if ub < lb {
+            return Ok(());
+        }
+        let (id, dep) = (id.clone(), dep.clone());
+
+        let mut parent = HashMap::<Arc<Id>, Arc<Id>>::new();
+        let mut forward = vec![id.clone()];
+        let mut stack = vec![id.clone()];
+        while let Some(u) = stack.pop() {
+            for w in self.rev.get(&u).into_iter().flatten() {
+                if *w == dep {
+                    let mut path = vec![(*id).clone(), (*dep).clone()];
+                    let mut at = &u;
+                    path.push((**at).clone());
+                    while let Some(p) = parent.get(at) {
+                        path.push((**p).clone());
+                        at = p;
+                    }
+                    return Err(path);
+                }
+                if self.ord[w] < ub && *w != id && !parent.contains_key(w) {
+                    parent.insert(w.clone(), u.clone());
+                    forward.push(w.clone());
+                    stack.push(w.clone());
+                }
+            }
+        }
+
+        let mut seen = HashSet::<Arc<Id>>::from([dep.clone()]);
+        let mut backward = vec![dep.clone()];
+        let mut stack = vec![dep];
+        while let Some(u) = stack.pop() {
+            let Some(deps) = graph.deps.read(&u, |_, deps| deps.to_vec()) else {
+                continue;
+            };
+            for d in deps {
+                if self.ord.get(&d).is_some_and(|&o| o > lb) && seen.insert(d.clone()) {
+                    backward.push(d.clone());
+                    stack.push(d);
+                }
+            }
+        }
+
+        backward.sort_by_key(|n| self.ord[n]);
+        forward.sort_by_key(|n| self.ord[n]);
+        let mut slots = (backward.iter().chain(&forward))
+            .map(|n| self.ord[n])
+            .collect::<Vec<_>>();
+        slots.sort_unstable();
+        for (n, slot) in backward.into_iter().chain(forward).zip(slots) {
+            self.ord.insert(n, slot);
+        }
+        Ok(())
+    }
+
+    /// Records the accepted `id -> dep`, ids new to the order go to its ends
+    fn link(&mut self, id: Arc<Id>, dep: Arc<Id>) {
+        if !self.ord.contains_key(&dep) {
+            self.min -= 1;
+            self.ord.insert(dep.clone(), self.min);
+        }
+        if !self.ord.contains_key(&id) {
+            self.max += 1;
+            self.ord.insert(id.clone(), self.max);
+        }
+        self.rev.entry(dep).or_default().push(id);
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Adds `id` as a node depending on `dep` unless `dep` already transitively depends on
+    /// `id`, then the graph is left as is and the error holds the cycle the edge would close
+    ///
+    /// A topological order is kept between calls and only the part of it between `dep` and
+    /// `id` gets searched and reordered, so growing a graph edge by edge stays cheap. Any
+    /// other change to the graph makes the next call rebuild the order in O(V + E), and while
+    /// the deps already contain a cycle every call searches them in full. Only deps count,
+    /// the way they were added and without following aliases or groups
+    ///
+    /// [`add`](Self::add) stays unchecked for bulk ingestion, edges added concurrently by it
+    /// can slip past the check
+    pub fn try_add_edge(&self, id: Id, dep: Id) -> Result<(), CycleError<Id>> {
+        let (id, dep) = (self.normalize(&id), self.normalize(&dep));
+        if id == dep {
+            return Err(CycleError {
+                path: vec![id.clone(), id],
+            });
+        }
+        if self.ids.contains(&id)
+            && self.deps.read(&id, |_, deps| deps.contains(&dep)) == Some(true)
+        {
+            return Ok(());
+        }
+        let mut order = self.order.lock().unwrap();
+        let generation = self.generation.load(Ordering::Acquire);
+        if order
+            .as_ref()
+            .is_none_or(|order| order.generation != generation)
+        {
+            *order = TopoOrder::build(self, generation);
+        }
+        match order.as_mut() {
+            Some(order) => order.check(self, &id, &dep),
+            None => match self.path_between_ids(&dep, &id, |id| self.direct_deps(id)) {
+                Some(path) => Err([id.clone()].into_iter().chain(path).collect()),
+                None => Ok(()),
+            },
+        }
+        .map_err(|path| CycleError { path })?;
+
+        let (shared, shared_dep) = (self.intern(&id), self.intern(&dep));
+        self.link(&self.deps, &shared, std::slice::from_ref(&dep));
+        if !self.kinds.is_empty() {
+            self.label_normal(&shared, std::slice::from_ref(&dep));
+        }
+        self.ids.insert(shared.clone());
+        self.touch();
+        if let Some(valid) = order.as_mut() {
+            valid.link(shared, shared_dep);
+            // some other change came in meanwhile, the order can't tell what it was
+            valid.generation = generation + 1;
+            if self.generation.load(Ordering::Acquire) != valid.generation {
+                *order = None;
+            }
+        }
+        Ok(())
+    }
+}
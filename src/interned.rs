@@ -0,0 +1,307 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+
+/// Nodes of a wide level handled between two checks of the cancel flag
+const CANCEL_CHECK: usize = 4096;
+
+/// A node set snapshotted with its ids interned to dense `u32` indices, so leveling
+/// runs over plain vectors instead of hashing and cloning ids at every level
+pub(crate) struct Interned<Id> {
+    ids: Vec<Arc<Id>>,
+    roots: Vec<u32>,
+    /// Number of deps inside the set not placed yet, per node
+    pending: Vec<AtomicU32>,
+    /// dep -> dependents restricted to nodes of the set, the dependents of `i` are
+    /// `rev[rev_at[i]..rev_at[i + 1]]`
+    rev_at: Vec<usize>,
+    rev: Vec<u32>,
+    /// Pinned minimum level per node, `None` if the graph has no pins
+    pins: Option<Vec<usize>>,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone> Interned<Id> {
+    /// Same root rule as [`DepRes::resolve_nodes`], other nodes whose deps all point
+    /// outside the set never become ready and end up reported as islands
+    ///
+    /// Soft deps inside the set count like hard ones, except for the `(node, dep)` edges in
+    /// `dropped`. Hard deps not kept by `opts` don't exist for this, a node without any kept
+    /// hard dep counts as having none
+    pub fn new<S: BuildHasher + Clone + Default + Send + Sync>(
+        graph: &DepRes<Id, S>,
+        nodes: &Set<Arc<Id>, S>,
+        opts: &ResolveOpts<Id>,
+        dropped: &[(Arc<Id>, Arc<Id>)],
+    ) -> Self {
+        let inner_only = opts.inner_only;
+        let ids = nodes.to_vec();
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (&**id, i as u32))
+            .collect::<HashMap<_, _, S>>();
+        let dropped = dropped
+            .iter()
+            .map(|(id, dep)| (&**id, &**dep))
+            .collect::<HashSet<_>>();
+        // deps on aliases and groups count as deps on what they stand for
+        let lookup = |dep: &Id, inner: &mut Vec<u32>| match index.get(dep) {
+            Some(&i) => inner.push(i),
+            None => {
+                graph.for_each_target(dep, &mut |target| inner.extend(index.get(target).copied()))
+            }
+        };
+        let node_deps = |id: &Arc<Id>| {
+            let mut inner = vec![];
+            let has_hard = graph.deps.read(id, |_, deps| {
+                inner.reserve(deps.len());
+                let Some(keep) = opts.keep else {
+                    deps.for_each(|dep| lookup(dep, &mut inner));
+                    return true;
+                };
+                let mut has_hard = false;
+                deps.for_each(|dep| {
+                    if keep(id, dep) {
+                        has_hard = true;
+                        lookup(dep, &mut inner);
+                    }
+                });
+                has_hard
+            });
+            // dropped edges name what the soft dep stands for, like soft_cycle_edges sees it
+            graph.soft.read(id, |_, soft| {
+                soft.for_each(|dep| {
+                    graph.for_each_target(dep, &mut |target| {
+                        if !dropped.contains(&(&**id, target)) {
+                            inner.extend(index.get(target).copied())
+                        }
+                    })
+                })
+            });
+            (has_hard == Some(true), inner)
+        };
+        let deps = if ids.len() < graph.parallel_threshold() {
+            ids.iter().map(node_deps).collect::<Vec<_>>()
+        } else {
+            ids.par_iter().map(node_deps).collect::<Vec<_>>()
+        };
+        let pins = (!graph.pins.is_empty()).then(|| {
+            ids.iter()
+                .map(|id| graph.pins.get_cloned(id).unwrap_or(0))
+                .collect()
+        });
+        Self::from_deps(ids, deps, inner_only, pins)
+    }
+
+    /// From each node's `(has hard deps, deps inside the set)`, indices into `ids`
+    pub fn from_deps(
+        ids: Vec<Arc<Id>>,
+        deps: Vec<(bool, Vec<u32>)>,
+        inner_only: bool,
+        pins: Option<Vec<usize>>,
+    ) -> Self {
+        let mut roots = vec![];
+        let mut pending = Vec::with_capacity(ids.len());
+        let mut rev_at = vec![0; ids.len() + 1];
+        for (i, (has_hard, deps)) in deps.iter().enumerate() {
+            if deps.is_empty() && (!has_hard || inner_only) {
+                roots.push(i as u32);
+            }
+            pending.push(AtomicU32::new(deps.len() as u32));
+            for &dep in deps {
+                rev_at[dep as usize + 1] += 1;
+            }
+        }
+        for i in 1..rev_at.len() {
+            rev_at[i] += rev_at[i - 1];
+        }
+        let mut rev = vec![0; rev_at[ids.len()]];
+        let mut cursor = rev_at.clone();
+        for (i, (_, deps)) in deps.into_iter().enumerate() {
+            for dep in deps {
+                rev[cursor[dep as usize]] = i as u32;
+                cursor[dep as usize] += 1;
+            }
+        }
+        Self {
+            ids,
+            roots,
+            pending,
+            rev_at,
+            rev,
+            pins,
+        }
+    }
+
+    /// Levels the set into a [`ResolvedDeps`], see [`walk`](Self::walk) for the knobs
+    pub fn resolve<S: BuildHasher + Clone + Default>(
+        self,
+        progress: impl Fn(ResolveProgress),
+        opts: &ResolveOpts<Id>,
+        max_levels: usize,
+        parallel_threshold: usize,
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        let mut levels = BTreeMap::new();
+        let walked = self.walk(progress, opts, max_levels, parallel_threshold, |lv, ids| {
+            let ids = ids.iter().map(|&id| self.ids[id as usize].clone()).collect();
+            levels.insert(lv, Arc::new(ids));
+            ControlFlow::Continue(())
+        })?;
+        let ControlFlow::Continue(separate) = walked else {
+            unreachable!("collecting levels never breaks")
+        };
+        let mut resolved = ResolvedDeps::new(levels);
+        resolved.isolated = self.level_ids(&separate);
+        Ok(resolved)
+    }
+
+    /// The ids behind the indices `ids`
+    pub fn level_ids(&self, ids: &[u32]) -> Vec<Id> {
+        ids.iter().map(|&id| (*self.ids[id as usize]).clone()).collect()
+    }
+
+    /// Kahn's algorithm, level by level: placing a level counts down the pending deps of
+    /// its dependents, and the ones reaching zero form the next level, i.e. every node
+    /// lands at `max(dep level) + 1`
+    ///
+    /// Every placed level goes to `on_level` in ascending order, only the level at hand and
+    /// the next one are kept, and breaking stops right there. Returns the isolated nodes the
+    /// policy of `opts` separates
+    ///
+    /// `progress` runs on the calling thread, once upfront and once per placed level, not
+    /// counting isolated nodes set aside by the policy of `opts`. The cancel flag of `opts`
+    /// is checked before every level and every [`CANCEL_CHECK`] nodes within one, at most
+    /// `max_levels` levels are placed. Sets of fewer than `parallel_threshold` nodes never
+    /// fan out
+    pub fn walk(
+        &self,
+        progress: impl Fn(ResolveProgress),
+        opts: &ResolveOpts<Id>,
+        max_levels: usize,
+        parallel_threshold: usize,
+        mut on_level: impl FnMut(usize, &[u32]) -> ControlFlow<()>,
+    ) -> Result<ControlFlow<(), Vec<u32>>, DepResolveError<Id>> {
+        let cancelled = || {
+            opts.cancel
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+        };
+        // roots nobody in the set depends on
+        let (isolated, mut level) = match opts.isolated {
+            IsolatedPolicy::Level0 => (vec![], self.roots.clone()),
+            IsolatedPolicy::Separate | IsolatedPolicy::LastLevel => (self.roots.iter().copied())
+                .partition(|&i| self.rev_at[i as usize] == self.rev_at[i as usize + 1]),
+        };
+        let total = self.ids.len() - isolated.len();
+        progress(ResolveProgress {
+            level: None,
+            placed: 0,
+            remaining: total,
+        });
+        if self.ids.is_empty() {
+            return Ok(ControlFlow::Continue(vec![]));
+        }
+        if level.is_empty() && total > 0 {
+            return Err(trace::failed(DepResolveError::IslandsOrCircular, 0, total));
+        }
+        let ready = |id: &u32| {
+            let id = *id as usize;
+            self.rev[self.rev_at[id]..self.rev_at[id + 1]]
+                .iter()
+                .copied()
+                .filter(|&dependent| {
+                    self.pending[dependent as usize].fetch_sub(1, Ordering::Relaxed) == 1
+                })
+        };
+        // the level at hand and the one it readies, swapped and reused level after level
+        let mut next = vec![];
+        let (mut lv, mut placed) = (0, 0);
+        // ready nodes held back by their pin, by the level they were pinned to
+        let mut deferred = BTreeMap::<usize, Vec<u32>>::new();
+        self.hold_back(&mut level, 0, 0, &mut deferred);
+        while !level.is_empty() || !deferred.is_empty() {
+            if cancelled() {
+                return Err(trace::failed(DepResolveError::Cancelled, placed, total));
+            }
+            if lv == max_levels {
+                let e = DepResolveError::DepthLimitExceeded {
+                    limit: max_levels,
+                    placed,
+                };
+                return Err(trace::failed(e, placed, total));
+            }
+            if level.len() < PAR_FRONTIER || self.ids.len() < parallel_threshold {
+                for id in &level {
+                    next.extend(ready(id));
+                }
+            } else {
+                next.par_extend(
+                    level
+                        .par_chunks(CANCEL_CHECK)
+                        .filter(|_| !cancelled())
+                        .flat_map_iter(|chunk| chunk.iter().flat_map(ready)),
+                );
+            }
+            self.hold_back(&mut next, 0, lv + 1, &mut deferred);
+            placed += level.len();
+            #[cfg(feature = "tracing")]
+            tracing::trace!(level = lv, width = level.len(), "level placed");
+            progress(ResolveProgress {
+                level: Some(lv),
+                placed,
+                remaining: total - placed,
+            });
+            if on_level(lv, &level).is_break() {
+                return Ok(ControlFlow::Break(()));
+            }
+            level.clear();
+            std::mem::swap(&mut level, &mut next);
+            lv += 1;
+        }
+        if cancelled() {
+            return Err(trace::failed(DepResolveError::Cancelled, placed, total));
+        }
+        if placed != total {
+            let e = DepResolveError::IslandsOrCircular;
+            return Err(trace::failed(e, placed, total));
+        }
+        match opts.isolated {
+            IsolatedPolicy::Level0 => {}
+            IsolatedPolicy::Separate => return Ok(ControlFlow::Continue(isolated)),
+            IsolatedPolicy::LastLevel => {
+                if !isolated.is_empty() {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(level = lv, width = isolated.len(), "level placed");
+                    if on_level(lv, &isolated).is_break() {
+                        return Ok(ControlFlow::Break(()));
+                    }
+                }
+            }
+        }
+        Ok(ControlFlow::Continue(vec![]))
+    }
+
+    /// Moves the nodes of `order[from..]`, the level `lv` to be, that are pinned after `lv`
+    /// into `deferred`, and the ones pinned exactly to `lv` in
+    fn hold_back(
+        &self,
+        order: &mut Vec<u32>,
+        from: usize,
+        lv: usize,
+        deferred: &mut BTreeMap<usize, Vec<u32>>,
+    ) {
+        let Some(pins) = &self.pins else { return };
+        let mut kept = from;
+        for i in from..order.len() {
+            let id = order[i];
+            match pins[id as usize] {
+                pin if pin > lv => deferred.entry(pin).or_default().push(id),
+                _ => {
+                    order[kept] = id;
+                    kept += 1;
+                }
+            }
+        }
+        order.truncate(kept);
+        order.extend(deferred.remove(&lv).unwrap_or_default());
+    }
+}
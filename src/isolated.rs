@@ -0,0 +1,40 @@
+use crate::*;
+
+/// Where [`DepRes::resolve_isolated`] puts nodes that have neither deps nor dependents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IsolatedPolicy {
+    /// Into level 0 like every other node without deps, what [`DepRes::resolve`] does
+    #[default]
+    Level0,
+    /// Into no level, they are listed by [`ResolvedDeps::isolated`] instead
+    Separate,
+    /// Into a level of their own after all others
+    LastLevel,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Like [`resolve`](Self::resolve), placing isolated nodes according to `policy`
+    ///
+    /// A node is isolated if it has no deps in the graph and nothing in the graph depends on
+    /// it. The policy wins over pins of isolated nodes
+    pub fn resolve_isolated(
+        &self,
+        policy: IsolatedPolicy,
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        let opts = ResolveOpts {
+            isolated: policy,
+            ..ResolveOpts::default()
+        };
+        self.resolve_nodes_with(&self.ids, opts, |_| {})
+            .map(|(resolved, _)| resolved)
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    /// Isolated nodes left out of the levels by [`IsolatedPolicy::Separate`], empty otherwise
+    pub fn isolated(&self) -> &[Id] {
+        &self.isolated
+    }
+}
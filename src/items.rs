@@ -0,0 +1,109 @@
+use crate::*;
+use std::convert::Infallible;
+
+/// A resolution handing back the items themselves, see [`DepRes::resolve_items`]
+#[derive(Debug, Clone)]
+pub struct ResolvedItems<'a, T> {
+    levels: Vec<Vec<&'a T>>,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Like [`resolve`](Self::resolve), with every id swapped for its item out of `items`
+    ///
+    /// Every node needs an item and every item a node, otherwise this returns
+    /// [`DepResolveError::ItemsMismatch`] listing both sides. If several items share an id
+    /// the first one is used
+    pub fn resolve_items<'a, T: DepMeta<Id = Id>>(
+        &self,
+        items: &'a [T],
+    ) -> Result<ResolvedItems<'a, T>, DepResolveError<Id>> {
+        let mut by_id =
+            HashMap::<Id, &T, S>::with_capacity_and_hasher(items.len(), self.hasher.clone());
+        let mut unknown_items = vec![];
+        for item in items {
+            let id = item.get_id();
+            if !self.ids.contains(&id) {
+                unknown_items.push(id);
+                continue;
+            }
+            by_id.entry(id).or_insert(item);
+        }
+        let missing_items = self
+            .ids()
+            .filter(|id| !by_id.contains_key(id))
+            .collect::<Vec<_>>();
+        if !unknown_items.is_empty() || !missing_items.is_empty() {
+            return Err(DepResolveError::ItemsMismatch {
+                unknown_items,
+                missing_items,
+            });
+        }
+        let levels = self
+            .resolve_nodes(&self.ids, false)?
+            .to_levels()
+            .into_iter()
+            .map(|level| level.iter().map(|id| by_id[id]).collect())
+            .collect();
+        Ok(ResolvedItems { levels })
+    }
+}
+
+impl<'a, T> ResolvedItems<'a, T> {
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Number of items over all levels
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Levels in ascending order, items inside a level are unordered
+    pub fn iter_level(&self) -> impl Iterator<Item = DepLevel<&[&'a T]>> + '_ {
+        (self.levels.iter().enumerate()).map(|(level, items)| DepLevel {
+            level,
+            deps: &items[..],
+        })
+    }
+
+    pub fn sorted_by_level(&self) -> Vec<&'a T> {
+        self.levels.iter().flatten().copied().collect()
+    }
+}
+
+impl<'a, T: DepMeta + Sync> ResolvedItems<'a, T>
+where
+    T::Id: Send,
+{
+    /// Like [`ResolvedDeps::for_each_level`], handing `f` the items
+    pub fn for_each_level<E: Send>(
+        &self,
+        f: impl Fn(&T) -> Result<(), E> + Sync,
+    ) -> Result<(), LevelError<T::Id, E>> {
+        for (level, items) in self.levels.iter().enumerate() {
+            let failures = items
+                .par_iter()
+                .filter_map(|item| f(item).err().map(|e| (item.get_id(), e)))
+                .collect::<Vec<_>>();
+            if !failures.is_empty() {
+                return Err(LevelError { level, failures });
+            }
+        }
+        Ok(())
+    }
+
+    /// Infallible [`for_each_level`](Self::for_each_level)
+    pub fn for_each_level_ok(&self, f: impl Fn(&T) + Sync) {
+        // nothing can fail, so every level runs
+        let _ = self.for_each_level(|item| {
+            f(item);
+            Ok::<(), Infallible>(())
+        });
+    }
+}
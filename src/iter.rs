@@ -0,0 +1,154 @@
+use crate::*;
+
+/// Consuming iterator over the levels of a [`ResolvedDeps`] in ascending order
+#[derive(Debug)]
+pub struct IntoLevels<Id: Eq + Hash + Clone, S: BuildHasher + Clone = RandomState> {
+    levels: std::collections::btree_map::IntoIter<usize, Arc<Vec<Arc<Id>>>>,
+    hasher: std::marker::PhantomData<S>,
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> Iterator for IntoLevels<Id, S> {
+    type Item = DepLevel<Vec<Id>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (level, ids) = self.levels.next()?;
+        // ids are moved out unless the level or the id itself is still shared
+        let deps: Vec<Id> = match Arc::try_unwrap(ids) {
+            Ok(ids) => ids
+                .into_iter()
+                .map(|id| Arc::try_unwrap(id).unwrap_or_else(|id| (*id).clone()))
+                .collect(),
+            Err(ids) => ids.iter().map(|id| (**id).clone()).collect(),
+        };
+        Some(DepLevel { level, deps })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.levels.size_hint()
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> ExactSizeIterator for IntoLevels<Id, S> {}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> IntoIterator for ResolvedDeps<Id, S> {
+    type Item = DepLevel<Vec<Id>>;
+    type IntoIter = IntoLevels<Id, S>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoLevels {
+            levels: self.levels.into_iter(),
+            hasher: std::marker::PhantomData,
+        }
+    }
+}
+
+/// Borrowing iterator over the levels of a [`ResolvedDeps`] in ascending order, as slices
+/// like [`ResolvedDeps::iter_level`]
+#[derive(Debug)]
+pub struct Levels<'a, Id> {
+    levels: std::collections::btree_map::Iter<'a, usize, Arc<Vec<Arc<Id>>>>,
+}
+
+impl<'a, Id> Iterator for Levels<'a, Id> {
+    type Item = DepLevel<&'a [Arc<Id>]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&level, ids) = self.levels.next()?;
+        Some(DepLevel {
+            level,
+            deps: ids.as_slice(),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.levels.size_hint()
+    }
+}
+
+impl<Id> ExactSizeIterator for Levels<'_, Id> {}
+
+impl<'a, Id: Eq + Hash + Clone, S: BuildHasher + Clone> IntoIterator for &'a ResolvedDeps<Id, S> {
+    type Item = DepLevel<&'a [Arc<Id>]>;
+    type IntoIter = Levels<'a, Id>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Levels {
+            levels: self.levels.iter(),
+        }
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    /// Levels in ascending order, with every level wider than `max_chunk` split into consecutive chunks
+    ///
+    /// All chunks of a level carry that level's index and never mix in ids of another level.
+    /// In deterministic mode ids are sorted before chunking, so chunk membership is reproducible
+    ///
+    /// Panics if `max_chunk` is 0
+    pub fn chunked(&self, max_chunk: usize) -> impl Iterator<Item = DepLevel<Vec<Id>>> + '_ {
+        assert!(max_chunk > 0, "max_chunk must be at least 1");
+        self.iter_level_shared().flat_map(move |level| {
+            let mut ids = level.deps.iter().map(|id| (**id).clone()).peekable();
+            std::iter::from_fn(move || {
+                ids.peek()?;
+                Some(DepLevel {
+                    level: level.level,
+                    deps: ids.by_ref().take(max_chunk).collect(),
+                })
+            })
+        })
+    }
+}
+
+#[cfg(parallel)]
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// All levels in parallel, in no particular order
+    ///
+    /// Meant for read-only analysis only, levels run concurrently here so this must not be
+    /// used to execute nodes, use the ordered sequential iterators for that
+    pub fn par_iter_level(&self) -> impl ParallelIterator<Item = DepLevel<&[Arc<Id>]>> + '_ {
+        self.into_par_iter()
+    }
+}
+
+#[cfg(parallel)]
+type ParLevels<'a, Id> = rayon::iter::Map<
+    rayon::collections::btree_map::Iter<'a, usize, Arc<Vec<Arc<Id>>>>,
+    fn((&'a usize, &'a Arc<Vec<Arc<Id>>>)) -> DepLevel<&'a [Arc<Id>]>,
+>;
+
+#[cfg(parallel)]
+/// Same as [`ResolvedDeps::par_iter_level`], read-only analysis only
+impl<'a, Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    IntoParallelIterator for &'a ResolvedDeps<Id, S>
+{
+    type Iter = ParLevels<'a, Id>;
+    type Item = DepLevel<&'a [Arc<Id>]>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.levels.par_iter().map(|(&level, ids)| DepLevel {
+            level,
+            deps: ids.as_slice(),
+        })
+    }
+}
+
+#[cfg(parallel)]
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepLevel<Arc<LevelSet<Id, S>>>
+{
+    /// The ids of this level in parallel, they are independent of each other so this is safe to execute
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = Id> + '_ {
+        self.deps.par_iter().cloned()
+    }
+}
+
+#[cfg(parallel)]
+impl<Id: Sync + Send + Clone> DepLevel<&[Arc<Id>]> {
+    /// The ids of this level in parallel, they are independent of each other so this is safe to execute
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = Id> + '_ {
+        self.deps.par_iter().map(|id| (**id).clone())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::*;
+
+/// Label of a hard edge, see [`DepRes::add_edge_kind`]
+///
+/// A fixed set rather than a type parameter of [`DepRes`], `Custom` covers anything else
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeKind {
+    /// Edges from [`DepMeta::get_deps`]
+    #[default]
+    Normal,
+    Build,
+    Run,
+    Dev,
+    Custom(u32),
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Adds `id` as a node depending on `dep` through an edge of `kind`
+    ///
+    /// An edge can carry several kinds, deps added through [`add`](Self::add) are [`EdgeKind::Normal`]
+    pub fn add_edge_kind(&self, id: Id, dep: Id, kind: EdgeKind) {
+        let (id, dep) = (self.intern(&id), self.intern(&dep));
+        let known = self.deps.read(&id, |_, deps| deps.contains(&dep)) == Some(true);
+        let new = || Set::with_hasher(self.hasher.clone());
+        self.deps
+            .get_or_insert_with(id.clone(), new, |_, deps| deps.insert(dep.clone()));
+        let new = || HashMap::with_hasher(self.hasher.clone());
+        self.kinds.get_or_insert_with(id.clone(), new, |_, labels| {
+            let kinds = labels.entry(dep).or_insert_with(|| match known {
+                true => vec![EdgeKind::Normal],
+                false => vec![],
+            });
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        });
+        self.ids.insert(id);
+        self.touch();
+    }
+
+    /// Kinds of the edge `id -> dep`, empty if there is no such edge
+    pub fn edge_kinds(&self, id: &Id, dep: &Id) -> Vec<EdgeKind> {
+        let (id, dep) = (&*self.canon(id), &*self.canon(dep));
+        if let Some(kinds) = (self.kinds)
+            .read(id, |_, labels| labels.get(dep).cloned())
+            .flatten()
+        {
+            return kinds;
+        }
+        match self.deps.read(id, |_, deps| deps.contains(dep)) == Some(true) {
+            true => vec![EdgeKind::Normal],
+            false => vec![],
+        }
+    }
+
+    /// Like [`edges`](Self::edges), once per kind of every edge
+    pub fn edges_with_kind(&self) -> impl Iterator<Item = (Id, Id, EdgeKind)> + '_ {
+        self.edges().flat_map(move |(id, dep)| {
+            self.edge_kinds(&id, &dep)
+                .into_iter()
+                .map(move |kind| (id.clone(), dep.clone(), kind))
+        })
+    }
+
+    /// Like [`resolve`](Self::resolve), leveling by only the edges with a kind passing `keep`
+    ///
+    /// A node whose edges are all filtered out becomes a root
+    pub fn resolve_filtered(
+        &self,
+        keep: impl Fn(&EdgeKind) -> bool + Sync,
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        let keep_edge = |id: &Id, dep: &Id| {
+            let kept = self.kinds.read(id, |_, labels| {
+                (labels.get(dep)).map(|kinds| kinds.iter().any(&keep))
+            });
+            kept.flatten().unwrap_or_else(|| keep(&EdgeKind::Normal))
+        };
+        let opts = ResolveOpts {
+            keep: Some(&keep_edge),
+            ..ResolveOpts::default()
+        };
+        self.resolve_nodes_with(&self.ids, opts, |_| {})
+            .map(|(resolved, _)| resolved)
+    }
+
+    /// Adds [`EdgeKind::Normal`] to the labeled ones of the plain `id -> deps` edges
+    ///
+    /// `deps` are raw as the item gave them, labels are keyed by the canonical ids
+    pub(crate) fn label_normal(&self, id: &Id, deps: &[Id]) {
+        let deps = self.canon_all(deps);
+        self.kinds.modify(id, |_, labels| {
+            for dep in deps.iter() {
+                if let Some(kinds) = labels.get_mut(dep) {
+                    if !kinds.contains(&EdgeKind::Normal) {
+                        kinds.insert(0, EdgeKind::Normal);
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,65 @@
+use crate::*;
+use std::collections::hash_set;
+
+/// The ids of one level as a set, see [`ResolvedDeps::get_level`]
+///
+/// Built once per resolution on first use and read-only from then on
+#[derive(Debug, Clone)]
+pub struct LevelSet<Id, S = RandomState>(HashSet<Id, S>);
+
+impl<Id: Eq + Hash, S: BuildHasher> LevelSet<Id, S> {
+    pub(crate) fn new(ids: HashSet<Id, S>) -> Self {
+        Self(ids)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Takes any borrowed form of the ids, e.g. `&str` for `String` ids
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.0.contains(id)
+    }
+
+    /// The ids in no particular order
+    pub fn iter(&self) -> hash_set::Iter<'_, Id> {
+        self.0.iter()
+    }
+}
+
+impl<'a, Id, S> IntoIterator for &'a LevelSet<Id, S> {
+    type Item = &'a Id;
+    type IntoIter = hash_set::Iter<'a, Id>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// The ids in parallel, they are independent of each other
+#[cfg(parallel)]
+impl<'a, Id: Eq + Hash + Sync, S: BuildHasher + Sync> IntoParallelIterator for &'a LevelSet<Id, S> {
+    type Item = &'a Id;
+    type Iter = rayon::collections::hash_set::Iter<'a, Id>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.0.par_iter()
+    }
+}
+
+/// The same ids, whatever the hasher
+impl<Id: Eq + Hash, S: BuildHasher> PartialEq for LevelSet<Id, S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Id: Eq + Hash, S: BuildHasher> Eq for LevelSet<Id, S> {}
@@ -1,29 +1,179 @@
-use dashmap::{DashMap, DashSet};
-use rayon::{iter::Either, prelude::*};
 use std::{
-    hash::Hash,
-    ops::Deref,
+    borrow::Borrow,
+    collections::{hash_map::RandomState, BTreeMap, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+    ops::{Bound, Deref, Index, RangeBounds},
     rc::Rc,
-    sync::{atomic::AtomicBool, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, RwLock,
+    },
 };
 use thiserror::Error;
-use tuples::TupleCloned;
 
 #[cfg(test)]
+#[allow(clippy::println_empty_string, clippy::map_clone)]
 mod tests;
 
+mod par;
+use par::*;
+
+mod store;
+use store::{Map, Set, StoreMap, StoreSet};
+
+mod dep;
+pub use dep::Dep;
+
+mod diff;
+pub use diff::{DepResDiff, ResolutionDiff};
+
+mod dot;
+pub use dot::{DotOptions, EdgeDirection};
+
+mod display;
+pub use display::Render;
+
+mod iter;
+pub use iter::{IntoLevels, Levels};
+
+mod check;
+pub use check::{check_resolution, ResolutionViolation};
+
+mod interned;
+
+mod trace;
+
+mod critical;
+
+mod components;
+
+mod frozen;
+pub use frozen::FrozenDepRes;
+
+mod normalize;
+
+mod incremental;
+pub use incremental::CycleError;
+
+mod walk;
+pub use walk::{Walk, WalkControl};
+
+mod from_fn;
+pub use from_fn::FromFnError;
+
+mod borrowed;
+mod exclude;
+pub use exclude::ExcludedResolution;
+mod rebalance;
+mod explain;
+pub use explain::Explanation;
+mod csv;
+pub use csv::CsvError;
+mod level_set;
+pub use level_set::LevelSet;
+mod streaming;
+mod map_ids;
+pub use map_ids::MapIdsError;
+
+mod schedule;
+pub use schedule::{NodeSchedule, ScheduleEstimate};
+
+mod stats;
+pub use stats::{GraphStats, LevelStats};
+
+mod exec;
+pub use exec::LevelError;
+
+mod items;
+pub use items::ResolvedItems;
+
+mod isolated;
+pub use isolated::IsolatedPolicy;
+
+mod fingerprint;
+
+mod ext;
+#[cfg(parallel)]
+pub use ext::ParResolveExt;
+pub use ext::ResolveExt;
+
+mod report;
+pub use report::{ResolveProblem, ResolveReport};
+
+mod plan;
+pub use plan::ExecutionPlan;
+
+mod soft;
+
+mod alias;
+
+mod group;
+
+mod kinds;
+pub use kinds::EdgeKind;
+
+mod progress;
+pub use progress::ResolveProgress;
+
+mod threaded;
+pub use threaded::{ExecuteError, NodeFailure};
+
+#[cfg(feature = "async")]
+mod run_async;
+#[cfg(feature = "async")]
+pub use run_async::{OnError, RunAsyncError};
+
+#[cfg(feature = "serde")]
+mod serde_impls;
+
+#[cfg(feature = "petgraph")]
+mod petgraph_impls;
+
+#[cfg(feature = "build-files")]
+mod build_files;
+
+#[cfg(feature = "cargo-metadata")]
+mod cargo_metadata;
+#[cfg(feature = "cargo-metadata")]
+pub use cargo_metadata::{CargoMetadataError, CargoMetadataOptions};
+
+#[cfg(feature = "serde")]
+mod adjacency;
+#[cfg(feature = "serde")]
+pub use adjacency::AdjacencyJsonError;
+
+#[cfg(feature = "gen")]
+mod gen;
+#[cfg(feature = "gen")]
+pub use gen::GraphGen;
+
+#[cfg(feature = "miette")]
+mod miette_impls;
+#[cfg(feature = "miette")]
+pub use miette_impls::{DiagnosticSources, SourcedReport};
+
+/// An item of the graph
+///
+/// The trait is object safe, `Box<dyn DepMeta<Id = I> + Send + Sync>` and `&dyn DepMeta<Id = I>`
+/// are items too, so items of different types can be added together
 pub trait DepMeta {
     type Id: Eq + Hash + Clone;
 
     fn get_id(&self) -> Self::Id;
 
     fn get_deps(&self) -> &[Self::Id];
+
+    /// Deps that only order the item after them if they are part of the graph, missing ones
+    /// are ignored and cycles made up of them only get broken, see [`DepRes::add_soft_edge`]
+    fn get_soft_deps(&self) -> &[Self::Id] {
+        &[]
+    }
 }
 
 mod impls {
     use crate::*;
 
-    impl<T: DepMeta> DepMeta for &T {
+    impl<T: DepMeta + ?Sized> DepMeta for &T {
         type Id = T::Id;
 
         fn get_id(&self) -> Self::Id {
@@ -33,9 +183,13 @@ mod impls {
         fn get_deps(&self) -> &[Self::Id] {
             (**self).get_deps()
         }
+
+        fn get_soft_deps(&self) -> &[Self::Id] {
+            (**self).get_soft_deps()
+        }
     }
 
-    impl<T: DepMeta> DepMeta for Rc<T> {
+    impl<T: DepMeta + ?Sized> DepMeta for Rc<T> {
         type Id = T::Id;
 
         fn get_id(&self) -> Self::Id {
@@ -45,9 +199,13 @@ mod impls {
         fn get_deps(&self) -> &[Self::Id] {
             self.deref().get_deps()
         }
+
+        fn get_soft_deps(&self) -> &[Self::Id] {
+            self.deref().get_soft_deps()
+        }
     }
 
-    impl<T: DepMeta> DepMeta for Box<T> {
+    impl<T: DepMeta + ?Sized> DepMeta for Box<T> {
         type Id = T::Id;
 
         fn get_id(&self) -> Self::Id {
@@ -57,9 +215,13 @@ mod impls {
         fn get_deps(&self) -> &[Self::Id] {
             self.deref().get_deps()
         }
+
+        fn get_soft_deps(&self) -> &[Self::Id] {
+            self.deref().get_soft_deps()
+        }
     }
 
-    impl<T: DepMeta> DepMeta for Arc<T> {
+    impl<T: DepMeta + ?Sized> DepMeta for Arc<T> {
         type Id = T::Id;
 
         fn get_id(&self) -> Self::Id {
@@ -69,153 +231,1432 @@ mod impls {
         fn get_deps(&self) -> &[Self::Id] {
             self.deref().get_deps()
         }
+
+        fn get_soft_deps(&self) -> &[Self::Id] {
+            self.deref().get_soft_deps()
+        }
     }
 }
 
-#[derive(Debug, Default)]
-pub struct DepRes<Id: Eq + Hash + Clone> {
-    ids: DashSet<Id>,
-    deps: DashMap<Id, DashSet<Id>>,
+/// Frontier width from which graph searches fan out over rayon
+const PAR_FRONTIER: usize = 256;
+
+/// Default of [`DepRes::set_parallel_threshold`]
+const PARALLEL_THRESHOLD: usize = 512;
+
+/// node -> deps, or dep -> dependents for the reverse index
+type Adjacency<Id, S> = Map<Arc<Id>, Set<Arc<Id>, S>, S>;
+
+type RevIndex<Id, S> = Arc<Adjacency<Id, S>>;
+
+/// node -> dep -> kinds of labeled edges
+type Labels<Id, S> = Map<Arc<Id>, HashMap<Arc<Id>, Vec<EdgeKind>, S>, S>;
+
+/// Decides per `(node, dep)` whether an edge takes part in a resolve
+type EdgeFilter<'a, Id> = &'a (dyn Fn(&Id, &Id) -> bool + Sync);
+
+/// The last [`DepRes::resolve_cached`] result with the generation it was computed at
+type Cached<Id, S> = Mutex<Option<(u64, Arc<ResolvedDeps<Id, S>>)>>;
+
+/// Level index -> level set, the set view of a [`ResolvedDeps`]
+type LevelSets<Id, S> = BTreeMap<usize, Arc<LevelSet<Id, S>>>;
+
+/// id -> level, a hashbrown map for the lookups by borrowed ids
+type LevelMap<Id, S> = hashbrown::HashMap<Arc<Id>, usize, S>;
+
+/// A resolution with the soft edges dropped to get it
+type WithDropped<Id, S> = (ResolvedDeps<Id, S>, Vec<(Id, Id)>);
+
+/// Each component's ids next to how resolving it went
+type PerComponent<Id, S> = Vec<(Vec<Id>, Result<ResolvedDeps<Id, S>, DepResolveError<Id>>)>;
+
+/// A dependency graph, `S` is the hasher used by every internal map and set
+///
+/// Every distinct id is allocated once in `pool` and shared by `ids` and `deps`
+#[derive(Debug)]
+pub struct DepRes<Id: Eq + Hash + Clone, S: BuildHasher + Clone = RandomState> {
+    ids: Set<Arc<Id>, S>,
+    deps: Adjacency<Id, S>,
+    /// node -> soft deps, see [`add_soft_edge`](Self::add_soft_edge)
+    soft: Adjacency<Id, S>,
+    /// node -> dep -> kinds, only for edges labeled through [`add_edge_kind`](Self::add_edge_kind)
+    kinds: Labels<Id, S>,
+    /// alias -> target, see [`add_alias`](Self::add_alias)
+    aliases: Map<Arc<Id>, Arc<Id>, S>,
+    /// group -> members, see [`define_group`](Self::define_group)
+    groups: Map<Arc<Id>, Arc<Vec<Arc<Id>>>, S>,
+    pool: Map<Arc<Id>, (), S>,
+    /// Bumped by every mutation, used to invalidate derived caches
+    generation: AtomicU64,
+    rev: RwLock<Option<(u64, RevIndex<Id, S>)>>,
+    cached: Cached<Id, S>,
+    /// Resolves fail once they would need more levels than this
+    max_levels: AtomicUsize,
+    /// Counts below this stay off the thread pool, see
+    /// [`set_parallel_threshold`](Self::set_parallel_threshold)
+    parallel_threshold: AtomicUsize,
+    /// Lowest level a node may be placed at, see [`pin_min_level`](Self::pin_min_level)
+    pins: Map<Arc<Id>, usize, S>,
+    /// Canonicalizes incoming ids, see [`with_normalizer`](DepRes::with_normalizer)
+    normalizer: Option<Arc<normalize::Normalizer<Id, S>>>,
+    /// Kept for [`try_add_edge`](DepRes::try_add_edge), rebuilt once the graph changed otherwise
+    order: Mutex<Option<incremental::TopoOrder<Id, S>>>,
+    hasher: S,
 }
 
 impl<Id: Eq + Hash + Clone> DepRes<Id> {
     pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> Default for DepRes<Id, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> DepRes<Id, S> {
+    pub fn with_hasher(hasher: S) -> Self {
         Self {
-            ids: DashSet::new(),
-            deps: DashMap::new(),
+            ids: Set::with_hasher(hasher.clone()),
+            deps: Map::with_hasher(hasher.clone()),
+            soft: Map::with_hasher(hasher.clone()),
+            kinds: Map::with_hasher(hasher.clone()),
+            aliases: Map::with_hasher(hasher.clone()),
+            groups: Map::with_hasher(hasher.clone()),
+            pool: Map::with_hasher(hasher.clone()),
+            generation: AtomicU64::new(0),
+            rev: RwLock::new(None),
+            cached: Mutex::new(None),
+            max_levels: AtomicUsize::new(usize::MAX),
+            parallel_threshold: AtomicUsize::new(PARALLEL_THRESHOLD),
+            pins: Map::with_hasher(hasher.clone()),
+            normalizer: None,
+            order: Mutex::new(None),
+            hasher,
+        }
+    }
+
+    /// Number of nodes added as items, ids only seen as someone's dep are not counted
+    pub fn node_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Whether `id` was added as an item, ids only seen as someone's dep are not contained
+    ///
+    /// Like the other queries this takes any borrowed form of the ids, e.g. `&str` for
+    /// `String` ids, and then allocates nothing unless a normalizer sees a new id
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        self.find(id).is_some_and(|id| self.ids.contains(&id))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = Id> + '_ {
+        self.ids.to_vec().into_iter().map(|a| (*a).clone())
+    }
+
+    /// Makes every resolve fail with [`DepResolveError::DepthLimitExceeded`] once it would
+    /// need more than `limit` levels, a hard bound on the work for corrupted inputs
+    ///
+    /// `usize::MAX`, the default, means unlimited
+    pub fn set_max_levels(&self, limit: usize) {
+        self.max_levels.store(limit, Ordering::Relaxed);
+        self.touch();
+    }
+
+    pub fn max_levels(&self) -> usize {
+        self.max_levels.load(Ordering::Relaxed)
+    }
+
+    /// Makes [`add`](Self::add) calls with fewer than `n` items, dep lists of fewer than `n`
+    /// deps and resolves of fewer than `n` nodes run on the calling thread, since for small
+    /// graphs handing work to the thread pool costs more than the work itself
+    ///
+    /// Results are the same either way, 0 always fans out and `usize::MAX` never does. The
+    /// default is 512, without the `parallel` feature everything is sequential anyway
+    pub fn set_parallel_threshold(&self, n: usize) {
+        self.parallel_threshold.store(n, Ordering::Relaxed);
+    }
+
+    pub fn parallel_threshold(&self) -> usize {
+        self.parallel_threshold.load(Ordering::Relaxed)
+    }
+
+    fn touch(&self) {
+        self.generation.fetch_add(1, Ordering::Release);
+    }
+
+    /// The shared allocation of the canonical form of `id`, created on first sight
+    fn intern(&self, id: &Id) -> Arc<Id> {
+        let id = &*self.canon(id);
+        if let Some(shared) = self.pool.key_of(id) {
+            return shared;
         }
+        (self.pool).get_or_insert_with(Arc::new(id.clone()), || (), |shared, _| shared.clone())
+    }
+
+    /// The shared allocation of `id` if there is one, without interning it
+    fn shared(&self, id: &Id) -> Arc<Id> {
+        self.pool.key_of(id).unwrap_or_else(|| Arc::new(id.clone()))
+    }
+
+    /// Drops `id` from the pool once nothing but the pool refers to it anymore
+    fn release(&self, id: &Id) {
+        self.pool
+            .remove_if(id, |shared, _| Arc::strong_count(shared) == 1);
+    }
+
+    /// Like [`release`](Self::release) for a handle to the pooled allocation itself
+    fn release_shared(&self, id: Arc<Id>) {
+        self.pool
+            .remove_if(&id, |shared, _| Arc::strong_count(shared) == 2);
+    }
+
+    /// Adds `id` as a node, returns whether it wasn't one yet
+    #[cfg_attr(not(any(feature = "serde", feature = "petgraph")), allow(dead_code))]
+    fn insert_id(&self, id: &Id) -> bool {
+        self.ids.insert(self.intern(id))
+    }
+
+    /// Records deps of `id`, creating its deps entry even if `deps` is empty
+    #[cfg_attr(not(any(feature = "serde", feature = "petgraph")), allow(dead_code))]
+    fn insert_deps<'a>(&self, id: &Id, deps: impl IntoIterator<Item = &'a Id>)
+    where
+        Id: 'a,
+    {
+        // interned upfront, the pool is not to be touched under a lock of `deps`
+        let deps = deps.into_iter().map(|dep| self.intern(dep)).collect::<Vec<_>>();
+        self.deps.get_or_insert_with(
+            self.intern(id),
+            || Set::with_hasher(self.hasher.clone()),
+            |_, entry| entry.insert_all(deps),
+        );
     }
 }
 
-impl<Id: Sync + Send + Eq + Hash + Clone> DepRes<Id> {
-    pub fn add<'a>(
-        &self,
-        items: &'a impl IntoParallelRefIterator<'a, Item = impl DepMeta<Id = Id>>,
-    ) {
-        items.par_iter().for_each(|item| {
-            let id = item.get_id();
-            let deps = item.get_deps();
-            let has_dep = !deps.is_empty();
-            if has_dep {
-                deps.par_iter().for_each(|dep| {
-                    let dset = self
-                        .deps
-                        .entry(id.clone())
-                        .or_insert_with(|| DashSet::new());
-                    dset.insert(dep.clone());
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Fewer items than the [parallel threshold](Self::set_parallel_threshold) are added
+    /// on the calling thread, going by the upper bound of their size hint
+    #[cfg(parallel)]
+    pub fn add<'a, T, D>(&self, items: &'a T)
+    where
+        T: IntoParallelRefIterator<'a, Item = D>,
+        &'a T: IntoIterator<Item = D>,
+        D: DepMeta<Id = Id>,
+    {
+        let span = trace::AddSpan::enter();
+        let seq = items.into_iter();
+        if (seq.size_hint().1).is_some_and(|n| n < self.parallel_threshold()) {
+            seq.for_each(|item| {
+                span.count(&item);
+                self.add_item(item);
+            });
+        } else {
+            items.par_iter().for_each(|item| {
+                span.count(&item);
+                self.add_item(item);
+            });
+        }
+        self.touch();
+    }
+
+    /// Like [`add`](Self::add), with all parallel work running on `pool` instead of the global pool
+    #[cfg(parallel)]
+    pub fn add_on<'a, T, D>(&self, pool: &rayon::ThreadPool, items: &'a T)
+    where
+        T: IntoParallelRefIterator<'a, Item = D> + Sync,
+        &'a T: IntoIterator<Item = D>,
+        D: DepMeta<Id = Id>,
+    {
+        pool.install(|| self.add(items))
+    }
+
+    /// Without the `parallel` feature, or on wasm32 without threads, items are added one after the other
+    #[cfg(not(parallel))]
+    pub fn add(&self, items: impl IntoIterator<Item = impl DepMeta<Id = Id>>) {
+        let span = trace::AddSpan::enter();
+        items.into_iter().for_each(|item| {
+            span.count(&item);
+            self.add_item(item);
+        });
+        self.touch();
+    }
+
+    /// Like [`add`](Self::add) for any iterator, e.g. an adapter chain or a map, the items are
+    /// taken one after the other without collecting them first
+    pub fn add_iter(&self, items: impl IntoIterator<Item = impl DepMeta<Id = Id>>) {
+        let span = trace::AddSpan::enter();
+        items.into_iter().for_each(|item| {
+            span.count(&item);
+            self.add_item(item);
+        });
+        self.touch();
+    }
+
+    /// The deps are gathered into a set before the entry is looked up once, no lock of the map
+    /// is held while inserting in parallel since a stolen task could otherwise lock it again
+    fn add_item(&self, item: impl DepMeta<Id = Id>) {
+        let id = self.intern(&item.get_id());
+        self.link(&self.deps, &id, item.get_deps());
+        self.link(&self.soft, &id, item.get_soft_deps());
+        if !self.kinds.is_empty() {
+            self.label_normal(&id, item.get_deps());
+        }
+        self.ids.insert(id);
+    }
+
+    /// Adds `deps` to the entry of `id` in `adjacency`, creating no entry if there are none
+    fn link(&self, adjacency: &Adjacency<Id, S>, id: &Arc<Id>, deps: &[Id]) {
+        if deps.is_empty() {
+            return;
+        }
+        let set = Set::with_capacity_and_hasher(deps.len(), self.hasher.clone());
+        if deps.len() < self.parallel_threshold() {
+            for dep in deps {
+                set.insert(self.intern(dep));
+            }
+        } else {
+            deps.par_iter().for_each(|dep| {
+                set.insert(self.intern(dep));
+            });
+        }
+        adjacency.upsert(id.clone(), set, |known, set| known.insert_all(set.into_vec()));
+    }
+
+    /// Number of distinct (node, dep) edges
+    pub fn edge_count(&self) -> usize {
+        let mut count = 0;
+        self.deps.for_each(|_, deps| count += deps.len());
+        count
+    }
+
+    /// Every distinct `(node, dep)` edge
+    ///
+    /// Deps are copied out per node so no lock is held between items, which keeps
+    /// concurrent [`add`](Self::add) calls from deadlocking against a running iteration
+    pub fn edges(&self) -> impl Iterator<Item = (Id, Id)> + '_ {
+        self.deps.keys().into_iter().flat_map(move |id| {
+            self.direct_deps(&id)
+                .into_iter()
+                .map(move |dep| ((*id).clone(), dep))
+        })
+    }
+
+    #[cfg(parallel)]
+    pub fn par_edges(&self) -> impl ParallelIterator<Item = (Id, Id)> + '_ {
+        self.deps.keys().into_par_iter().flat_map_iter(|id| {
+            self.direct_deps(&id)
+                .into_iter()
+                .map(move |dep| ((*id).clone(), dep))
+        })
+    }
+
+    /// Removes the node `id` along with its own deps, returns whether it was present
+    ///
+    /// Edges of other nodes that point at `id` are kept, so those nodes now have a missing dep
+    pub fn remove(&self, id: &Id) -> bool {
+        let id = &*self.canon(id);
+        // a cached resolution would keep the removed ids in the pool
+        self.cached.lock().unwrap().take();
+        let removed = self.ids.remove(id).is_some();
+        self.kinds.remove(id);
+        for adjacency in [&self.deps, &self.soft] {
+            if let Some((_, deps)) = adjacency.remove(id) {
+                for dep in deps.into_vec() {
+                    self.release_shared(dep);
+                }
+            }
+        }
+        self.pins.remove(id);
+        self.release(id);
+        self.touch();
+        removed
+    }
+
+    /// Removes every node not matching `pred`, along with the edges of other nodes that
+    /// point at one of them
+    ///
+    /// Unlike [`remove`](Self::remove) this leaves no dangling edges behind, a node that only
+    /// depended on removed ones becomes a root. Deps on ids that never were nodes are kept
+    pub fn retain(&self, pred: impl Fn(&Id) -> bool + Sync) {
+        let doomed = self.doomed(pred);
+        self.remove_all(doomed.into_iter().collect());
+    }
+
+    /// Like [`retain`](Self::retain), also removing everything that transitively depends on
+    /// a removed node, soft deps don't count for that
+    pub fn retain_with_dependents(&self, pred: impl Fn(&Id) -> bool + Sync) {
+        let doomed = self.doomed(pred);
+        let found = Set::with_hasher(self.hasher.clone());
+        Self::reach(&self.reverse_index(), &found, doomed.clone());
+        found.insert_all(doomed);
+        // dependents of ids that never were nodes are nodes as well, so everything found is one
+        self.remove_all(found.into_vec().into_iter().collect());
+    }
+
+    /// The nodes not matching `pred`
+    fn doomed(&self, pred: impl Fn(&Id) -> bool + Sync) -> Vec<Arc<Id>> {
+        (self.ids.to_vec().into_par_iter())
+            .filter(|id| !pred(id))
+            .collect()
+    }
+
+    /// Removes the nodes of `doomed` and every edge pointing at one of them
+    fn remove_all(&self, doomed: HashSet<Arc<Id>, S>) {
+        if doomed.is_empty() {
+            return;
+        }
+        for id in &doomed {
+            self.remove(id);
+        }
+        for adjacency in [&self.deps, &self.soft] {
+            for id in adjacency.keys() {
+                let dropped = adjacency.read(&id, |_, deps| {
+                    let before = deps.len();
+                    deps.retain(|dep| !doomed.contains(dep));
+                    deps.len() != before
+                });
+                if dropped != Some(true) {
+                    continue;
+                }
+                // an empty entry would still count as having deps
+                adjacency.remove_if(&id, |_, deps| deps.is_empty());
+                self.kinds.modify(&id, |_, labels| {
+                    labels.retain(|dep, _| !doomed.contains(dep));
                 });
+                self.kinds.remove_if(&id, |_, labels| labels.is_empty());
             }
-            self.ids.insert(id);
+        }
+        for id in doomed {
+            self.release_shared(id);
+        }
+        self.touch();
+    }
+
+    /// Makes every resolve place `id` at `level` or later, even if its deps would allow an
+    /// earlier one, its dependents move down with it as needed
+    ///
+    /// Levels nothing else lands on before it are kept as empty levels, so level numbers stay
+    /// contiguous and `id` really is at `level`. Returns [`DepResolveError::UnknownTargets`]
+    /// if `id` was not added as an item, the pin goes away with [`remove`](Self::remove)
+    pub fn pin_min_level(&self, id: Id, level: usize) -> Result<(), DepResolveError<Id>> {
+        let id = self.normalize(&id);
+        let Some(id) = self.ids.get_key(&id) else {
+            return Err(DepResolveError::UnknownTargets(vec![id]));
+        };
+        self.pins.insert(id, level);
+        self.touch();
+        Ok(())
+    }
+
+    /// Drops the pin of `id`, returning its level
+    pub fn unpin(&self, id: &Id) -> Option<usize> {
+        let (_, level) = self.pins.remove(&*self.canon(id))?;
+        self.touch();
+        Some(level)
+    }
+
+    /// Ids that directly depend on `id`, including dependents of ids that were never added as items
+    ///
+    /// Dependents through an alias or group count as well, as resolving places them after `id`
+    pub fn dependents_of<Q>(&self, id: &Q) -> Vec<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let Some(id) = self.find(id) else {
+            return vec![];
+        };
+        let dependents = self.reverse_index().get_cloned(&id);
+        (dependents.map(StoreSet::into_vec).unwrap_or_default().into_iter())
+            .map(|a| (*a).clone())
+            .collect()
+    }
+
+    /// Everything that transitively depends on `id`, not including `id` itself
+    pub fn transitive_dependents<Q>(&self, id: &Q) -> HashSet<Id>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let Some(id) = self.find(id) else {
+            return HashSet::new();
+        };
+        let rev = self.reverse_index();
+        let found = Set::with_hasher(self.hasher.clone());
+        Self::reach(&rev, &found, vec![id.clone()]);
+        found.remove(&id);
+        found.into_vec().into_iter().map(|a| (*a).clone()).collect()
+    }
+
+    /// Worklist walk over an adjacency map (forward deps or the reverse index), adding every reached id into `found`
+    fn reach(adjacency: &Adjacency<Id, S>, found: &Set<Arc<Id>, S>, mut queue: Vec<Arc<Id>>) {
+        while let Some(id) = queue.pop() {
+            adjacency.read(&id, |_, next| {
+                next.for_each(|next| {
+                    if found.insert(next.clone()) {
+                        queue.push(next.clone());
+                    }
+                })
+            });
+        }
+    }
+
+    /// Everything `id` transitively depends on, not including `id` itself
+    ///
+    /// Deps are followed through aliases and groups like [`resolved_deps_of`](Self::resolved_deps_of)
+    /// does, the aliases and groups themselves are not listed. Returns [`DepResolveError::IslandsOrCircular`] if the walk runs into a cycle
+    pub fn transitive_deps<Q>(&self, id: &Q) -> Result<HashSet<Id>, DepResolveError<Id>>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        match self.find(id) {
+            Some(id) => self.transitive_deps_from(&id),
+            None => Ok(HashSet::new()),
+        }
+    }
+
+    fn transitive_deps_from(&self, id: &Id) -> Result<HashSet<Id>, DepResolveError<Id>> {
+        let mut done = HashSet::new();
+        let mut on_path = HashSet::new();
+        on_path.insert(id.clone());
+        let mut stack = vec![(id.clone(), self.target_deps(id))];
+        while let Some((_, pending)) = stack.last_mut() {
+            match pending.pop() {
+                Some(dep) => {
+                    if on_path.contains(&dep) {
+                        return Err(DepResolveError::IslandsOrCircular);
+                    }
+                    if done.contains(&dep) {
+                        continue;
+                    }
+                    on_path.insert(dep.clone());
+                    let deps = self.target_deps(&dep);
+                    stack.push((dep, deps));
+                }
+                None => {
+                    let (node, _) = stack.pop().unwrap();
+                    on_path.remove(&node);
+                    done.insert(node);
+                }
+            }
+        }
+        done.remove(id);
+        Ok(done)
+    }
+
+    /// Whether `a` transitively depends on `b`, stopping as soon as `b` is reached
+    ///
+    /// Deps are followed through aliases and groups, so an alias or group `b` is never reached
+    /// itself. Unknown ids simply have no deps, so they yield `false`
+    pub fn depends_on<Q>(&self, a: &Q, b: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let (Some(a), Some(b)) = (self.find(a), self.find(b)) else {
+            return false;
+        };
+        let (a, b) = (&*a, &*b);
+        let visited = Set::with_hasher(self.hasher.clone());
+        let mut frontier = vec![a.clone()];
+        while !frontier.is_empty() {
+            let found = AtomicBool::new(false);
+            let visit = |dep: &Id| {
+                if dep == b {
+                    found.store(true, Ordering::Relaxed);
+                }
+                visited.insert(dep.clone())
+            };
+            let next: Vec<Id> = if frontier.len() < PAR_FRONTIER {
+                frontier
+                    .iter()
+                    .flat_map(|id| self.target_deps(id))
+                    .filter(|dep| visit(dep))
+                    .collect()
+            } else {
+                frontier
+                    .par_iter()
+                    .flat_map_iter(|id| self.target_deps(id))
+                    .filter(|dep| !found.load(Ordering::Relaxed) && visit(dep))
+                    .collect()
+            };
+            if found.load(Ordering::Relaxed) {
+                return true;
+            }
+            frontier = next;
+        }
+        false
+    }
+
+    /// A shortest dependency chain `from -> .. -> to`, each hop being a dep with aliases and groups
+    /// followed, see [`resolved_deps_of`](Self::resolved_deps_of)
+    ///
+    /// When `from == to` this is the shortest cycle through `from` (`[from, from]` for a self-loop),
+    /// and `None` if there is none, `from` on its own is no path. Unknown ids have no path either
+    pub fn path_between<Q>(&self, from: &Q, to: &Q) -> Option<Vec<Id>>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        let (from, to) = (self.find(from)?, self.find(to)?);
+        self.path_between_ids(&from, &to, |id| self.target_deps(id))
+    }
+
+    /// [`path_between`](Self::path_between) for canonical ids, hopping along `deps`
+    fn path_between_ids(
+        &self,
+        from: &Id,
+        to: &Id,
+        deps: impl Fn(&Id) -> Vec<Id>,
+    ) -> Option<Vec<Id>> {
+        let mut parents = HashMap::<Id, Id>::new();
+        let mut queue = VecDeque::from([from.clone()]);
+        while let Some(id) = queue.pop_front() {
+            for dep in deps(&id) {
+                if dep == *to {
+                    let mut path = vec![dep, id];
+                    while let Some(parent) = parents.get(path.last().unwrap()) {
+                        path.push(parent.clone());
+                    }
+                    path.reverse();
+                    return Some(path);
+                }
+                if dep != *from && !parents.contains_key(&dep) {
+                    parents.insert(dep.clone(), id.clone());
+                    queue.push_back(dep);
+                }
+            }
+        }
+        None
+    }
+
+    /// Copies the deps out so no lock is held while walking
+    fn direct_deps(&self, id: &Id) -> Vec<Id> {
+        let deps = self.deps.read(id, |_, set| {
+            let mut deps = Vec::with_capacity(set.len());
+            set.for_each(|a| deps.push((**a).clone()));
+            deps
+        });
+        deps.unwrap_or_default()
+    }
+
+    /// Lazily built dep -> dependents index, rebuilt when the graph has changed since the last build
+    ///
+    /// A dep on an alias or group lists its node under the alias or group and under what it
+    /// stands for
+    fn reverse_index(&self) -> RevIndex<Id, S> {
+        let generation = self.generation.load(Ordering::Acquire);
+        if let Some((g, rev)) = &*self.rev.read().unwrap() {
+            if *g == generation {
+                return rev.clone();
+            }
+        }
+        let rev = Adjacency::<Id, S>::with_hasher(self.hasher.clone());
+        self.deps.par_for_each(|id, deps| {
+            let add = |dep: Arc<Id>| {
+                let new = || Set::with_hasher(self.hasher.clone());
+                rev.get_or_insert_with(dep, new, |_, dependents| {
+                    dependents.insert(id.clone());
+                });
+            };
+            deps.for_each(|dep| {
+                add(dep.clone());
+                // what the dep stands for has `id` as a dependent as well
+                self.for_each_target(dep, &mut |target| {
+                    if target != &**dep {
+                        add(self.shared(target));
+                    }
+                });
+            });
         });
+        let rev = Arc::new(rev);
+        *self.rev.write().unwrap() = Some((generation, rev.clone()));
+        rev
     }
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct ResolvedDeps<Id: Eq + Hash + Clone> {
-    lvs: DashMap<usize, Arc<DashSet<Id>>>,
+/// Deep copy of the whole graph, O(V + E)
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync> Clone
+    for DepRes<Id, S>
+{
+    fn clone(&self) -> Self {
+        Self {
+            ids: self.ids.clone(),
+            deps: self.deps.clone(),
+            soft: self.soft.clone(),
+            kinds: self.kinds.clone(),
+            aliases: self.aliases.clone(),
+            groups: self.groups.clone(),
+            pool: self.pool.clone(),
+            generation: AtomicU64::new(0),
+            rev: RwLock::new(None),
+            cached: Mutex::new(None),
+            max_levels: AtomicUsize::new(self.max_levels()),
+            parallel_threshold: AtomicUsize::new(self.parallel_threshold()),
+            pins: self.pins.clone(),
+            normalizer: self.normalizer.clone(),
+            order: Mutex::new(None),
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+/// Same nodes, hard and soft edges, aliases, groups and pins, i.e. the content
+/// [`fingerprint`](DepRes::fingerprint) hashes, in O(V + E)
+///
+/// Insertion order, the members order of a group and edge kinds don't matter
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> PartialEq for DepRes<Id, S> {
+    fn eq(&self, other: &Self) -> bool {
+        fn same_edges<Id: Eq + Hash, S: BuildHasher + Clone>(
+            a: &Adjacency<Id, S>,
+            b: &Adjacency<Id, S>,
+        ) -> bool {
+            let count = |adjacency: &Adjacency<Id, S>| {
+                let mut count = 0;
+                adjacency.for_each(|_, deps| count += deps.len());
+                count
+            };
+            count(a) == count(b)
+                && a.all(|id, own| match b.read(id, |_, deps| own.all(|dep| deps.contains(dep))) {
+                    Some(same) => same,
+                    None => own.is_empty(),
+                })
+        }
+        self.ids.len() == other.ids.len()
+            && self.ids.all(|id| other.ids.contains(id))
+            && same_edges(&self.deps, &other.deps)
+            && same_edges(&self.soft, &other.soft)
+            && self.aliases.len() == other.aliases.len()
+            && (self.aliases)
+                .all(|alias, target| other.aliases.read(alias, |_, t| t == target) == Some(true))
+            && self.groups.len() == other.groups.len()
+            && self.groups.all(|group, own| {
+                other.groups.read(group, |_, members| {
+                    let members = members.iter().collect::<HashSet<_>>();
+                    let own = own.iter().collect::<HashSet<_>>();
+                    members == own
+                }) == Some(true)
+            })
+            && self.pins.len() == other.pins.len()
+            && (self.pins).all(|id, level| other.pins.read(id, |_, l| l == level) == Some(true))
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> Eq for DepRes<Id, S> {}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedDeps<Id: Eq + Hash + Clone, S: BuildHasher + Clone = RandomState> {
+    /// Level index -> ids, sharing the allocations the graph interned so resolving copies no id
+    levels: BTreeMap<usize, Arc<Vec<Arc<Id>>>>,
+    /// Lazily built set view of `levels` for the APIs handing out levels as sets
+    sets: OnceLock<LevelSets<Id, S>>,
+    /// Lazily built id -> level map
+    level_map: OnceLock<LevelMap<Id, S>>,
+    /// Nodes without deps and dependents kept out of the levels, see [`IsolatedPolicy::Separate`]
+    isolated: Vec<Id>,
+    /// The one of the graph this came from, so lookups take raw ids
+    normalizer: Option<Arc<normalize::Normalizer<Id, S>>>,
+}
+
+/// Same ids at the same level indices, and the same separated isolated nodes, in O(V)
+///
+/// The order inside a level doesn't matter, so resolutions of one graph compare equal
+/// whether deterministic or not
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> PartialEq for ResolvedDeps<Id, S> {
+    fn eq(&self, other: &Self) -> bool {
+        let same = |a: &[Arc<Id>], b: &[Arc<Id>]| {
+            a.len() == b.len() && {
+                let b = b.iter().collect::<HashSet<_>>();
+                a.iter().all(|id| b.contains(id))
+            }
+        };
+        self.levels.len() == other.levels.len()
+            && self.levels.iter().all(|(lv, ids)| {
+                other.levels.get(lv).is_some_and(|other| same(ids, other))
+            })
+            && self.isolated.len() == other.isolated.len()
+            && self.isolated.iter().collect::<HashSet<_>>()
+                == other.isolated.iter().collect::<HashSet<_>>()
+    }
 }
 
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> Eq for ResolvedDeps<Id, S> {}
+
 #[derive(Debug, Default, Clone)]
 pub struct DepLevel<D> {
     pub level: usize,
     pub deps: D,
 }
 
-impl<Id: Eq + Hash + Clone> ResolvedDeps<Id> {
-    fn new(lvs: DashMap<usize, Arc<DashSet<Id>>>) -> Self {
-        Self { lvs }
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> Default for ResolvedDeps<Id, S> {
+    fn default() -> Self {
+        Self::new(BTreeMap::new())
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    fn new(levels: BTreeMap<usize, Arc<Vec<Arc<Id>>>>) -> Self {
+        Self {
+            levels,
+            sets: OnceLock::new(),
+            level_map: OnceLock::new(),
+            isolated: vec![],
+            normalizer: None,
+        }
+    }
+
+    /// Levels `0..` from plain ids, duplicates inside a level are dropped
+    #[cfg(any(test, feature = "serde"))]
+    pub(crate) fn from_levels(levels: Vec<Vec<Id>>) -> Self {
+        let levels = levels
+            .into_iter()
+            .map(|ids| {
+                let mut seen = HashSet::<&Id, S>::default();
+                let ids = ids
+                    .iter()
+                    .filter(|id| seen.insert(id))
+                    .map(|id| Arc::new(id.clone()))
+                    .collect::<Vec<_>>();
+                Arc::new(ids)
+            })
+            .enumerate()
+            .collect();
+        Self::new(levels)
+    }
+
+    /// Levels in ascending order as slices of the ids stored in the graph, nothing is copied
+    /// or allocated
+    ///
+    /// [`iter_level_sets`](Self::iter_level_sets) hands them out as sets instead
+    pub fn iter_level(&self) -> impl Iterator<Item = DepLevel<&[Arc<Id>]>> + '_ {
+        self.levels.iter().map(|(&level, ids)| DepLevel {
+            level,
+            deps: ids.as_slice(),
+        })
+    }
+
+    /// Same as [`iter_level`](Self::iter_level)
+    pub fn iter_level_shared(&self) -> impl Iterator<Item = DepLevel<&[Arc<Id>]>> + '_ {
+        self.iter_level()
     }
 
     pub fn sorted_by_level(&self) -> Vec<Id> {
-        let mut vec = self.lvs.iter().collect::<Vec<_>>();
-        vec.sort_by_key(|r| *r.key());
-        let ids = vec
+        self.iter_level_shared()
+            .flat_map(|level| level.deps.iter().map(|id| (**id).clone()))
+            .collect()
+    }
+
+    /// Lazy [`sorted_by_level`](Self::sorted_by_level), ids are cloned one at a time as
+    /// they are consumed
+    ///
+    /// Reversed it yields dependents before their deps, e.g. for tearing things down
+    pub fn iter_topo(&self) -> impl DoubleEndedIterator<Item = Id> + '_ {
+        self.levels
+            .values()
+            .flat_map(|ids| ids.iter())
+            .map(|id| (**id).clone())
+    }
+
+    /// [`iter_topo`](Self::iter_topo) from the last level to the first
+    pub fn iter_topo_rev(&self) -> impl Iterator<Item = Id> + '_ {
+        self.iter_topo().rev()
+    }
+
+    /// Like [`sorted_by_level`](Self::sorted_by_level), handing out the ids stored in the
+    /// graph instead of copies
+    pub fn sorted_by_level_shared(&self) -> Vec<Arc<Id>> {
+        self.iter_level_shared()
+            .flat_map(|level| level.deps.iter().cloned())
+            .collect()
+    }
+
+    /// Like [`sorted_by_level`](Self::sorted_by_level), with the ids of each level ordered by `key`
+    ///
+    /// Wrap the key in [`std::cmp::Reverse`] for descending order. The sort is stable, so ties
+    /// keep the order `sorted_by_level` gives them, i.e. ascending ids for deterministic resolves
+    pub fn sorted_by_level_with<K: Ord>(&self, key: impl Fn(&Id) -> K) -> Vec<Id> {
+        self.to_levels()
+            .into_iter()
+            .flat_map(|mut level| {
+                level.sort_by_cached_key(|id| key(id));
+                level
+            })
+            .collect()
+    }
+
+    /// Level `n` as a slice of the ids stored in the graph, nothing is copied or allocated
+    ///
+    /// `resolved[n]` is the same, panicking if out of range
+    pub fn get_level_shared(&self, n: usize) -> Option<&[Arc<Id>]> {
+        self.levels.get(&n).map(|ids| ids.as_slice())
+    }
+
+    /// Level `n` as a set, the sets of all levels are built on first use
+    pub fn get_level(&self, n: usize) -> Option<Arc<LevelSet<Id, S>>> {
+        self.sets().get(&n).cloned()
+    }
+
+    /// Level `n`, panics if out of range, see [`get_level`](Self::get_level) for the non-panicking version
+    pub fn level(&self, n: usize) -> DepLevel<Arc<LevelSet<Id, S>>> {
+        match self.get_level(n) {
+            Some(deps) => DepLevel { level: n, deps },
+            None => panic!(
+                "level {n} is out of range, max level is {:?}",
+                self.max_level()
+            ),
+        }
+    }
+
+    /// Highest level index, `None` for an empty resolution
+    pub fn max_level(&self) -> Option<usize> {
+        self.levels.keys().next_back().copied()
+    }
+
+    /// Number of levels, this is `max_level + 1` unless levels were filtered without compacting
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Total number of ids across all levels
+    pub fn len(&self) -> usize {
+        self.levels.values().map(|ids| ids.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Plain levels indexed by level, ids are moved out where neither a clone of this nor
+    /// the graph holds them anymore
+    pub fn into_levels(self) -> Vec<Vec<Id>> {
+        self.into_iter().map(|level| level.deps).collect()
+    }
+
+    /// Plain levels indexed by level
+    pub fn to_levels(&self) -> Vec<Vec<Id>> {
+        self.iter_level_shared()
+            .map(|level| level.deps.iter().map(|id| (**id).clone()).collect())
+            .collect()
+    }
+
+    /// Like [`to_levels`](Self::to_levels), handing out the ids stored in the graph instead of copies
+    pub fn to_levels_shared(&self) -> Vec<Vec<Arc<Id>>> {
+        self.levels.values().map(|ids| ids.to_vec()).collect()
+    }
+
+    /// Splits into levels `0..n` and levels `n..`, the latter re-indexed to start at 0
+    ///
+    /// Either half is simply empty when `n` is 0 or past the last level
+    pub fn split_at_level(mut self, n: usize) -> (ResolvedDeps<Id, S>, ResolvedDeps<Id, S>) {
+        let tail = self.levels.split_off(&n);
+        let tail = tail.into_iter().map(|(lv, ids)| (lv - n, ids)).collect();
+        (ResolvedDeps::new(self.levels), ResolvedDeps::new(tail))
+    }
+
+    /// The levels in `range` re-indexed to start at 0, sharing the level sets with `self`
+    pub fn slice_levels(&self, range: impl RangeBounds<usize>) -> ResolvedDeps<Id, S> {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let levels = self
+            .levels
             .iter()
-            .flat_map(|r| r.value().iter().map(|a| a.clone()))
-            .collect::<Vec<_>>();
-        ids
+            .filter(|(lv, _)| range.contains(*lv))
+            .map(|(lv, ids)| (lv - start, ids.clone()))
+            .collect();
+        ResolvedDeps::new(levels)
     }
 
-    pub fn raw_level(&self) -> &DashMap<usize, Arc<DashSet<Id>>> {
-        &self.lvs
+    /// Keeps only the ids matching `pred`, dropping levels that end up empty
+    ///
+    /// With `compact` the remaining levels are renumbered to be contiguous again,
+    /// otherwise they keep their original index so they still line up with `self`
+    pub fn filter(&self, pred: impl Fn(&Id) -> bool, compact: bool) -> ResolvedDeps<Id, S> {
+        let levels = self
+            .levels
+            .iter()
+            .map(|(&lv, ids)| {
+                let ids = ids.iter().filter(|id| pred(id)).cloned().collect::<Vec<_>>();
+                (lv, ids)
+            })
+            .filter(|(_, ids)| !ids.is_empty())
+            .enumerate()
+            .map(|(i, (lv, ids))| (if compact { i } else { lv }, Arc::new(ids)))
+            .collect();
+        ResolvedDeps::new(levels)
     }
 
-    pub fn iter_level(&self) -> impl Iterator<Item = DepLevel<Arc<DashSet<Id>>>> + '_ {
-        self.lvs.iter().map(|kv| DepLevel {
-            level: *kv.key(),
-            deps: kv.value().clone(),
+    /// Levels in ascending order as sets, e.g. for membership checks, the sets of all levels
+    /// are built on first use
+    pub fn iter_level_sets(&self) -> impl Iterator<Item = DepLevel<Arc<LevelSet<Id, S>>>> + '_ {
+        self.sets().iter().map(|(&level, deps)| DepLevel {
+            level,
+            deps: deps.clone(),
+        })
+    }
+
+    fn sets(&self) -> &LevelSets<Id, S> {
+        self.sets.get_or_init(|| {
+            self.levels
+                .iter()
+                .map(|(&lv, ids)| {
+                    let ids = ids.iter().map(|id| (**id).clone()).collect();
+                    (lv, Arc::new(LevelSet::new(ids)))
+                })
+                .collect()
         })
     }
 }
 
-impl<Id: Sync + Send + Eq + Hash + Clone> DepRes<Id> {
-    pub fn resolve(&mut self) -> Result<ResolvedDeps<Id>, DepResolveError> {
-        let lvs = DashMap::new();
+/// Level `n` like [`get_level_shared`](ResolvedDeps::get_level_shared), panics if out of range
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> Index<usize> for ResolvedDeps<Id, S> {
+    type Output = [Arc<Id>];
 
-        if self.ids.is_empty() {
-            return Ok(ResolvedDeps::new(lvs));
+    fn index(&self, n: usize) -> &Self::Output {
+        match self.get_level_shared(n) {
+            Some(ids) => ids,
+            None => panic!(
+                "level {n} is out of range, max level is {:?}",
+                self.max_level()
+            ),
         }
+    }
+}
 
-        let (lv0, other): (DashSet<Id>, DashSet<Id>) = self.ids.par_iter().partition_map(|kv| {
-            let id = kv.key().clone();
-            if let None = self.deps.get(&id) {
-                Either::Left(id.clone())
-            } else {
-                Either::Right(id.clone())
+impl<Id: Eq + Hash + Clone + Ord, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    /// Switches to deterministic mode, see [`DepRes::resolve_deterministic`]
+    ///
+    /// This sorts the ids inside each level, everything derived from the result keeps that order
+    pub fn into_deterministic(mut self) -> Self {
+        for ids in self.levels.values_mut() {
+            Arc::make_mut(ids).sort();
+        }
+        self.isolated.sort();
+        self
+    }
+
+    /// Like [`iter_topo`](Self::iter_topo) with the ids of each level sorted, in the order
+    /// of [`to_sorted_levels`](Self::to_sorted_levels)
+    ///
+    /// A level is sorted when the iterator reaches it, only ids that are consumed are cloned
+    pub fn iter_topo_sorted(&self) -> impl DoubleEndedIterator<Item = Id> + '_ {
+        self.levels
+            .values()
+            .flat_map(|ids| {
+                let mut ids = ids.iter().collect::<Vec<_>>();
+                ids.sort_unstable();
+                ids
+            })
+            .map(|id| (**id).clone())
+    }
+
+    /// Like [`to_levels`](Self::to_levels) with the ids of each level sorted
+    pub fn to_sorted_levels(&self) -> Vec<Vec<Id>> {
+        let mut levels = self.to_levels();
+        for level in &mut levels {
+            level.sort();
+        }
+        levels
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// The level `id` was placed at, `None` if it's not part of this resolution
+    ///
+    /// Takes any borrowed form of the ids like the queries of [`DepRes`]
+    pub fn level_of<Q>(&self, id: &Q) -> Option<usize>
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        match &self.normalizer {
+            Some(normalizer) => {
+                let id = normalizer.canonical_borrowed(id);
+                borrowed::get_in::<Id, Id, _, _>(self.level_map(), &id)
             }
-        });
-        if lv0.is_empty() {
-            return Err(DepResolveError::IslandsOrCircular);
-        }
-        let lv0 = Arc::new(lv0);
-        lvs.insert(0, lv0.cloned());
-
-        let mut last = lv0;
-        let mut other = other;
-        let mut new_other = DashSet::new();
-        let internal_data_error = AtomicBool::new(false);
-        let mut lv = 1;
-        loop {
-            let lvn = DashSet::new();
-            new_other.clear();
-
-            other.par_iter().for_each(|id| {
-                if let Some(deps) = self.deps.get(&*id) {
-                    if deps.par_iter().any(|id| last.contains(&*id)) {
-                        lvn.insert(id.clone());
-                    } else {
-                        new_other.insert(id.clone());
+            None => borrowed::get_in(self.level_map(), id),
+        }
+    }
+
+    /// Whether `id` is part of this resolution
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        self.level_of(id).is_some()
+    }
+
+    pub fn to_level_map(&self) -> HashMap<Id, usize, S> {
+        self.level_map()
+            .iter()
+            .map(|(id, &level)| ((**id).clone(), level))
+            .collect()
+    }
+
+    /// Whether `a` sits at a strictly lower level than `b`, `None` if either is not part of this resolution
+    pub fn is_before(&self, a: &Id, b: &Id) -> Option<bool> {
+        Some(self.level_of(a)? < self.level_of(b)?)
+    }
+
+    fn level_map(&self) -> &LevelMap<Id, S> {
+        self.level_map.get_or_init(|| {
+            let mut map = LevelMap::with_capacity_and_hasher(self.len(), S::default());
+            for (&level, ids) in &self.levels {
+                map.extend(ids.iter().map(|id| (id.clone(), level)));
+            }
+            map
+        })
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    pub fn resolve(&mut self) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        self.resolve_nodes(&self.ids, false)
+    }
+
+    /// Like [`resolve`](Self::resolve), handing out the same result until the graph changes
+    ///
+    /// Every mutation invalidates the cached resolution, the next call computes it again.
+    /// Concurrent calls wait for one computation instead of each running their own. Errors
+    /// are not cached
+    pub fn resolve_cached(&self) -> Result<Arc<ResolvedDeps<Id, S>>, DepResolveError<Id>> {
+        let mut cached = self.cached.lock().unwrap();
+        // read before resolving, a mutation racing the resolve leaves the result stale
+        let generation = self.generation.load(Ordering::Acquire);
+        if let Some((g, resolved)) = &*cached {
+            if *g == generation {
+                return Ok(resolved.clone());
+            }
+        }
+        let resolved = Arc::new(self.resolve_nodes(&self.ids, false)?);
+        *cached = Some((generation, resolved.clone()));
+        Ok(resolved)
+    }
+
+    /// Like [`resolve`](Self::resolve), with all parallel work running on `pool` instead of the global pool
+    #[cfg(parallel)]
+    pub fn resolve_on(
+        &self,
+        pool: &rayon::ThreadPool,
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        pool.install(|| self.resolve_nodes(&self.ids, false))
+    }
+
+    /// Like [`resolve`](Self::resolve), returning [`DepResolveError::Cancelled`] soon after `cancel` is set
+    ///
+    /// The flag is checked before every level and in chunks within wide ones. Nothing of the
+    /// graph changes during a resolve, so it can be resolved again afterwards
+    pub fn resolve_cancelable(
+        &self,
+        cancel: &AtomicBool,
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        let opts = ResolveOpts {
+            cancel: Some(cancel),
+            ..ResolveOpts::default()
+        };
+        self.resolve_nodes_with(&self.ids, opts, |_| {})
+            .map(|(resolved, _)| resolved)
+    }
+
+    /// Like [`resolve`](Self::resolve), with ids sorted wherever the result emits them in sequence
+    ///
+    /// Level membership never depends on thread timing, only the iteration order of the
+    /// level sets does, so this makes [`ResolvedDeps::sorted_by_level`], `to_levels`,
+    /// `into_levels` and the consuming iterator reproducible across runs and thread counts.
+    /// Levels handed out as sets (`iter_level`, `get_level`) are still sets
+    pub fn resolve_deterministic(&self) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>>
+    where
+        Id: Ord,
+    {
+        Ok(self.resolve_nodes(&self.ids, false)?.into_deterministic())
+    }
+
+    /// Levels only the nodes impacted by a change to `changed`, i.e. `changed` and all of their transitive dependents
+    ///
+    /// Deps outside the impacted set are treated as already built, so every changed
+    /// node that doesn't depend on another changed node lands at level 0
+    pub fn impacted_levels(
+        &self,
+        changed: &[Id],
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        let rev = self.reverse_index();
+        let nodes = Set::with_hasher(self.hasher.clone());
+        let mut queue = vec![];
+        for id in changed {
+            let id = self.shared(&self.canon(id));
+            if nodes.insert(id.clone()) {
+                queue.push(id);
+            }
+        }
+        Self::reach(&rev, &nodes, queue);
+        self.resolve_nodes(&nodes, true)
+    }
+
+    /// Levels only `targets` and their transitive deps, unrelated parts of the graph are never looked at
+    pub fn resolve_for_targets(
+        &self,
+        targets: &[Id],
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        let targets = &*self.canon_all(targets);
+        let unknown = targets
+            .iter()
+            .filter(|id| !self.ids.contains(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            return Err(DepResolveError::UnknownTargets(unknown));
+        }
+        // missing deps stay out of the node set, same as they are for a full resolve
+        let closure = self.closure(targets);
+        closure.retain(|id| self.ids.contains(id));
+        self.resolve_nodes(&closure, false)
+    }
+
+    /// Clones the part of the graph made of `roots` and everything they transitively depend on
+    ///
+    /// Deps on ids that were never added are kept dangling and the aliases and groups the deps
+    /// go through are copied along, so the subgraph resolves exactly like the corresponding
+    /// part of the full graph
+    pub fn subgraph(&self, roots: &[Id]) -> DepRes<Id, S> {
+        let mut sub = DepRes::with_hasher(self.hasher.clone());
+        sub.normalizer = self.normalizer.clone();
+        let closure = self.closure(&self.canon_all(roots)).into_vec();
+        closure.into_par_iter().for_each(|id| {
+            if !self.ids.contains(&id) {
+                // the aliases and groups reached, a node would shadow them
+                if let Some(target) = self.aliases.get_cloned(&id) {
+                    sub.pool.insert(target.clone(), ());
+                    sub.pool.insert(id.clone(), ());
+                    sub.aliases.insert(id, target);
+                } else if let Some(members) = self.groups.get_cloned(&id) {
+                    for member in members.iter() {
+                        sub.pool.insert(member.clone(), ());
                     }
-                } else {
-                    internal_data_error.store(true, std::sync::atomic::Ordering::Relaxed);
+                    sub.pool.insert(id.clone(), ());
+                    sub.groups.insert(id, members);
                 }
-            });
+                return;
+            }
+            if let Some(deps) = self.deps.get_cloned(&id) {
+                deps.for_each(|dep| {
+                    sub.pool.insert(dep.clone(), ());
+                });
+                sub.deps.insert(id.clone(), deps);
+            }
+            sub.pool.insert(id.clone(), ());
+            sub.ids.insert(id);
+        });
+        sub
+    }
 
-            if internal_data_error.load(std::sync::atomic::Ordering::Relaxed) {
-                return Err(DepResolveError::InternalDataError);
+    /// The same nodes with every edge flipped, `a` depending on `b` becomes `b` depending on `a`
+    ///
+    /// Hard and soft edges between nodes carry over, deps on ids that are no node have nothing
+    /// to hang off in the transpose and are left out, as are aliases, groups, kinds and pins
+    pub fn transpose(&self) -> DepRes<Id, S> {
+        let mut transposed = DepRes::with_hasher(self.hasher.clone());
+        transposed.normalizer = self.normalizer.clone();
+        self.ids.par_for_each(|id| {
+            transposed.pool.insert(id.clone(), ());
+            transposed.ids.insert(id.clone());
+        });
+        let flip = |from: &Adjacency<Id, S>, to: &Adjacency<Id, S>| {
+            from.par_for_each(|id, deps| {
+                deps.for_each(|dep| {
+                    if !self.ids.contains(dep) {
+                        return;
+                    }
+                    let new = || Set::with_hasher(self.hasher.clone());
+                    to.get_or_insert_with(dep.clone(), new, |_, dependents| {
+                        dependents.insert(id.clone());
+                    });
+                })
+            })
+        };
+        flip(&self.deps, &transposed.deps);
+        flip(&self.soft, &transposed.soft);
+        transposed
+    }
+
+    /// `roots` plus every id reachable from them along dep edges, including never added ids
+    ///
+    /// A dep on an alias or group not shadowed by a node goes on to its target or members,
+    /// both the alias or group and what it stands for end up in the closure
+    fn closure(&self, roots: &[Id]) -> Set<Arc<Id>, S> {
+        let closure = Set::with_hasher(self.hasher.clone());
+        let mut queue = roots.iter().map(|id| self.shared(id)).collect::<Vec<_>>();
+        for id in &queue {
+            closure.insert(id.clone());
+        }
+        while let Some(id) = queue.pop() {
+            let next = match self.ids.contains(&id) {
+                true => None,
+                false => (self.aliases.get_cloned(&id).map(|target| vec![target]))
+                    .or_else(|| self.groups.get_cloned(&id).map(|members| members.to_vec())),
+            };
+            let next = next.unwrap_or_else(|| {
+                (self.deps.read(&id, |_, deps| deps.to_vec())).unwrap_or_default()
+            });
+            for next in next {
+                if closure.insert(next.clone()) {
+                    queue.push(next);
+                }
             }
+        }
+        closure
+    }
 
-            if lvn.is_empty() {
-                if other.is_empty() {
-                    return Ok(ResolvedDeps::new(lvs));
-                } else {
+    /// Levels `nodes`, with `inner_only` ignoring deps that point outside of `nodes`
+    ///
+    /// Nodes without deps, or with `inner_only` without deps inside `nodes`, form level 0
+    fn resolve_nodes(
+        &self,
+        nodes: &Set<Arc<Id>, S>,
+        inner_only: bool,
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        let opts = ResolveOpts {
+            inner_only,
+            ..ResolveOpts::default()
+        };
+        self.resolve_nodes_with(nodes, opts, |_| {})
+            .map(|(resolved, _)| resolved)
+    }
+
+    /// [`resolve_nodes`](Self::resolve_nodes) with every knob of [`ResolveOpts`], reporting to
+    /// `progress` along the way, also returns the soft edges dropped to break cycles
+    ///
+    /// Cycles are only looked for once the plain leveling failed, so graphs without soft
+    /// cycles pay nothing for them. `progress` then sees both attempts
+    fn resolve_nodes_with(
+        &self,
+        nodes: &Set<Arc<Id>, S>,
+        opts: ResolveOpts<Id>,
+        progress: impl Fn(ResolveProgress),
+    ) -> Result<WithDropped<Id, S>, DepResolveError<Id>> {
+        #[cfg(feature = "tracing")]
+        let _span = {
+            let span = tracing::debug_span!(
+                "resolve",
+                nodes = nodes.len(),
+                edges = tracing::field::Empty
+            );
+            if !span.is_disabled() {
+                span.record("edges", self.edge_count());
+            }
+            span.entered()
+        };
+        let resolve = |dropped: &[(Arc<Id>, Arc<Id>)]| {
+            let mut resolved = interned::Interned::new(self, nodes, &opts, dropped).resolve(
+                &progress,
+                &opts,
+                self.max_levels(),
+                self.parallel_threshold(),
+            )?;
+            resolved.normalizer = self.normalizer.clone();
+            Ok(resolved)
+        };
+        match resolve(&[]) {
+            Err(DepResolveError::IslandsOrCircular) if !self.soft.is_empty() => {
+                let dropped = self.soft_cycle_edges(nodes, opts.keep);
+                if dropped.is_empty() {
                     return Err(DepResolveError::IslandsOrCircular);
                 }
+                #[cfg(feature = "tracing")]
+                tracing::debug!(dropped = dropped.len(), "retrying without soft cycle edges");
+                let resolved = resolve(&dropped)?;
+                let dropped = dropped
+                    .into_iter()
+                    .map(|(id, dep)| ((*id).clone(), (*dep).clone()))
+                    .collect();
+                Ok((resolved, dropped))
             }
+            r => r.map(|resolved| (resolved, vec![])),
+        }
+    }
+}
+
+/// Knobs of a single resolve, see [`DepRes::resolve_nodes_with`]
+struct ResolveOpts<'a, Id> {
+    /// Ignore deps that point outside of the resolved nodes
+    inner_only: bool,
+    /// Give up with [`DepResolveError::Cancelled`] once set
+    cancel: Option<&'a AtomicBool>,
+    /// Which hard `(node, dep)` edges to level by, all of them if `None`
+    keep: Option<EdgeFilter<'a, Id>>,
+    isolated: IsolatedPolicy,
+}
 
-            let lvn = Arc::new(lvn);
-            lvs.insert(lv, lvn.cloned());
-            last = lvn;
-            std::mem::swap(&mut other, &mut new_other);
-            lv += 1;
+impl<Id> Default for ResolveOpts<'_, Id> {
+    fn default() -> Self {
+        Self {
+            inner_only: false,
+            cancel: None,
+            keep: None,
+            isolated: IsolatedPolicy::Level0,
         }
     }
 }
 
 #[derive(Error, Debug, Clone, PartialEq, Eq, Hash)]
-pub enum DepResolveError {
+pub enum DepResolveError<Id> {
     #[error("There are islands or circular reference dependencies")]
     IslandsOrCircular,
     #[error("internal data error")]
     InternalDataError,
+    #[error("unknown target ids: {0:?}")]
+    UnknownTargets(Vec<Id>),
+    #[error("resolution was cancelled")]
+    Cancelled,
+    #[error("more than {limit} levels needed, gave up after placing {placed} nodes")]
+    DepthLimitExceeded { limit: usize, placed: usize },
+    #[error("alias {alias:?} already points to {existing:?}")]
+    AliasConflict { alias: Id, existing: Id },
+    #[error("alias {0:?} would end up pointing to itself")]
+    AliasCycle(Id),
+    #[error("group {0:?} would end up containing itself")]
+    GroupCycle(Id),
+    #[error("items {unknown_items:?} are no nodes, nodes {missing_items:?} have no item")]
+    ItemsMismatch {
+        /// Ids of items that were never added to the graph
+        unknown_items: Vec<Id>,
+        /// Nodes without an item
+        missing_items: Vec<Id>,
+    },
+}
+
+impl<Id> DepResolveError<Id> {
+    /// The variant, for tracing events which can't require `Id: Debug`
+    #[cfg(feature = "tracing")]
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::IslandsOrCircular => "IslandsOrCircular",
+            Self::InternalDataError => "InternalDataError",
+            Self::UnknownTargets(_) => "UnknownTargets",
+            Self::Cancelled => "Cancelled",
+            Self::DepthLimitExceeded { .. } => "DepthLimitExceeded",
+            Self::AliasConflict { .. } => "AliasConflict",
+            Self::AliasCycle(_) => "AliasCycle",
+            Self::GroupCycle(_) => "GroupCycle",
+            Self::ItemsMismatch { .. } => "ItemsMismatch",
+        }
+    }
 }
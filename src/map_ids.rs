@@ -0,0 +1,181 @@
+use crate::*;
+use std::convert::Infallible;
+
+/// Why [`DepRes::try_map_ids`] gave up
+#[derive(Error, Debug)]
+pub enum MapIdsError<Id, J, E> {
+    /// The closure failed on `id`
+    #[error("mapping {id:?} failed: {error}")]
+    Map { id: Id, error: E },
+    /// Two ids map to the same one, which would merge them
+    #[error("{first:?} and {second:?} both map to {mapped:?}")]
+    Collision { first: Id, second: Id, mapped: J },
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// The same graph with every id, nodes, deps, aliases and groups alike, replaced by what
+    /// `f` maps it to, built in parallel
+    ///
+    /// Ids mapping to the same one are merged: the merged node has the deps, edge kinds and
+    /// soft deps of all of them and the highest of their pins, deps between merged ids are
+    /// dropped instead of turning into self deps. Aliases merged with different targets
+    /// become a group of those targets, as do aliases merged with a group, and alias or
+    /// group links that merging would turn into a cycle are dropped. Use
+    /// [`try_map_ids`](Self::try_map_ids) to reject such mappings instead
+    ///
+    /// Limits and the parallel threshold carry over, a normalizer doesn't since it works on
+    /// `Id`s, the mapped ids are taken as they are
+    pub fn map_ids<J: Eq + Hash + Clone + Send + Sync>(
+        &self,
+        f: impl Fn(&Id) -> J + Sync,
+    ) -> DepRes<J> {
+        match self.rebuild(|id| Ok::<_, Infallible>(f(id)), true) {
+            Ok(graph) => graph,
+            Err(MapIdsError::Map { error, .. }) => match error {},
+            Err(MapIdsError::Collision { .. }) => unreachable!("merging never collides"),
+        }
+    }
+
+    /// Like [`map_ids`](Self::map_ids) for a mapping that may fail, and that has to be
+    /// injective: returns [`MapIdsError::Map`] for the first id `f` fails on and
+    /// [`MapIdsError::Collision`] if two ids map to the same one
+    pub fn try_map_ids<J: Eq + Hash + Clone + Send + Sync, E: Send>(
+        &self,
+        f: impl Fn(&Id) -> Result<J, E> + Sync,
+    ) -> Result<DepRes<J>, MapIdsError<Id, J, E>> {
+        self.rebuild(f, false)
+    }
+
+    /// Maps every interned id once, then rebuilds each part of the graph from the mapped
+    /// ones, with `merge` deciding what collisions do
+    fn rebuild<J: Eq + Hash + Clone + Send + Sync, E: Send>(
+        &self,
+        f: impl Fn(&Id) -> Result<J, E> + Sync,
+        merge: bool,
+    ) -> Result<DepRes<J>, MapIdsError<Id, J, E>> {
+        let keys = self.pool.keys();
+        let mapped = (keys.par_iter())
+            .map(|id| {
+                f(id).map_err(|error| MapIdsError::Map {
+                    id: (**id).clone(),
+                    error,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let graph = DepRes::<J>::new();
+        graph.set_max_levels(self.max_levels());
+        graph.set_parallel_threshold(self.parallel_threshold());
+        let mut map = HashMap::with_capacity_and_hasher(keys.len(), self.hasher.clone());
+        let mut first = HashMap::new();
+        for (id, j) in keys.iter().zip(mapped) {
+            let shared = graph.intern(&j);
+            if !merge {
+                if let Some(first) = first.insert(shared.clone(), id) {
+                    return Err(MapIdsError::Collision {
+                        first: (**first).clone(),
+                        second: (**id).clone(),
+                        mapped: j,
+                    });
+                }
+            }
+            map.insert(&**id, shared);
+        }
+        let m = |id: &Id| map[id].clone();
+        // a dep between two ids merged into one, not a self dep the graph already had
+        let merged =
+            |id: &Id, to: &Arc<J>, dep: &Id, dep_to: &Arc<J>| Arc::ptr_eq(to, dep_to) && id != dep;
+
+        self.ids.par_for_each(|id| {
+            graph.ids.insert(m(id));
+        });
+        for (from, to) in [(&self.deps, &graph.deps), (&self.soft, &graph.soft)] {
+            from.par_for_each(|key, deps| {
+                let id = m(key);
+                let set = Set::with_capacity_and_hasher(deps.len(), graph.hasher.clone());
+                deps.for_each(|dep| {
+                    let dep_to = m(dep);
+                    if !merged(key, &id, dep, &dep_to) {
+                        set.insert(dep_to);
+                    }
+                });
+                // an empty entry means something, one emptied by merging must not show up
+                if set.is_empty() && !deps.is_empty() {
+                    return;
+                }
+                to.upsert(id, set, |known, set| known.insert_all(set.into_vec()));
+            });
+        }
+
+        self.kinds.for_each(|key, own| {
+            let id = m(key);
+            let new = || HashMap::with_hasher(graph.hasher.clone());
+            graph
+                .kinds
+                .get_or_insert_with(id.clone(), new, |_, labels| {
+                    for (dep, kinds) in own {
+                        let dep_to = m(dep);
+                        if merged(key, &id, dep, &dep_to) {
+                            continue;
+                        }
+                        let known = labels.entry(dep_to).or_default();
+                        for kind in kinds {
+                            if !known.contains(kind) {
+                                known.push(*kind);
+                            }
+                        }
+                    }
+                });
+        });
+        if merge && !self.kinds.is_empty() {
+            // plain edges merged with labeled ones keep counting as normal ones
+            self.deps.for_each(|id, deps| {
+                let mut plain = deps.to_vec();
+                self.kinds.read(id, |_, labels| {
+                    plain.retain(|dep| !labels.contains_key(&**dep));
+                });
+                let plain = plain
+                    .iter()
+                    .map(|dep| (*m(dep)).clone())
+                    .collect::<Vec<_>>();
+                graph.label_normal(&m(id), &plain);
+            });
+        }
+        self.pins.for_each(|id, &level| {
+            graph
+                .pins
+                .upsert(m(id), level, |pin, level| *pin = (*pin).max(level));
+        });
+
+        // through the checks of `add_alias` and `define_group`, merging may close cycles
+        let mut targets = HashMap::<Arc<J>, Vec<Arc<J>>>::new();
+        self.aliases.for_each(|alias, target| {
+            let (alias, target) = (m(alias), m(target));
+            let known = targets.entry(alias).or_default();
+            if !known.contains(&target) {
+                known.push(target);
+            }
+        });
+        self.groups.for_each(|group, members| {
+            let group = (*m(group)).clone();
+            let _ = graph.define_group(group.clone(), []);
+            for member in members.iter() {
+                let _ = graph.define_group(group.clone(), [(*m(member)).clone()]);
+            }
+        });
+        for (alias, targets) in targets {
+            let targets = targets.into_iter().map(|target| (*target).clone());
+            let _ = match targets.len() == 1 && !graph.groups.contains_key(&alias) {
+                true => graph.add_alias((*alias).clone(), targets.last().unwrap()),
+                false => {
+                    for target in targets {
+                        let _ = graph.define_group((*alias).clone(), [target]);
+                    }
+                    Ok(())
+                }
+            };
+        }
+        Ok(graph)
+    }
+}
@@ -0,0 +1,283 @@
+use crate::*;
+use miette::{Diagnostic, LabeledSpan, NamedSource, SourceCode, SourceSpan};
+use std::fmt::{self, Debug, Display, Formatter};
+
+macro_rules! boxed {
+    ($e:expr) => {
+        Some(Box::new($e) as Box<dyn Display>)
+    };
+}
+
+impl<Id: Debug> Diagnostic for DepResolveError<Id> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        boxed!(match self {
+            Self::IslandsOrCircular => "dep_res::islands_or_circular",
+            Self::InternalDataError => "dep_res::internal",
+            Self::UnknownTargets(_) => "dep_res::unknown_targets",
+            Self::Cancelled => "dep_res::cancelled",
+            Self::DepthLimitExceeded { .. } => "dep_res::depth_limit",
+            Self::AliasConflict { .. } => "dep_res::alias_conflict",
+            Self::AliasCycle(_) => "dep_res::alias_cycle",
+            Self::GroupCycle(_) => "dep_res::group_cycle",
+            Self::ItemsMismatch { .. } => "dep_res::items_mismatch",
+        })
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        match self {
+            Self::IslandsOrCircular => {
+                boxed!("DepRes::resolve_with_report lists every cycle and missing dep")
+            }
+            Self::UnknownTargets(_) => boxed!("targets have to be nodes of the graph"),
+            Self::DepthLimitExceeded { .. } => boxed!("raise the limit with DepRes::set_max_levels"),
+            _ => None,
+        }
+    }
+}
+
+impl<Id: Debug> Diagnostic for CycleError<Id> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        boxed!("dep_res::edge_cycle")
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        boxed!("leave the edge out, or add it as a soft edge with DepRes::add_soft_edge")
+    }
+}
+
+impl<Id: Debug + Display> Diagnostic for ResolveProblem<Id> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        match self {
+            Self::MissingDep { .. } => boxed!("dep_res::missing_dep"),
+            Self::SelfLoop(_) => boxed!("dep_res::self_loop"),
+            Self::Cycle(_) => boxed!("dep_res::cycle"),
+            Self::Error(e) => e.code(),
+        }
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        match self {
+            Self::MissingDep { id, dep } => {
+                boxed!(format!("add {dep} to the graph or drop it from the deps of {id}"))
+            }
+            Self::SelfLoop(id) => boxed!(format!("drop {id} from its own deps")),
+            Self::Cycle(_) => boxed!(
+                "drop one of the edges, or turn it into a soft edge with DepRes::add_soft_edge"
+            ),
+            Self::Error(e) => e.help(),
+        }
+    }
+}
+
+impl<Id: Debug + Display> Diagnostic for ResolveReport<Id> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        boxed!("dep_res::unresolvable")
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(self.problems.iter().map(|p| p as &dyn Diagnostic)))
+    }
+}
+
+/// Manifest texts keyed by the id they declare, see [`ResolveReport::with_sources`]
+#[derive(Debug, Clone)]
+pub struct DiagnosticSources<Id: Eq + Hash> {
+    sources: HashMap<Id, (String, String)>,
+}
+
+impl<Id: Eq + Hash> Default for DiagnosticSources<Id> {
+    fn default() -> Self {
+        Self {
+            sources: HashMap::new(),
+        }
+    }
+}
+
+impl<Id: Eq + Hash> DiagnosticSources<Id> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the text `id` was declared in, `name` is what diagnostics call it, e.g. a path
+    pub fn insert(&mut self, id: Id, name: impl Into<String>, text: impl Into<String>) {
+        self.sources.insert(id, (name.into(), text.into()));
+    }
+
+    /// Where `id`'s text names `dep`, the last whole word occurrence so a self loop points at
+    /// the dep and not at the name the manifest starts with
+    fn declaration(&self, id: &Id, dep: &impl Display) -> Option<(NamedSource<String>, SourceSpan)>
+    where
+        Id: Display,
+    {
+        let (name, text) = self.sources.get(id)?;
+        let dep = dep.to_string();
+        let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+        let at = text.match_indices(&dep).map(|(at, _)| at).filter(|&at| {
+            !text[..at].ends_with(is_word) && !text[at + dep.len()..].starts_with(is_word)
+        });
+        let at = at.last()?;
+        let source = NamedSource::new(name, text.clone());
+        Some((source, (at, dep.len()).into()))
+    }
+}
+
+impl<Id: Eq + Hash + Clone + Debug + Display> ResolveReport<Id> {
+    /// Attaches the manifests in `sources`, so rendering the report labels the dep
+    /// declarations behind each missing dep, self loop and cycle
+    ///
+    /// Problems of ids without a source render like they do without any
+    pub fn with_sources(self, sources: &DiagnosticSources<Id>) -> SourcedReport<Id> {
+        let problems = self
+            .problems
+            .iter()
+            .map(|problem| {
+                let edges = match problem {
+                    ResolveProblem::MissingDep { id, dep } => vec![(id, dep)],
+                    ResolveProblem::SelfLoop(id) => vec![(id, id)],
+                    ResolveProblem::Cycle(ids) => {
+                        ids.iter().zip(ids.iter().cycle().skip(1)).collect()
+                    }
+                    ResolveProblem::Error(_) => vec![],
+                };
+                let declarations = edges
+                    .into_iter()
+                    .filter_map(|(id, dep)| {
+                        let (source, span) = sources.declaration(id, dep)?;
+                        let label = match problem {
+                            ResolveProblem::MissingDep { .. } => format!("{dep} is no node"),
+                            _ => format!("{id} depends on {dep}"),
+                        };
+                        Some(Declaration {
+                            message: format!("{id} declares a dep on {dep}"),
+                            source,
+                            label: LabeledSpan::new_with_span(Some(label), span),
+                        })
+                    })
+                    .collect();
+                SourcedProblem {
+                    problem: problem.clone(),
+                    declarations,
+                }
+            })
+            .collect();
+        SourcedReport {
+            report: self,
+            problems,
+        }
+    }
+}
+
+/// A [`ResolveReport`] with labeled spans into the manifests behind its problems, see
+/// [`ResolveReport::with_sources`]
+#[derive(Debug)]
+pub struct SourcedReport<Id> {
+    report: ResolveReport<Id>,
+    problems: Vec<SourcedProblem<Id>>,
+}
+
+impl<Id> SourcedReport<Id> {
+    pub fn report(&self) -> &ResolveReport<Id> {
+        &self.report
+    }
+
+    pub fn into_report(self) -> ResolveReport<Id> {
+        self.report
+    }
+}
+
+impl<Id: Debug + Display> Display for SourcedReport<Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.report, f)
+    }
+}
+
+impl<Id: Debug + Display> std::error::Error for SourcedReport<Id> {}
+
+impl<Id: Debug + Display> Diagnostic for SourcedReport<Id> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.report.code()
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        Some(Box::new(self.problems.iter().map(|p| p as &dyn Diagnostic)))
+    }
+}
+
+/// A problem with the declarations it comes from, a cycle spans several manifests so each
+/// of its edges becomes a related diagnostic instead of a label
+#[derive(Debug)]
+struct SourcedProblem<Id> {
+    problem: ResolveProblem<Id>,
+    declarations: Vec<Declaration>,
+}
+
+impl<Id: Debug + Display> SourcedProblem<Id> {
+    fn single(&self) -> Option<&Declaration> {
+        match self.problem {
+            ResolveProblem::Cycle(_) => None,
+            _ => self.declarations.first(),
+        }
+    }
+}
+
+impl<Id: Debug + Display> Display for SourcedProblem<Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.problem, f)
+    }
+}
+
+impl<Id: Debug + Display> std::error::Error for SourcedProblem<Id> {}
+
+impl<Id: Debug + Display> Diagnostic for SourcedProblem<Id> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.problem.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.problem.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.single().map(|d| &d.source as &dyn SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let label = self.single()?.label.clone();
+        Some(Box::new(std::iter::once(label)))
+    }
+
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn Diagnostic> + 'a>> {
+        match self.problem {
+            ResolveProblem::Cycle(_) if !self.declarations.is_empty() => Some(Box::new(
+                self.declarations.iter().map(|d| d as &dyn Diagnostic),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// One edge of a cycle, pointing at where it was declared
+#[derive(Debug)]
+struct Declaration {
+    message: String,
+    source: NamedSource<String>,
+    label: LabeledSpan,
+}
+
+impl Display for Declaration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Declaration {}
+
+impl Diagnostic for Declaration {
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        Some(Box::new(std::iter::once(self.label.clone())))
+    }
+}
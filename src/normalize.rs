@@ -0,0 +1,99 @@
+use crate::*;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Maps raw ids to their canonical form, see [`DepRes::with_normalizer`]
+pub(crate) struct Normalizer<Id, S> {
+    normalize: Box<dyn Fn(&Id) -> Id + Send + Sync>,
+    /// raw id -> canonical id, so `normalize` runs once per distinct raw id
+    seen: Map<Id, Id, S>,
+}
+
+impl<Id, S> fmt::Debug for Normalizer<Id, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Normalizer").finish_non_exhaustive()
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> Normalizer<Id, S> {
+    fn canonical(&self, id: &Id) -> Id {
+        if let Some(canonical) = self.seen.get_cloned(id) {
+            return canonical;
+        }
+        (self.seen).get_or_insert_with(
+            id.clone(),
+            || (self.normalize)(id),
+            |_, canonical| canonical.clone(),
+        )
+    }
+
+    /// [`canonical`](Self::canonical) of a borrowed id, copied only if it wasn't seen before
+    ///
+    /// Only used for lookups, so an id not seen before is mapped without being remembered,
+    /// querying for ids that aren't there doesn't grow `seen`
+    pub(crate) fn canonical_borrowed<Q>(&self, id: &Q) -> Id
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = Id> + ?Sized,
+    {
+        match self.seen.get_cloned(id) {
+            Some(canonical) => canonical,
+            None => (self.normalize)(&id.to_owned()),
+        }
+    }
+}
+
+/// `id` through `normalizer` if there is one
+pub(crate) fn canon<'a, Id: Eq + Hash + Clone, S: BuildHasher + Clone>(
+    normalizer: &Option<Arc<Normalizer<Id, S>>>,
+    id: &'a Id,
+) -> Cow<'a, Id> {
+    match normalizer {
+        Some(normalizer) => Cow::Owned(normalizer.canonical(id)),
+        None => Cow::Borrowed(id),
+    }
+}
+
+impl<Id: Eq + Hash + Clone> DepRes<Id> {
+    /// A graph passing every id it is given through `normalize` first, e.g. to fold case
+    ///
+    /// Applies to items, deps, aliases, groups, edge kinds and pins as well as to the ids
+    /// queries and target lists take, resolutions of the graph take raw ids too. So the graph
+    /// only holds canonical ids and hands out nothing else. `normalize` runs once per distinct
+    /// raw id, except for lookups by a borrowed form of an id it hasn't seen yet, and should
+    /// map canonical ids to themselves
+    pub fn with_normalizer(normalize: impl Fn(&Id) -> Id + Send + Sync + 'static) -> Self {
+        Self::with_hasher_and_normalizer(RandomState::new(), normalize)
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone> DepRes<Id, S> {
+    /// [`with_normalizer`](DepRes::with_normalizer) with a custom hasher
+    pub fn with_hasher_and_normalizer(
+        hasher: S,
+        normalize: impl Fn(&Id) -> Id + Send + Sync + 'static,
+    ) -> Self {
+        let mut dr = Self::with_hasher(hasher.clone());
+        dr.normalizer = Some(Arc::new(Normalizer {
+            normalize: Box::new(normalize),
+            seen: Map::with_hasher(hasher),
+        }));
+        dr
+    }
+
+    /// The canonical form of `id`, `id` itself without a normalizer
+    pub fn normalize(&self, id: &Id) -> Id {
+        self.canon(id).into_owned()
+    }
+
+    pub(crate) fn canon<'a>(&self, id: &'a Id) -> Cow<'a, Id> {
+        canon(&self.normalizer, id)
+    }
+
+    pub(crate) fn canon_all<'a>(&self, ids: &'a [Id]) -> Cow<'a, [Id]> {
+        match self.normalizer {
+            Some(_) => Cow::Owned(ids.iter().map(|id| self.normalize(id)).collect()),
+            None => Cow::Borrowed(ids),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! The rayon surface used internally, with sequential stand-ins when `cfg(parallel)` is off, see `build.rs`
+
+#[cfg(parallel)]
+pub(crate) use rayon::prelude::*;
+
+#[cfg(not(parallel))]
+pub(crate) use seq::*;
+
+#[cfg(not(parallel))]
+mod seq {
+    pub trait IntoParallelRefIterator<'a> {
+        type Item: 'a;
+        type Iter: Iterator<Item = Self::Item>;
+
+        fn par_iter(&'a self) -> Self::Iter;
+    }
+
+    impl<'a, T: 'a> IntoParallelRefIterator<'a> for [T] {
+        type Item = &'a T;
+        type Iter = std::slice::Iter<'a, T>;
+
+        fn par_iter(&'a self) -> Self::Iter {
+            self.iter()
+        }
+    }
+
+    impl<'a, T: 'a> IntoParallelRefIterator<'a> for Vec<T> {
+        type Item = &'a T;
+        type Iter = std::slice::Iter<'a, T>;
+
+        fn par_iter(&'a self) -> Self::Iter {
+            self.iter()
+        }
+    }
+
+    impl<'a, Id: 'a, S> IntoParallelRefIterator<'a> for crate::LevelSet<Id, S> {
+        type Item = &'a Id;
+        type Iter = std::collections::hash_set::Iter<'a, Id>;
+
+        fn par_iter(&'a self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    pub trait ParallelSlice<T> {
+        fn par_chunks(&self, chunk_size: usize) -> std::slice::Chunks<'_, T>;
+    }
+
+    impl<T> ParallelSlice<T> for [T] {
+        fn par_chunks(&self, chunk_size: usize) -> std::slice::Chunks<'_, T> {
+            self.chunks(chunk_size)
+        }
+    }
+
+    pub trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<T: IntoIterator> IntoParallelIterator for T {}
+
+    pub trait ParallelIterator: Iterator + Sized {
+        fn flat_map_iter<U: IntoIterator, F: FnMut(Self::Item) -> U>(
+            self,
+            f: F,
+        ) -> std::iter::FlatMap<Self, U, F> {
+            self.flat_map(f)
+        }
+    }
+
+    impl<T: Iterator> ParallelIterator for T {}
+
+    pub trait ParallelExtend<T>: Extend<T> {
+        fn par_extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+            self.extend(iter)
+        }
+    }
+
+    impl<T, E: Extend<T>> ParallelExtend<T> for E {}
+}
@@ -0,0 +1,51 @@
+use crate::*;
+use petgraph::graph::{DiGraph, NodeIndex};
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Converts into a petgraph graph with edges pointing from dependent to dependency
+    ///
+    /// Ids only seen as someone's dep get a node too, so they become regular nodes
+    /// when converted back with [`from_petgraph`](Self::from_petgraph)
+    pub fn to_petgraph(&self) -> (DiGraph<Id, ()>, HashMap<Id, NodeIndex>) {
+        let mut graph = DiGraph::with_capacity(self.node_count(), self.edge_count());
+        let mut indices = HashMap::new();
+        let mut index_of = |graph: &mut DiGraph<Id, ()>, id: Id| {
+            *indices
+                .entry(id.clone())
+                .or_insert_with(|| graph.add_node(id))
+        };
+        for id in self.ids() {
+            index_of(&mut graph, id);
+        }
+        for (id, dep) in self.edges() {
+            let a = index_of(&mut graph, id);
+            let b = index_of(&mut graph, dep);
+            graph.add_edge(a, b, ());
+        }
+        (graph, indices)
+    }
+
+    /// Builds a graph from petgraph, every node becomes a node and every edge `a -> b` means `a` depends on `b`
+    pub fn from_petgraph(graph: &DiGraph<Id, ()>) -> Self {
+        let dr = DepRes::<Id, S>::default();
+        for id in graph.node_weights() {
+            dr.insert_id(id);
+        }
+        for edge in graph.raw_edges() {
+            dr.insert_deps(&graph[edge.source()], [&graph[edge.target()]]);
+        }
+        dr
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// Maps the node weights of `graph` to `(id, level)`, `None` for ids not in this resolution
+    pub fn to_petgraph_levels(&self, graph: &DiGraph<Id, ()>) -> DiGraph<(Id, Option<usize>), ()> {
+        let levels = self.level_map();
+        graph.map(|_, id| (id.clone(), levels.get(id).copied()), |_, _| ())
+    }
+}
@@ -0,0 +1,180 @@
+use crate::*;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    sync::{Condvar, Mutex},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeState {
+    Waiting,
+    Ready,
+    Running,
+    Done,
+}
+
+#[derive(Debug)]
+struct PlanState {
+    nodes: Vec<NodeState>,
+    /// Deps not completed yet, per node
+    pending: Vec<usize>,
+    /// Smallest position in `ids` first, so earlier levels and higher priorities win
+    ready: BinaryHeap<Reverse<usize>>,
+    /// Nodes not completed yet
+    remaining: usize,
+}
+
+/// Hands out nodes as soon as all of their deps completed, see [`DepRes::execution_plan`]
+///
+/// Can be driven from any number of threads at once
+#[derive(Debug)]
+pub struct ExecutionPlan<Id: Eq + Hash> {
+    ids: Vec<Id>,
+    index: HashMap<Id, usize>,
+    dependents: Vec<Vec<usize>>,
+    state: Mutex<PlanState>,
+    changed: Condvar,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// A completion driven plan over all nodes, without level barriers
+    ///
+    /// ```ignore
+    /// let plan = graph.execution_plan()?;
+    /// while let Some(id) = plan.next_ready() {
+    ///     run(&id);
+    ///     plan.complete(&id);
+    /// }
+    /// ```
+    ///
+    /// A node waits for the same deps resolving levels it after, aliases and groups followed
+    /// and soft deps included unless they were dropped to break a cycle
+    pub fn execution_plan(&self) -> Result<ExecutionPlan<Id>, DepResolveError<Id>> {
+        let (resolved, dropped) = self.resolve_nodes_with(&self.ids, Default::default(), |_| {})?;
+        Ok(self.plan(resolved.sorted_by_level(), &dropped))
+    }
+
+    /// Like [`execution_plan`](Self::execution_plan), among ready ids the ones of lower levels
+    /// and then with smaller `key` are handed out first, see [`ResolvedDeps::sorted_by_level_with`]
+    pub fn execution_plan_with<K: Ord>(
+        &self,
+        key: impl Fn(&Id) -> K,
+    ) -> Result<ExecutionPlan<Id>, DepResolveError<Id>> {
+        let (resolved, dropped) = self.resolve_nodes_with(&self.ids, Default::default(), |_| {})?;
+        Ok(self.plan(resolved.sorted_by_level_with(key), &dropped))
+    }
+
+    /// `ids` in level order, their position is their priority, `dropped` the soft edges the
+    /// resolve left out
+    fn plan(&self, ids: Vec<Id>, dropped: &[(Id, Id)]) -> ExecutionPlan<Id> {
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i))
+            .collect::<HashMap<_, _>>();
+        let mut pending = vec![0; ids.len()];
+        let mut dependents = vec![vec![]; ids.len()];
+        for (i, id) in ids.iter().enumerate() {
+            for dep in self.leveled_deps(id, dropped) {
+                if let Some(&dep) = index.get(&dep) {
+                    pending[i] += 1;
+                    dependents[dep].push(i);
+                }
+            }
+        }
+        let mut nodes = vec![NodeState::Waiting; ids.len()];
+        let mut ready = BinaryHeap::new();
+        for i in (0..ids.len()).filter(|&i| pending[i] == 0) {
+            nodes[i] = NodeState::Ready;
+            ready.push(Reverse(i));
+        }
+        ExecutionPlan {
+            state: Mutex::new(PlanState {
+                nodes,
+                pending,
+                ready,
+                remaining: ids.len(),
+            }),
+            ids,
+            index,
+            dependents,
+            changed: Condvar::new(),
+        }
+    }
+}
+
+impl<Id: Eq + Hash + Clone> ExecutionPlan<Id> {
+    /// A node whose deps all completed, `None` if there is none right now
+    pub fn next_ready(&self) -> Option<Id> {
+        let mut state = self.state.lock().unwrap();
+        self.take_ready(&mut state)
+    }
+
+    /// Blocks until a node is ready, `None` once every node completed
+    pub fn wait_ready(&self) -> Option<Id> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(id) = self.take_ready(&mut state) {
+                return Some(id);
+            }
+            if state.remaining == 0 {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Blocking iterator over [`wait_ready`](Self::wait_ready), ids still have to be completed
+    pub fn iter(&self) -> impl Iterator<Item = Id> + '_ {
+        std::iter::from_fn(|| self.wait_ready())
+    }
+
+    /// Marks `id` done, making dependents whose deps all completed ready
+    ///
+    /// Panics if `id` is not currently handed out, i.e. unknown, not ready yet or already completed
+    pub fn complete(&self, id: &Id) {
+        let i = *self
+            .index
+            .get(id)
+            .expect("completed an id that is not part of the plan");
+        let mut state = self.state.lock().unwrap();
+        assert_eq!(
+            state.nodes[i],
+            NodeState::Running,
+            "completed an id that was not handed out"
+        );
+        state.nodes[i] = NodeState::Done;
+        state.remaining -= 1;
+        for &dependent in &self.dependents[i] {
+            state.pending[dependent] -= 1;
+            if state.pending[dependent] == 0 {
+                state.nodes[dependent] = NodeState::Ready;
+                state.ready.push(Reverse(dependent));
+            }
+        }
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    /// Whether every node completed
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().remaining == 0
+    }
+
+    /// Ids that were never handed out
+    pub(crate) fn waiting(&self) -> Vec<Id> {
+        let state = self.state.lock().unwrap();
+        (state.nodes.iter().enumerate())
+            .filter(|(_, node)| **node == NodeState::Waiting)
+            .map(|(i, _)| self.ids[i].clone())
+            .collect()
+    }
+
+    fn take_ready(&self, state: &mut PlanState) -> Option<Id> {
+        let Reverse(i) = state.ready.pop()?;
+        state.nodes[i] = NodeState::Running;
+        Some(self.ids[i].clone())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::*;
+
+/// Snapshot handed to the callback of [`DepRes::resolve_with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResolveProgress {
+    /// The level that was just placed, `None` for the report before leveling starts
+    pub level: Option<usize>,
+    /// Nodes placed so far
+    pub placed: usize,
+    /// Nodes not placed yet
+    pub remaining: usize,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Like [`resolve`](Self::resolve), calling `progress` once with the totals and then once per placed level
+    ///
+    /// `progress` runs on the calling thread between levels with no locks held, so it may
+    /// freely use the graph. On a cycle the last report still has nodes remaining
+    pub fn resolve_with_progress(
+        &self,
+        progress: impl Fn(ResolveProgress) + Sync,
+    ) -> Result<ResolvedDeps<Id, S>, DepResolveError<Id>> {
+        self.resolve_nodes_with(&self.ids, ResolveOpts::default(), progress)
+            .map(|(resolved, _)| resolved)
+    }
+}
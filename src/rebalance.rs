@@ -0,0 +1,102 @@
+use crate::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// The same nodes with some moved to later levels so that no level holds more than
+    /// `max_width`, a width of 0 counts as 1
+    ///
+    /// Nodes never move earlier or up to a level of one of their dependents in `graph`.
+    /// Levels are filled in order, each with the nodes that can go there and have the least
+    /// slack, how far they can still be delayed without adding a level. When every level
+    /// can't stay under the bound, e.g. with more roots than fit before the last level, the
+    /// result gets more levels instead. Greedy in O((V + E) log V), so it may add a level an
+    /// optimal schedule wouldn't
+    ///
+    /// Separated isolated nodes stay separated, the empty levels before a node pinned with
+    /// [`DepRes::pin_min_level`] stay empty levels
+    pub fn rebalance(&self, graph: &DepRes<Id, S>, max_width: usize) -> ResolvedDeps<Id, S> {
+        let max_width = max_width.max(1);
+        let mut ids = vec![];
+        let mut level = vec![];
+        for lv in self.iter_level_shared() {
+            ids.extend(lv.deps.iter().cloned());
+            level.resize(ids.len(), lv.level);
+        }
+        let index = (ids.iter().enumerate())
+            .map(|(i, id)| (&**id, i))
+            .collect::<HashMap<_, _>>();
+
+        // only edges the levels already order, a dropped soft edge may go either way
+        let mut dependents = vec![vec![]; ids.len()];
+        let mut pending = vec![0usize; ids.len()];
+        for (i, id) in ids.iter().enumerate() {
+            let mut deps = vec![];
+            for adjacency in [&graph.deps, &graph.soft] {
+                if let Some(set) = adjacency.read(&**id, |_, set| set.to_vec()) {
+                    deps.extend(set);
+                }
+            }
+            let mut seen = HashSet::new();
+            for dep in deps {
+                graph.for_each_target(&*dep, &mut |target| {
+                    if let Some(&j) = index.get(target) {
+                        if level[j] < level[i] && seen.insert(j) {
+                            dependents[j].push(i);
+                            pending[i] += 1;
+                        }
+                    }
+                });
+            }
+        }
+
+        // latest level each node could take without adding a level, ids are in level order
+        let last = level.last().copied().unwrap_or(0);
+        let mut latest = vec![last; ids.len()];
+        for i in (0..ids.len()).rev() {
+            for &k in &dependents[i] {
+                latest[i] = latest[i].min(latest[k].saturating_sub(1));
+            }
+        }
+
+        // release level -> nodes whose deps are all placed
+        let mut waiting = BTreeMap::<usize, Vec<usize>>::new();
+        for i in (0..ids.len()).filter(|&i| pending[i] == 0) {
+            waiting.entry(level[i]).or_default().push(i);
+        }
+        let mut ready = BinaryHeap::new();
+        let mut levels = BTreeMap::new();
+        let mut at = 0;
+        while !waiting.is_empty() || !ready.is_empty() {
+            while let Some(entry) = waiting.first_entry().filter(|e| *e.key() <= at) {
+                ready.extend(entry.remove().into_iter().map(|i| Reverse((latest[i], i))));
+            }
+            let mut placed = vec![];
+            while placed.len() < max_width {
+                let Some(Reverse((_, i))) = ready.pop() else {
+                    break;
+                };
+                placed.push(i);
+            }
+            for &i in &placed {
+                for &k in &dependents[i] {
+                    pending[k] -= 1;
+                    if pending[k] == 0 {
+                        waiting.entry(level[k].max(at + 1)).or_default().push(k);
+                    }
+                }
+            }
+            // nothing is ready only before a node a pin holds back, the level stays as an empty one
+            let placed = placed.into_iter().map(|i| ids[i].clone()).collect();
+            levels.insert(at, Arc::new(placed));
+            at += 1;
+        }
+
+        let mut resolved = ResolvedDeps::new(levels);
+        resolved.isolated = self.isolated.clone();
+        resolved.normalizer = self.normalizer.clone();
+        resolved
+    }
+}
@@ -0,0 +1,252 @@
+use crate::*;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// One thing wrong with a graph, see [`DepRes::resolve_with_report`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResolveProblem<Id> {
+    /// `id` depends on `dep`, which is no node and stands for none
+    MissingDep { id: Id, dep: Id },
+    /// `id` depends on itself
+    SelfLoop(Id),
+    /// One cycle out of a strongly connected component, each id depends on the next and the
+    /// last one on the first
+    Cycle(Vec<Id>),
+    /// A failure not caused by the graph's shape, e.g. the level limit
+    Error(DepResolveError<Id>),
+}
+
+/// One line, e.g. `cycle: a -> b -> a`
+impl<Id: Display + Debug> Display for ResolveProblem<Id> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingDep { id, dep } => {
+                write!(f, "missing dep: {id} depends on {dep}, which is no node")
+            }
+            Self::SelfLoop(id) => write!(f, "self loop: {id} depends on itself"),
+            Self::Cycle(ids) => {
+                write!(f, "cycle: ")?;
+                for (i, id) in ids.iter().chain(ids.first()).enumerate() {
+                    write!(f, "{}{id}", if i == 0 { "" } else { " -> " })?;
+                }
+                Ok(())
+            }
+            Self::Error(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl<Id: Display + Debug> std::error::Error for ResolveProblem<Id> {}
+
+/// Everything [`DepRes::resolve_with_report`] found wrong with a graph
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{} problem(s) found, {} node(s) left unleveled", .problems.len(), .unleveled.len())]
+pub struct ResolveReport<Id> {
+    /// In no particular order
+    pub problems: Vec<ResolveProblem<Id>>,
+    /// Nodes no leveling could place, the members of cycles, nodes whose deps are all missing
+    /// and everything depending on them
+    pub unleveled: Vec<Id>,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Like [`resolve`](Self::resolve), but on failure reports every missing dep, self loop
+    /// and one cycle per strongly connected component instead of only the first problem
+    ///
+    /// The analysis only runs once resolving failed, successful resolves cost the same as
+    /// [`resolve`](Self::resolve). Soft edges count as well, except those resolving drops to
+    /// break a cycle
+    pub fn resolve_with_report(&self) -> Result<ResolvedDeps<Id, S>, ResolveReport<Id>> {
+        match self.resolve_nodes(&self.ids, false) {
+            Ok(resolved) => Ok(resolved),
+            Err(DepResolveError::IslandsOrCircular) => Err(self.report()),
+            Err(e) => Err(ResolveReport {
+                problems: vec![ResolveProblem::Error(e)],
+                unleveled: vec![],
+            }),
+        }
+    }
+
+    fn report(&self) -> ResolveReport<Id> {
+        let ids = self.ids.to_vec();
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (&**id, i))
+            .collect::<HashMap<_, _, S>>();
+        let mut problems = vec![];
+        // node -> deps among the nodes without self loops, the same edges resolving levels by
+        let mut fwd = vec![vec![]; ids.len()];
+        for (i, id) in ids.iter().enumerate() {
+            for dep in self.direct_deps(id) {
+                let mut found = false;
+                self.for_each_target(&dep, &mut |target| {
+                    if let Some(&j) = index.get(target) {
+                        found = true;
+                        match i == j {
+                            true => problems.push(ResolveProblem::SelfLoop((**id).clone())),
+                            false => fwd[i].push(j),
+                        }
+                    }
+                });
+                if !found {
+                    problems.push(ResolveProblem::MissingDep {
+                        id: (**id).clone(),
+                        dep,
+                    });
+                }
+            }
+        }
+        // soft deps only count where they point to a node and weren't dropped to break a cycle
+        if !self.soft.is_empty() {
+            let dropped = self.soft_cycle_edges(&self.ids, None);
+            let dropped = dropped
+                .iter()
+                .map(|(id, dep)| (&**id, &**dep))
+                .collect::<HashSet<_>>();
+            for (i, id) in ids.iter().enumerate() {
+                self.soft.read(id, |_, soft| {
+                    soft.for_each(|dep| {
+                        self.for_each_target(dep, &mut |target| {
+                            if dropped.contains(&(&**id, target)) {
+                                return;
+                            }
+                            if let Some(&j) = index.get(target) {
+                                if i != j {
+                                    fwd[i].push(j);
+                                }
+                            }
+                        });
+                    })
+                });
+            }
+        }
+
+        for component in strongly_connected(&fwd) {
+            if component.len() > 1 {
+                let cycle = cycle_in(&fwd, &component);
+                problems.push(ResolveProblem::Cycle(
+                    cycle.into_iter().map(|i| (*ids[i]).clone()).collect(),
+                ));
+            }
+        }
+
+        // what leveling places: a node without deps among the nodes is a root unless it has hard
+        // deps, which then all are missing, the others follow once their deps are placed
+        let mut pending = fwd.iter().map(Vec::len).collect::<Vec<_>>();
+        for problem in &problems {
+            if let ResolveProblem::SelfLoop(id) = problem {
+                pending[index[id]] += 1;
+            }
+        }
+        let mut rev = vec![vec![]; ids.len()];
+        for (i, deps) in fwd.iter().enumerate() {
+            for &dep in deps {
+                rev[dep].push(i);
+            }
+        }
+        let mut placed = vec![false; ids.len()];
+        let mut queue = (0..ids.len())
+            .filter(|&i| pending[i] == 0 && !self.deps.contains_key(&ids[i]))
+            .collect::<Vec<_>>();
+        while let Some(i) = queue.pop() {
+            placed[i] = true;
+            for &dependent in &rev[i] {
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    queue.push(dependent);
+                }
+            }
+        }
+        let unleveled = (0..ids.len())
+            .filter(|&i| !placed[i])
+            .map(|i| (*ids[i]).clone())
+            .collect();
+        ResolveReport {
+            problems,
+            unleveled,
+        }
+    }
+}
+
+/// Tarjan's algorithm without recursion, components come out in reverse topological order
+pub(crate) fn strongly_connected(fwd: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNSEEN: usize = usize::MAX;
+    let mut order = vec![UNSEEN; fwd.len()];
+    let mut low = vec![0; fwd.len()];
+    let mut on_stack = vec![false; fwd.len()];
+    let mut stack = vec![];
+    let mut components = vec![];
+    let mut next = 0;
+    for start in 0..fwd.len() {
+        if order[start] != UNSEEN {
+            continue;
+        }
+        // (node, position of the next edge to look at)
+        let mut frames = vec![(start, 0)];
+        order[start] = next;
+        low[start] = next;
+        next += 1;
+        stack.push(start);
+        on_stack[start] = true;
+        while let Some(&mut (node, ref mut edge)) = frames.last_mut() {
+            if let Some(&dep) = fwd[node].get(*edge) {
+                *edge += 1;
+                if order[dep] == UNSEEN {
+                    order[dep] = next;
+                    low[dep] = next;
+                    next += 1;
+                    stack.push(dep);
+                    on_stack[dep] = true;
+                    frames.push((dep, 0));
+                } else if on_stack[dep] {
+                    low[node] = low[node].min(order[dep]);
+                }
+                continue;
+            }
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == order[node] {
+                let mut component = vec![];
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// A cycle through the first member of `component`, which has to be strongly connected
+fn cycle_in(fwd: &[Vec<usize>], component: &[usize]) -> Vec<usize> {
+    let start = component[0];
+    let inside = component.iter().copied().collect::<HashSet<_>>();
+    let mut parent = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for &dep in &fwd[node] {
+            if dep == start {
+                let mut cycle = vec![node];
+                while let Some(&p) = parent.get(cycle.last().unwrap()) {
+                    cycle.push(p);
+                }
+                cycle.reverse();
+                return cycle;
+            }
+            if inside.contains(&dep) && !parent.contains_key(&dep) {
+                parent.insert(dep, node);
+                queue.push_back(dep);
+            }
+        }
+    }
+    unreachable!("a strongly connected component always has a cycle through each member")
+}
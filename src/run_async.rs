@@ -0,0 +1,87 @@
+use crate::*;
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use std::future::Future;
+
+/// What [`DepRes::run_async`] does with in-flight futures after one of them failed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum OnError {
+    /// Start nothing new but let the in-flight futures finish
+    #[default]
+    Drain,
+    /// Drop the in-flight futures right away
+    Abort,
+}
+
+/// Failure of [`DepRes::run_async`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum RunAsyncError<Id, E> {
+    #[error(transparent)]
+    Resolve(#[from] DepResolveError<Id>),
+    /// The first node whose future failed
+    #[error("node {id:?} failed")]
+    Node { id: Id, error: E },
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Runs the future of every id, each one starting as soon as the futures of all its deps resolved
+    ///
+    /// At most `concurrency` futures are in flight, they are polled concurrently by the returned
+    /// future itself so this works on any runtime. Wrap the body of `f` in a spawn for parallelism.
+    /// The plan is taken upfront, no graph locks are held across awaits.
+    ///
+    /// Panics if `concurrency` is 0
+    pub async fn run_async<E, Fut: Future<Output = Result<(), E>>>(
+        &self,
+        concurrency: usize,
+        on_error: OnError,
+        f: impl Fn(Id) -> Fut,
+    ) -> Result<(), RunAsyncError<Id, E>> {
+        self.run_plan(self.execution_plan()?, concurrency, on_error, f)
+            .await
+    }
+
+    /// Like [`run_async`](Self::run_async), starting ready ids in the order of
+    /// [`execution_plan_with`](Self::execution_plan_with)
+    pub async fn run_async_with<K: Ord, E, Fut: Future<Output = Result<(), E>>>(
+        &self,
+        concurrency: usize,
+        on_error: OnError,
+        key: impl Fn(&Id) -> K,
+        f: impl Fn(Id) -> Fut,
+    ) -> Result<(), RunAsyncError<Id, E>> {
+        self.run_plan(self.execution_plan_with(key)?, concurrency, on_error, f)
+            .await
+    }
+
+    async fn run_plan<E, Fut: Future<Output = Result<(), E>>>(
+        &self,
+        plan: ExecutionPlan<Id>,
+        concurrency: usize,
+        on_error: OnError,
+        f: impl Fn(Id) -> Fut,
+    ) -> Result<(), RunAsyncError<Id, E>> {
+        assert!(concurrency > 0, "concurrency must be at least 1");
+        let mut running = FuturesUnordered::new();
+        let mut failure = None;
+        loop {
+            while failure.is_none() && running.len() < concurrency {
+                let Some(id) = plan.next_ready() else { break };
+                let fut = f(id.clone());
+                running.push(async move { (id, fut.await) });
+            }
+            match running.next().await {
+                None => break,
+                Some((id, Ok(()))) => plan.complete(&id),
+                Some((id, Err(error))) => {
+                    failure.get_or_insert(RunAsyncError::Node { id, error });
+                    if on_error == OnError::Abort {
+                        break;
+                    }
+                }
+            }
+        }
+        failure.map_or(Ok(()), Err)
+    }
+}
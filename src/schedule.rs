@@ -0,0 +1,138 @@
+use crate::*;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// When and where a node runs in a [`ScheduleEstimate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeSchedule {
+    pub start: u64,
+    pub finish: u64,
+    pub worker: usize,
+}
+
+/// Outcome of [`ResolvedDeps::estimate_makespan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleEstimate<Id: Eq + Hash> {
+    /// Finish time of the last node
+    pub makespan: u64,
+    pub nodes: HashMap<Id, NodeSchedule>,
+    /// Total cost executed per worker, only workers that got to run anything are listed
+    pub busy: Vec<u64>,
+}
+
+impl<Id: Eq + Hash> ScheduleEstimate<Id> {
+    /// Share of the makespan each worker spent running nodes
+    pub fn utilization(&self) -> Vec<f64> {
+        self.busy
+            .iter()
+            .map(|&busy| match self.makespan {
+                0 => 0.0,
+                makespan => busy as f64 / makespan as f64,
+            })
+            .collect()
+    }
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    ResolvedDeps<Id, S>
+{
+    /// Simulates list scheduling of this resolution's nodes on `workers` workers
+    ///
+    /// A node is ready as soon as all of its deps in `graph` finished, there are no level
+    /// barriers, and free workers take the ready node with the highest `cost` first.
+    /// `usize::MAX` workers gives the bound imposed by the dependencies alone
+    ///
+    /// Deps are followed through aliases and groups, soft deps count when they sit at an
+    /// earlier level than their node
+    ///
+    /// Panics if `workers` is 0
+    pub fn estimate_makespan(
+        &self,
+        graph: &DepRes<Id, S>,
+        cost: impl Fn(&Id) -> u64,
+        workers: usize,
+    ) -> ScheduleEstimate<Id> {
+        assert!(workers > 0, "at least one worker is needed");
+        let ids = self.sorted_by_level();
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id, i))
+            .collect::<HashMap<_, _>>();
+        let costs = ids.iter().map(&cost).collect::<Vec<_>>();
+        let mut level_of = HashMap::new();
+        for level in self.iter_level_shared() {
+            for id in level.deps {
+                level_of.insert(&**id, level.level);
+            }
+        }
+        let mut pending = vec![0; ids.len()];
+        let mut dependents = vec![vec![]; ids.len()];
+        for (i, id) in ids.iter().enumerate() {
+            let hard = graph.resolved_deps_of(id);
+            for dep in graph.leveled_deps(id, &[]) {
+                // a soft dep that didn't end up before `id` was dropped to break a cycle
+                if !hard.contains(&dep) && level_of.get(&dep) >= level_of.get(id) {
+                    continue;
+                }
+                if let Some(&dep) = index.get(&dep) {
+                    pending[i] += 1;
+                    dependents[dep].push(i);
+                }
+            }
+        }
+
+        // highest cost first, earlier in level order on ties
+        let mut ready = (0..ids.len())
+            .filter(|&i| pending[i] == 0)
+            .map(|i| (costs[i], Reverse(i)))
+            .collect::<BinaryHeap<_>>();
+        let mut idle = BinaryHeap::new();
+        let mut running = BinaryHeap::new();
+        let mut busy = vec![];
+        let mut schedule = vec![None; ids.len()];
+        let mut now = 0;
+        loop {
+            while let Some(&(cost, Reverse(node))) = ready.peek() {
+                let worker = match idle.pop() {
+                    Some(Reverse(worker)) => worker,
+                    None if busy.len() < workers => {
+                        busy.push(0);
+                        busy.len() - 1
+                    }
+                    None => break,
+                };
+                ready.pop();
+                let finish = now + cost;
+                busy[worker] += cost;
+                schedule[node] = Some(NodeSchedule {
+                    start: now,
+                    finish,
+                    worker,
+                });
+                running.push(Reverse((finish, worker, node)));
+            }
+            let Some(Reverse((finish, worker, node))) = running.pop() else {
+                break;
+            };
+            now = finish;
+            idle.push(Reverse(worker));
+            for &dependent in &dependents[node] {
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    ready.push((costs[dependent], Reverse(dependent)));
+                }
+            }
+        }
+
+        let nodes = ids
+            .into_iter()
+            .zip(schedule)
+            .filter_map(|(id, schedule)| Some((id, schedule?)))
+            .collect::<HashMap<_, _>>();
+        ScheduleEstimate {
+            makespan: nodes.values().map(|n| n.finish).max().unwrap_or(0),
+            nodes,
+            busy,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::*;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// Serialized shape of [`DepRes`]: nodes without deps, plus each other node's sorted deps
+///
+/// ```json
+/// { "no_deps": [0, 2, 3], "deps": { "1": [0], "4": [3], "5": [4] } }
+/// ```
+#[derive(Serialize)]
+struct DepResSorted<Id: Ord> {
+    no_deps: Vec<Id>,
+    deps: BTreeMap<Id, Vec<Id>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "Id: DeserializeOwned + Eq + Hash")]
+struct DepResOwned<Id: Eq + Hash> {
+    no_deps: Vec<Id>,
+    deps: HashMap<Id, Vec<Id>>,
+}
+
+impl<Id: Serialize + Ord + Eq + Hash + Clone, S: BuildHasher + Clone> Serialize for DepRes<Id, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let mut deps = BTreeMap::new();
+        self.deps.for_each(|id, set| {
+            let mut set = set
+                .to_vec()
+                .iter()
+                .map(|a| (**a).clone())
+                .collect::<Vec<_>>();
+            set.sort();
+            deps.insert((**id).clone(), set);
+        });
+        let mut no_deps = (self.ids.to_vec().iter())
+            .filter(|id| !deps.contains_key(&***id))
+            .map(|a| (**a).clone())
+            .collect::<Vec<_>>();
+        no_deps.sort();
+        DepResSorted { no_deps, deps }.serialize(serializer)
+    }
+}
+
+impl<'de, Id: DeserializeOwned + Eq + Hash + Clone, S: BuildHasher + Clone + Default>
+    Deserialize<'de> for DepRes<Id, S>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = DepResOwned::<Id>::deserialize(deserializer)?;
+        let dr = DepRes::<Id, S>::default();
+        for id in repr.no_deps {
+            dr.insert_id(&id);
+        }
+        for (id, deps) in repr.deps {
+            if !deps.is_empty() {
+                dr.insert_deps(&id, &deps);
+            }
+            dr.insert_id(&id);
+        }
+        Ok(dr)
+    }
+}
+
+/// Levels as an array of arrays, level 0 first, each level sorted
+impl<Id: Serialize + Ord + Eq + Hash + Clone, S: BuildHasher + Clone> Serialize
+    for ResolvedDeps<Id, S>
+{
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let levels = self
+            .levels
+            .values()
+            .map(|level| {
+                let mut ids = level.iter().map(|id| &**id).collect::<Vec<_>>();
+                ids.sort();
+                ids
+            })
+            .collect::<Vec<_>>();
+        levels.serialize(serializer)
+    }
+}
+
+impl<'de, Id: DeserializeOwned + Eq + Hash + Clone, S: BuildHasher + Clone + Default>
+    Deserialize<'de> for ResolvedDeps<Id, S>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let levels = Vec::<Vec<Id>>::deserialize(deserializer)?;
+        Ok(ResolvedDeps::from_levels(levels))
+    }
+}
@@ -0,0 +1,144 @@
+use crate::*;
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Orders `id` after `dep` only if `dep` is part of the graph, a missing `dep` is ignored
+    ///
+    /// Cycles made up of soft edges only don't fail a resolve, one of their soft edges gets
+    /// dropped, see [`resolve_with_dropped`](Self::resolve_with_dropped). A cycle with a hard
+    /// edge in it still fails with [`DepResolveError::IslandsOrCircular`]. Neither side becomes
+    /// a node by this, and only resolving looks at soft edges
+    pub fn add_soft_edge(&self, id: Id, dep: Id) {
+        let id = self.intern(&id);
+        self.link(&self.soft, &id, &[dep]);
+        self.touch();
+    }
+
+    /// Like [`resolve`](Self::resolve), also returning the `(node, dep)` soft edges that were
+    /// dropped to break cycles, empty if there was none
+    pub fn resolve_with_dropped(&self) -> Result<WithDropped<Id, S>, DepResolveError<Id>> {
+        self.resolve_nodes_with(&self.ids, ResolveOpts::default(), |_| {})
+    }
+
+    /// Soft edges among `nodes` to drop so no cycle made up of soft edges only is left
+    ///
+    /// Only nodes a plain leveling can't place are looked at. A soft edge `node -> dep` is
+    /// dropped if `dep` still reaches `node` over soft edges, so such a cycle loses just one of
+    /// its soft edges. A cycle with a hard edge keeps all of its edges and fails the resolve.
+    /// Hard edges not kept by `keep` are left out, like leveling does
+    pub(crate) fn soft_cycle_edges(
+        &self,
+        nodes: &Set<Arc<Id>, S>,
+        keep: Option<EdgeFilter<Id>>,
+    ) -> Vec<(Arc<Id>, Arc<Id>)> {
+        let ids = nodes.to_vec();
+        let index = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (&**id, i))
+            .collect::<HashMap<_, _, S>>();
+        let inner = |adjacency: &Adjacency<Id, S>, id: &Id, hard: bool| {
+            let mut inner = vec![];
+            adjacency.read(id, |_, deps| {
+                deps.for_each(|dep| {
+                    if hard && !keep.is_none_or(|keep| keep(id, dep)) {
+                        return;
+                    }
+                    self.for_each_target(dep, &mut |target| {
+                        inner.extend(index.get(target).copied())
+                    });
+                })
+            });
+            inner
+        };
+        // node -> (dep, is soft)
+        let fwd = ids
+            .iter()
+            .map(|id| {
+                let mut deps = inner(&self.deps, id, true)
+                    .into_iter()
+                    .map(|dep| (dep, false))
+                    .collect::<Vec<_>>();
+                deps.extend(
+                    inner(&self.soft, id, false)
+                        .into_iter()
+                        .map(|dep| (dep, true)),
+                );
+                deps
+            })
+            .collect::<Vec<_>>();
+        soft_cycle_drops(fwd)
+            .into_iter()
+            .map(|(node, dep)| (ids[node].clone(), ids[dep].clone()))
+            .collect()
+    }
+}
+
+/// [`DepRes::soft_cycle_edges`] over node indices, `fwd` holds each node's `(dep, is soft)`
+/// edges and the dropped `(node, dep)` soft edges come back
+pub(crate) fn soft_cycle_drops(mut fwd: Vec<Vec<(usize, bool)>>) -> Vec<(usize, usize)> {
+    let mut pending = fwd.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut rev = vec![vec![]; fwd.len()];
+    for (i, deps) in fwd.iter().enumerate() {
+        for &(dep, _) in deps {
+            rev[dep].push(i);
+        }
+    }
+    let mut queue = (0..fwd.len())
+        .filter(|&i| pending[i] == 0)
+        .collect::<Vec<_>>();
+    while let Some(i) = queue.pop() {
+        for &dependent in &rev[i] {
+            pending[dependent] -= 1;
+            if pending[dependent] == 0 {
+                queue.push(dependent);
+            }
+        }
+    }
+    let stuck = pending.iter().map(|&n| n > 0).collect::<Vec<_>>();
+
+    let mut dropped = vec![];
+    let mut seen = vec![0usize; fwd.len()];
+    let mut round = 0;
+    for node in (0..fwd.len()).filter(|&i| stuck[i]) {
+        let mut k = 0;
+        while k < fwd[node].len() {
+            let (dep, soft) = fwd[node][k];
+            round += 1;
+            if soft && stuck[dep] && reaches(&fwd, &stuck, &mut seen, round, dep, node) {
+                fwd[node].swap_remove(k);
+                dropped.push((node, dep));
+            } else {
+                k += 1;
+            }
+        }
+    }
+    dropped
+}
+
+/// Whether `to` can be reached from `from` over the soft edges of `fwd` without leaving the
+/// `stuck` nodes, `seen` is marked with `round` instead of being cleared between calls
+fn reaches(
+    fwd: &[Vec<(usize, bool)>],
+    stuck: &[bool],
+    seen: &mut [usize],
+    round: usize,
+    from: usize,
+    to: usize,
+) -> bool {
+    let mut stack = vec![from];
+    seen[from] = round;
+    while let Some(i) = stack.pop() {
+        if i == to {
+            return true;
+        }
+        for &(dep, soft) in &fwd[i] {
+            if soft && stuck[dep] && seen[dep] != round {
+                seen[dep] = round;
+                stack.push(dep);
+            }
+        }
+    }
+    false
+}
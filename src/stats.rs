@@ -0,0 +1,87 @@
+use crate::*;
+
+/// Shape of a [`DepRes`], see [`DepRes::stats`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub max_out_degree: usize,
+    pub max_in_degree: usize,
+    /// `out_degree_histogram[d]` nodes have `d` deps
+    pub out_degree_histogram: Vec<usize>,
+    /// `in_degree_histogram[d]` nodes have `d` dependents
+    pub in_degree_histogram: Vec<usize>,
+}
+
+/// Shape of a [`ResolvedDeps`], see [`ResolvedDeps::stats`]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LevelStats {
+    pub level_count: usize,
+    pub max_width: usize,
+    pub mean_width: f64,
+    /// Lowest index among the widest levels, `None` for an empty resolution
+    pub widest_level: Option<usize>,
+}
+
+fn histogram(degrees: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut histogram = vec![];
+    for degree in degrees {
+        if histogram.len() <= degree {
+            histogram.resize(degree + 1, 0);
+        }
+        histogram[degree] += 1;
+    }
+    histogram
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Node and edge counts plus degree distributions over the added nodes
+    ///
+    /// Out-degrees include deps on ids that were never added, in-degrees only count
+    /// dependents, which are always added nodes
+    pub fn stats(&self) -> GraphStats {
+        let rev = self.reverse_index();
+        let degrees = (self.ids.to_vec().into_par_iter())
+            .map(|id| {
+                let deps = self.deps.read(&id, |_, deps| deps.len()).unwrap_or(0);
+                let dependents = rev.read(&id, |_, dependents| dependents.len()).unwrap_or(0);
+                (deps, dependents)
+            })
+            .collect::<Vec<_>>();
+        let out_degree_histogram = histogram(degrees.iter().map(|d| d.0));
+        let in_degree_histogram = histogram(degrees.iter().map(|d| d.1));
+        GraphStats {
+            node_count: degrees.len(),
+            edge_count: self.edge_count(),
+            max_out_degree: out_degree_histogram.len().saturating_sub(1),
+            max_in_degree: in_degree_histogram.len().saturating_sub(1),
+            out_degree_histogram,
+            in_degree_histogram,
+        }
+    }
+}
+
+impl<Id: Eq + Hash + Clone, S: BuildHasher + Clone + Default> ResolvedDeps<Id, S> {
+    pub fn stats(&self) -> LevelStats {
+        let widths = self
+            .iter_level()
+            .map(|l| (l.level, l.deps.len()))
+            .collect::<Vec<_>>();
+        let widest = widths
+            .iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)));
+        LevelStats {
+            level_count: widths.len(),
+            max_width: widest.map_or(0, |w| w.1),
+            mean_width: match widths.len() {
+                0 => 0.0,
+                n => self.len() as f64 / n as f64,
+            },
+            widest_level: widest.map(|w| w.0),
+        }
+    }
+}
@@ -0,0 +1,691 @@
+//! The concurrent maps and sets a [`DepRes`](crate::DepRes) keeps its graph in, dashmap with
+//! the `dashmap` feature and a hashbrown map behind a `RwLock` without it
+//!
+//! Everything goes through [`StoreMap`] and [`StoreSet`], whose reads hand entries to a
+//! closure instead of returning guards, so a backend needs no guard types of its own. A
+//! closure must not touch the map it was called from, it may run under a lock of it
+
+use std::borrow::Borrow;
+use std::cell::Cell;
+use std::hash::{BuildHasher, Hash};
+
+#[cfg(feature = "dashmap")]
+pub(crate) type Map<K, V, S> = dashmap::DashMap<K, V, S>;
+#[cfg(feature = "dashmap")]
+pub(crate) type Set<K, S> = dashmap::DashSet<K, S>;
+
+#[cfg(not(feature = "dashmap"))]
+pub(crate) type Map<K, V, S> = locked::LockedMap<K, V, S>;
+#[cfg(not(feature = "dashmap"))]
+pub(crate) type Set<K, S> = locked::LockedSet<K, S>;
+
+/// A map that takes inserts and removals through `&self`
+// dashmap's inherent methods of the same names shadow some of these
+#[cfg_attr(feature = "dashmap", allow(dead_code))]
+pub(crate) trait StoreMap<K: Eq + Hash, V, S: BuildHasher + Clone>: Sized {
+    fn with_hasher(hasher: S) -> Self;
+
+    fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self;
+
+    fn hasher(&self) -> &S;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool;
+
+    fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>;
+
+    fn insert(&self, key: K, value: V) -> Option<V>;
+
+    fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>;
+
+    /// Removes the entry of `key` if `f` agrees
+    fn remove_if<Q: Hash + Eq + ?Sized>(
+        &self,
+        key: &Q,
+        f: impl FnOnce(&K, &V) -> bool,
+    ) -> Option<(K, V)>
+    where
+        K: Borrow<Q>;
+
+    fn retain(&self, f: impl FnMut(&K, &mut V) -> bool);
+
+    /// `f` of the entry of `key`, `None` if there is none
+    fn read<Q: Hash + Eq + ?Sized, R>(&self, key: &Q, f: impl FnOnce(&K, &V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>;
+
+    /// Like [`read`](Self::read) with the value mutable
+    fn modify<Q: Hash + Eq + ?Sized, R>(
+        &self,
+        key: &Q,
+        f: impl FnOnce(&K, &mut V) -> R,
+    ) -> Option<R>
+    where
+        K: Borrow<Q>;
+
+    /// Like [`read`](Self::read) for the entry whose key hashes to `hash` and satisfies `eq`,
+    /// hashed with [`hasher`](Self::hasher), for keys that only borrow as the looked up type
+    fn read_hashed<R>(
+        &self,
+        hash: u64,
+        eq: impl FnMut(&K) -> bool,
+        f: impl FnOnce(&K, &V) -> R,
+    ) -> Option<R>;
+
+    /// `f` of the entry of `key`, inserted from `default` first if there is none, the key
+    /// handed to `f` is the stored one
+    fn get_or_insert_with<R>(
+        &self,
+        key: K,
+        default: impl FnOnce() -> V,
+        f: impl FnOnce(&K, &mut V) -> R,
+    ) -> R;
+
+    /// Inserts `value`, or merges it into the value already there with `merge`
+    fn upsert(&self, key: K, value: V, merge: impl FnOnce(&mut V, V)) {
+        // taken by `default` for a new key, so there is nothing left to merge
+        let value = Cell::new(Some(value));
+        self.get_or_insert_with(
+            key,
+            || value.take().unwrap(),
+            |_, existing| {
+                if let Some(value) = value.take() {
+                    merge(existing, value);
+                }
+            },
+        )
+    }
+
+    /// Calls `f` with every entry, in no particular order
+    fn for_each(&self, f: impl FnMut(&K, &V));
+
+    /// Like [`for_each`](Self::for_each) across the thread pool, sequential without `cfg(parallel)`
+    ///
+    /// Backends that can't iterate without holding a lock go over a copy of the keys and
+    /// read each entry in place, `f` must not write to this map
+    fn par_for_each(&self, f: impl Fn(&K, &V) + Sync + Send)
+    where
+        K: Clone + Send + Sync,
+        V: Send + Sync,
+        S: Send + Sync;
+
+    /// Whether `f` holds for every entry, stopping at the first it doesn't
+    fn all(&self, f: impl FnMut(&K, &V) -> bool) -> bool;
+
+    fn get_cloned<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        V: Clone,
+    {
+        self.read(key, |_, value| value.clone())
+    }
+
+    /// The stored key equal to `key`
+    fn key_of<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q> + Clone,
+    {
+        self.read(key, |key, _| key.clone())
+    }
+
+    /// Copies of the keys, so no lock is held while going through them
+    fn keys(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        let mut keys = Vec::with_capacity(self.len());
+        self.for_each(|key, _| keys.push(key.clone()));
+        keys
+    }
+}
+
+/// A set that takes inserts and removals through `&self`
+// dashmap's inherent methods of the same names shadow some of these
+#[cfg_attr(feature = "dashmap", allow(dead_code))]
+pub(crate) trait StoreSet<K: Eq + Hash, S: BuildHasher + Clone>: Sized {
+    fn with_hasher(hasher: S) -> Self;
+
+    fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool;
+
+    /// Returns whether `key` wasn't in yet
+    fn insert(&self, key: K) -> bool;
+
+    fn contains<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>;
+
+    fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q>;
+
+    fn retain(&self, f: impl FnMut(&K) -> bool);
+
+    /// Calls `f` with every key, in no particular order
+    fn for_each(&self, f: impl FnMut(&K));
+
+    /// Like [`for_each`](Self::for_each) across the thread pool, sequential without
+    /// `cfg(parallel)`, over a copy of the keys where the backend would hold a lock
+    fn par_for_each(&self, f: impl Fn(&K) + Sync + Send)
+    where
+        K: Clone + Send + Sync,
+        S: Send + Sync;
+
+    /// Whether `f` holds for every key, stopping at the first it doesn't
+    fn all(&self, f: impl FnMut(&K) -> bool) -> bool;
+
+    /// Every key, taken out of the set
+    fn into_vec(self) -> Vec<K>;
+
+    /// The stored key equal to `key`
+    fn get_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<K>
+    where
+        K: Borrow<Q> + Clone;
+
+    fn insert_all(&self, keys: impl IntoIterator<Item = K>) {
+        for key in keys {
+            self.insert(key);
+        }
+    }
+
+    /// Copies of the keys, so no lock is held while going through them
+    fn to_vec(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        let mut keys = Vec::with_capacity(self.len());
+        self.for_each(|key| keys.push(key.clone()));
+        keys
+    }
+}
+
+#[cfg(feature = "dashmap")]
+mod dash {
+    use super::*;
+    use dashmap::mapref::entry::Entry;
+    use dashmap::{DashMap, DashSet};
+    #[cfg(parallel)]
+    use rayon::prelude::*;
+
+    impl<K: Eq + Hash, V, S: BuildHasher + Clone> StoreMap<K, V, S> for DashMap<K, V, S> {
+        fn with_hasher(hasher: S) -> Self {
+            DashMap::with_hasher(hasher)
+        }
+
+        fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+            DashMap::with_capacity_and_hasher(capacity, hasher)
+        }
+
+        fn hasher(&self) -> &S {
+            DashMap::hasher(self)
+        }
+
+        fn len(&self) -> usize {
+            DashMap::len(self)
+        }
+
+        fn is_empty(&self) -> bool {
+            DashMap::is_empty(self)
+        }
+
+        fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+        where
+            K: Borrow<Q>,
+        {
+            DashMap::contains_key(self, key)
+        }
+
+        fn insert(&self, key: K, value: V) -> Option<V> {
+            DashMap::insert(self, key, value)
+        }
+
+        fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<(K, V)>
+        where
+            K: Borrow<Q>,
+        {
+            DashMap::remove(self, key)
+        }
+
+        fn remove_if<Q: Hash + Eq + ?Sized>(
+            &self,
+            key: &Q,
+            f: impl FnOnce(&K, &V) -> bool,
+        ) -> Option<(K, V)>
+        where
+            K: Borrow<Q>,
+        {
+            DashMap::remove_if(self, key, f)
+        }
+
+        fn retain(&self, f: impl FnMut(&K, &mut V) -> bool) {
+            DashMap::retain(self, f)
+        }
+
+        fn read<Q: Hash + Eq + ?Sized, R>(&self, key: &Q, f: impl FnOnce(&K, &V) -> R) -> Option<R>
+        where
+            K: Borrow<Q>,
+        {
+            self.get(key).map(|entry| f(entry.key(), entry.value()))
+        }
+
+        fn modify<Q: Hash + Eq + ?Sized, R>(
+            &self,
+            key: &Q,
+            f: impl FnOnce(&K, &mut V) -> R,
+        ) -> Option<R>
+        where
+            K: Borrow<Q>,
+        {
+            self.get_mut(key).map(|mut entry| {
+                let (key, value) = entry.pair_mut();
+                f(key, value)
+            })
+        }
+
+        fn read_hashed<R>(
+            &self,
+            hash: u64,
+            mut eq: impl FnMut(&K) -> bool,
+            f: impl FnOnce(&K, &V) -> R,
+        ) -> Option<R> {
+            let shard = self.shards()[self.determine_shard(hash as usize)].read();
+            let (key, value) = shard.raw_entry().from_hash(hash, |key| eq(key))?;
+            Some(f(key, value.get()))
+        }
+
+        fn get_or_insert_with<R>(
+            &self,
+            key: K,
+            default: impl FnOnce() -> V,
+            f: impl FnOnce(&K, &mut V) -> R,
+        ) -> R {
+            let mut entry = match self.entry(key) {
+                Entry::Occupied(entry) => entry.into_ref(),
+                Entry::Vacant(entry) => entry.insert(default()),
+            };
+            let (key, value) = entry.pair_mut();
+            f(key, value)
+        }
+
+        fn for_each(&self, mut f: impl FnMut(&K, &V)) {
+            self.iter().for_each(|entry| f(entry.key(), entry.value()))
+        }
+
+        fn par_for_each(&self, f: impl Fn(&K, &V) + Sync + Send)
+        where
+            K: Clone + Send + Sync,
+            V: Send + Sync,
+            S: Send + Sync,
+        {
+            #[cfg(parallel)]
+            self.par_iter()
+                .for_each(|entry| f(entry.key(), entry.value()));
+            #[cfg(not(parallel))]
+            self.for_each(f);
+        }
+
+        fn all(&self, mut f: impl FnMut(&K, &V) -> bool) -> bool {
+            self.iter().all(|entry| f(entry.key(), entry.value()))
+        }
+    }
+
+    impl<K: Eq + Hash, S: BuildHasher + Clone> StoreSet<K, S> for DashSet<K, S> {
+        fn with_hasher(hasher: S) -> Self {
+            DashSet::with_hasher(hasher)
+        }
+
+        fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+            DashSet::with_capacity_and_hasher(capacity, hasher)
+        }
+
+        fn len(&self) -> usize {
+            DashSet::len(self)
+        }
+
+        fn is_empty(&self) -> bool {
+            DashSet::is_empty(self)
+        }
+
+        fn insert(&self, key: K) -> bool {
+            DashSet::insert(self, key)
+        }
+
+        fn contains<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+        where
+            K: Borrow<Q>,
+        {
+            DashSet::contains(self, key)
+        }
+
+        fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<K>
+        where
+            K: Borrow<Q>,
+        {
+            DashSet::remove(self, key)
+        }
+
+        fn retain(&self, f: impl FnMut(&K) -> bool) {
+            DashSet::retain(self, f)
+        }
+
+        fn for_each(&self, mut f: impl FnMut(&K)) {
+            // shard by shard, unlike `DashSet::iter` this allocates nothing
+            for shard in self.shards() {
+                for (key, _) in shard.read().iter() {
+                    f(key);
+                }
+            }
+        }
+
+        fn par_for_each(&self, f: impl Fn(&K) + Sync + Send)
+        where
+            K: Clone + Send + Sync,
+            S: Send + Sync,
+        {
+            #[cfg(parallel)]
+            self.par_iter().for_each(|key| f(key.key()));
+            #[cfg(not(parallel))]
+            self.for_each(f);
+        }
+
+        fn all(&self, mut f: impl FnMut(&K) -> bool) -> bool {
+            self.iter().all(|key| f(key.key()))
+        }
+
+        fn into_vec(self) -> Vec<K> {
+            self.into_iter().collect()
+        }
+
+        fn get_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<K>
+        where
+            K: Borrow<Q> + Clone,
+        {
+            self.get(key).map(|key| key.key().clone())
+        }
+    }
+}
+
+#[cfg(not(feature = "dashmap"))]
+mod locked {
+    use super::*;
+    use hashbrown::hash_map::RawEntryMut;
+    use std::fmt::{self, Debug, Formatter};
+    use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+    #[cfg(parallel)]
+    use rayon::prelude::*;
+
+    /// A hashbrown map behind one `RwLock`, the hasher is kept outside so it can be handed
+    /// out without a guard
+    pub(crate) struct LockedMap<K, V, S> {
+        map: RwLock<hashbrown::HashMap<K, V, S>>,
+        hasher: S,
+    }
+
+    impl<K, V, S> LockedMap<K, V, S> {
+        // a panic under the lock leaves the map as consistent as dashmap would
+        fn read_lock(&self) -> RwLockReadGuard<'_, hashbrown::HashMap<K, V, S>> {
+            self.map.read().unwrap_or_else(PoisonError::into_inner)
+        }
+
+        fn write_lock(&self) -> RwLockWriteGuard<'_, hashbrown::HashMap<K, V, S>> {
+            self.map.write().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    impl<K: Clone, V: Clone, S: Clone> Clone for LockedMap<K, V, S> {
+        fn clone(&self) -> Self {
+            Self {
+                map: RwLock::new(self.read_lock().clone()),
+                hasher: self.hasher.clone(),
+            }
+        }
+    }
+
+    impl<K: Debug, V: Debug, S> Debug for LockedMap<K, V, S> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.debug_map().entries(self.read_lock().iter()).finish()
+        }
+    }
+
+    impl<K: Eq + Hash, V, S: BuildHasher + Clone> StoreMap<K, V, S> for LockedMap<K, V, S> {
+        fn with_hasher(hasher: S) -> Self {
+            Self::with_capacity_and_hasher(0, hasher)
+        }
+
+        fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+            Self {
+                map: RwLock::new(hashbrown::HashMap::with_capacity_and_hasher(
+                    capacity,
+                    hasher.clone(),
+                )),
+                hasher,
+            }
+        }
+
+        fn hasher(&self) -> &S {
+            &self.hasher
+        }
+
+        fn len(&self) -> usize {
+            self.read_lock().len()
+        }
+
+        fn is_empty(&self) -> bool {
+            self.read_lock().is_empty()
+        }
+
+        fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+        where
+            K: Borrow<Q>,
+        {
+            self.read_lock().contains_key(key)
+        }
+
+        fn insert(&self, key: K, value: V) -> Option<V> {
+            self.write_lock().insert(key, value)
+        }
+
+        fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<(K, V)>
+        where
+            K: Borrow<Q>,
+        {
+            self.write_lock().remove_entry(key)
+        }
+
+        fn remove_if<Q: Hash + Eq + ?Sized>(
+            &self,
+            key: &Q,
+            f: impl FnOnce(&K, &V) -> bool,
+        ) -> Option<(K, V)>
+        where
+            K: Borrow<Q>,
+        {
+            let mut map = self.write_lock();
+            let (k, value) = map.get_key_value(key)?;
+            match f(k, value) {
+                true => map.remove_entry(key),
+                false => None,
+            }
+        }
+
+        fn retain(&self, mut f: impl FnMut(&K, &mut V) -> bool) {
+            self.write_lock().retain(|key, value| f(key, value))
+        }
+
+        fn read<Q: Hash + Eq + ?Sized, R>(&self, key: &Q, f: impl FnOnce(&K, &V) -> R) -> Option<R>
+        where
+            K: Borrow<Q>,
+        {
+            let map = self.read_lock();
+            let (key, value) = map.get_key_value(key)?;
+            Some(f(key, value))
+        }
+
+        fn modify<Q: Hash + Eq + ?Sized, R>(
+            &self,
+            key: &Q,
+            f: impl FnOnce(&K, &mut V) -> R,
+        ) -> Option<R>
+        where
+            K: Borrow<Q>,
+        {
+            let mut map = self.write_lock();
+            let (key, value) = map.get_key_value_mut(key)?;
+            Some(f(key, value))
+        }
+
+        fn read_hashed<R>(
+            &self,
+            hash: u64,
+            eq: impl FnMut(&K) -> bool,
+            f: impl FnOnce(&K, &V) -> R,
+        ) -> Option<R> {
+            let map = self.read_lock();
+            let (key, value) = map.raw_entry().from_hash(hash, eq)?;
+            Some(f(key, value))
+        }
+
+        fn get_or_insert_with<R>(
+            &self,
+            key: K,
+            default: impl FnOnce() -> V,
+            f: impl FnOnce(&K, &mut V) -> R,
+        ) -> R {
+            let mut map = self.write_lock();
+            let (key, value) = match map.raw_entry_mut().from_key(&key) {
+                RawEntryMut::Occupied(entry) => entry.into_key_value(),
+                RawEntryMut::Vacant(entry) => entry.insert(key, default()),
+            };
+            f(key, value)
+        }
+
+        fn for_each(&self, mut f: impl FnMut(&K, &V)) {
+            self.read_lock()
+                .iter()
+                .for_each(|(key, value)| f(key, value))
+        }
+
+        fn par_for_each(&self, f: impl Fn(&K, &V) + Sync + Send)
+        where
+            K: Clone + Send + Sync,
+            V: Send + Sync,
+            S: Send + Sync,
+        {
+            // the lock is only held for one entry at a time, so writers get their turn in
+            // between, values are never copied. Keys gone since are skipped
+            let keys = self.keys();
+            let visit = |key: &K| {
+                self.read(key, |key, value| f(key, value));
+            };
+            #[cfg(parallel)]
+            keys.par_iter().for_each(visit);
+            #[cfg(not(parallel))]
+            keys.iter().for_each(visit);
+        }
+
+        fn all(&self, mut f: impl FnMut(&K, &V) -> bool) -> bool {
+            self.read_lock().iter().all(|(key, value)| f(key, value))
+        }
+    }
+
+    /// A [`LockedMap`] without values
+    pub(crate) struct LockedSet<K, S>(LockedMap<K, (), S>);
+
+    impl<K: Clone, S: Clone> Clone for LockedSet<K, S> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+
+    impl<K: Debug, S> Debug for LockedSet<K, S> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.debug_set().entries(self.0.read_lock().keys()).finish()
+        }
+    }
+
+    impl<K: Eq + Hash, S: BuildHasher + Clone> StoreSet<K, S> for LockedSet<K, S> {
+        fn with_hasher(hasher: S) -> Self {
+            Self(LockedMap::with_hasher(hasher))
+        }
+
+        fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+            Self(LockedMap::with_capacity_and_hasher(capacity, hasher))
+        }
+
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+
+        fn insert(&self, key: K) -> bool {
+            self.0.insert(key, ()).is_none()
+        }
+
+        fn contains<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+        where
+            K: Borrow<Q>,
+        {
+            self.0.contains_key(key)
+        }
+
+        fn remove<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<K>
+        where
+            K: Borrow<Q>,
+        {
+            self.0.remove(key).map(|(key, _)| key)
+        }
+
+        fn retain(&self, mut f: impl FnMut(&K) -> bool) {
+            self.0.retain(|key, _| f(key))
+        }
+
+        fn for_each(&self, mut f: impl FnMut(&K)) {
+            self.0.for_each(|key, _| f(key))
+        }
+
+        fn par_for_each(&self, f: impl Fn(&K) + Sync + Send)
+        where
+            K: Clone + Send + Sync,
+            S: Send + Sync,
+        {
+            // the copied keys are all `f` needs, no lock is held while it runs
+            let keys = self.0.keys();
+            #[cfg(parallel)]
+            keys.par_iter().for_each(f);
+            #[cfg(not(parallel))]
+            keys.iter().for_each(f);
+        }
+
+        fn all(&self, mut f: impl FnMut(&K) -> bool) -> bool {
+            self.0.all(|key, _| f(key))
+        }
+
+        fn into_vec(self) -> Vec<K> {
+            let map = self
+                .0
+                .map
+                .into_inner()
+                .unwrap_or_else(PoisonError::into_inner);
+            map.into_keys().collect()
+        }
+
+        fn get_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<K>
+        where
+            K: Borrow<Q> + Clone,
+        {
+            self.0.key_of(key)
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::*;
+use std::ops::ControlFlow;
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Resolves like [`resolve`](Self::resolve), but hands each level to `on_level` as soon
+    /// as it is placed instead of collecting them, in ascending order with the same ids in
+    /// the same order
+    ///
+    /// Only the level at hand and the next one are kept while leveling, so memory doesn't
+    /// grow with the number of levels. Once `on_level` breaks no further level is computed
+    /// and `Ok` comes back
+    ///
+    /// Errors show up late: the levels leading up to a cycle or to nodes with missing deps
+    /// are all handed out before [`DepResolveError::IslandsOrCircular`] is returned, as is
+    /// everything up to the limit before [`DepResolveError::DepthLimitExceeded`]. Soft deps
+    /// closing cycles are looked for upfront when the graph has soft deps, with no second
+    /// attempt once levels are out
+    pub fn resolve_streaming(
+        &self,
+        mut on_level: impl FnMut(usize, Vec<Id>) -> ControlFlow<()>,
+    ) -> Result<(), DepResolveError<Id>> {
+        let opts = ResolveOpts::default();
+        let dropped = match self.soft.is_empty() {
+            true => vec![],
+            false => self.soft_cycle_edges(&self.ids, opts.keep),
+        };
+        let interned = interned::Interned::new(self, &self.ids, &opts, &dropped);
+        // breaking is the caller stopping early, nothing to report
+        interned
+            .walk(
+                |_| {},
+                &opts,
+                self.max_levels(),
+                self.parallel_threshold(),
+                |lv, ids| on_level(lv, interned.level_ids(ids)),
+            )
+            .map(|_| ())
+    }
+}
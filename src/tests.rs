@@ -1,47 +1,30 @@
 use crate::*;
-
-#[derive(Debug)]
-struct SimpleDep {
-    pub id: usize,
-    pub deps: Vec<usize>,
-}
-
-impl DepMeta for SimpleDep {
-    type Id = usize;
-
-    fn get_id(&self) -> Self::Id {
-        self.id
-    }
-
-    fn get_deps(&self) -> &[Self::Id] {
-        &*self.deps
-    }
-}
+use std::collections::HashSet;
 
 #[test]
 fn test_1() {
     let items = vec![
-        SimpleDep {
+        Dep {
             id: 0,
             deps: vec![],
         },
-        SimpleDep {
+        Dep {
             id: 1,
             deps: vec![0],
         },
-        SimpleDep {
+        Dep {
             id: 2,
             deps: vec![],
         },
-        SimpleDep {
+        Dep {
             id: 3,
             deps: vec![],
         },
-        SimpleDep {
+        Dep {
             id: 4,
             deps: vec![3],
         },
-        SimpleDep {
+        Dep {
             id: 5,
             deps: vec![4],
         },
@@ -57,4 +40,4234 @@ fn test_1() {
     println!("");
     let levels = r.iter_level().collect::<Vec<_>>();
     println!("{:?}", levels);
+
+    assert_eq!(items.len(), 6);
+    assert_eq!(r.len(), 6);
+    assert_eq!(r.level_count(), 3);
+    assert_eq!(r.max_level(), Some(2));
+    assert_eq!(levels.len(), r.level_count());
+    let level = |n| sorted(r.get_level(n).unwrap().iter().map(|a| *a).collect());
+    assert_eq!(level(0), vec![0, 2, 3]);
+    assert_eq!(level(1), vec![1, 4]);
+    assert_eq!(level(2), vec![5]);
+    assert!(r.get_level(3).is_none());
+}
+
+fn dep(id: usize, deps: &[usize]) -> Dep<usize> {
+    Dep::with_deps(id, deps.iter().copied())
+}
+
+fn sorted(mut ids: Vec<usize>) -> Vec<usize> {
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_dependents_of() {
+    let items = vec![
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[0]),
+        dep(3, &[0, 9]),
+        dep(4, &[1]),
+    ];
+    let dr = DepRes::new();
+    dr.add(&items);
+    assert_eq!(sorted(dr.dependents_of(&0)), vec![1, 2, 3]);
+    assert_eq!(dr.dependents_of(&1), vec![4]);
+    assert!(dr.dependents_of(&4).is_empty());
+    assert!(dr.dependents_of(&42).is_empty());
+    // 9 was never added as an item but is still depended on
+    assert_eq!(dr.dependents_of(&9), vec![3]);
+
+    dr.add(&vec![dep(5, &[4]), dep(6, &[0])]);
+    assert_eq!(dr.dependents_of(&4), vec![5]);
+    assert_eq!(sorted(dr.dependents_of(&0)), vec![1, 2, 3, 6]);
+}
+
+fn hset(ids: &[usize]) -> HashSet<usize> {
+    ids.iter().copied().collect()
+}
+
+#[test]
+fn test_transitive_deps() {
+    let dr = DepRes::new();
+    dr.add(&vec![dep(0, &[]), dep(1, &[0]), dep(2, &[1]), dep(3, &[2])]);
+    assert_eq!(dr.transitive_deps(&3).unwrap(), hset(&[0, 1, 2]));
+    assert_eq!(dr.transitive_deps(&1).unwrap(), hset(&[0]));
+    assert!(dr.transitive_deps(&0).unwrap().is_empty());
+
+    // diamond, 0 is shared by both branches
+    let dr = DepRes::new();
+    dr.add(&vec![
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[0]),
+        dep(3, &[1, 2]),
+    ]);
+    assert_eq!(dr.transitive_deps(&3).unwrap(), hset(&[0, 1, 2]));
+}
+
+#[test]
+fn test_transitive_deps_cycle() {
+    let dr = DepRes::new();
+    dr.add(&vec![
+        dep(0, &[]),
+        dep(1, &[0, 3]),
+        dep(2, &[1]),
+        dep(3, &[2]),
+        dep(4, &[0]),
+    ]);
+    assert_eq!(
+        dr.transitive_deps(&2),
+        Err(DepResolveError::IslandsOrCircular)
+    );
+    // closure that doesn't reach the cycle is still fine
+    assert_eq!(dr.transitive_deps(&4).unwrap(), hset(&[0]));
+}
+
+fn diamond() -> Vec<Dep<usize>> {
+    vec![dep(0, &[]), dep(1, &[0]), dep(2, &[0]), dep(3, &[1, 2])]
+}
+
+#[test]
+fn test_transitive_dependents() {
+    let dr = DepRes::new();
+    dr.add(&diamond());
+    assert!(dr.transitive_dependents(&3).is_empty());
+    assert_eq!(dr.transitive_dependents(&1), hset(&[3]));
+    assert_eq!(dr.transitive_dependents(&0), hset(&[1, 2, 3]));
+}
+
+#[test]
+fn test_impacted_levels() {
+    let dr = DepRes::new();
+    dr.add(&diamond());
+
+    let r = dr.impacted_levels(&[3]).unwrap();
+    assert_eq!(r.sorted_by_level(), vec![3]);
+
+    let r = dr.impacted_levels(&[0]).unwrap();
+    assert_eq!(r.iter_level().count(), 3);
+    assert!(r.level(0).deps.contains(&0));
+    assert!(r.level(1).deps.contains(&1));
+    assert!(r.level(1).deps.contains(&2));
+    assert!(r.level(2).deps.contains(&3));
+
+    // 0 is outside the impacted set so 1 is a root here
+    let r = dr.impacted_levels(&[1]).unwrap();
+    assert_eq!(r.sorted_by_level(), vec![1, 3]);
+}
+
+#[test]
+fn test_depends_on() {
+    let dr = DepRes::new();
+    let mut items = diamond();
+    items.extend((4..1000).map(|i| dep(i, &[i - 1])));
+    dr.add(&items);
+    assert!(dr.depends_on(&1, &0));
+    assert!(dr.depends_on(&3, &0));
+    assert!(dr.depends_on(&999, &0));
+    assert!(!dr.depends_on(&0, &999));
+    assert!(!dr.depends_on(&1, &2));
+    assert!(!dr.depends_on(&0, &0));
+    assert!(!dr.depends_on(&42000, &0));
+    assert!(!dr.depends_on(&0, &42000));
+}
+
+#[test]
+fn test_depends_on_wide() {
+    let dr = DepRes::new();
+    let mut items = vec![dep(0, &[])];
+    items.extend((1..2000).map(|i| dep(i, &[0])));
+    items.push(dep(2000, &(1..2000).collect::<Vec<_>>()));
+    dr.add(&items);
+    assert!(dr.depends_on(&2000, &0));
+    assert!(dr.depends_on(&2000, &1500));
+    assert!(!dr.depends_on(&1500, &2000));
+}
+
+#[test]
+fn test_depends_on_cycle() {
+    let dr = DepRes::new();
+    dr.add(&vec![dep(0, &[2]), dep(1, &[0]), dep(2, &[1]), dep(3, &[])]);
+    assert!(dr.depends_on(&0, &1));
+    assert!(dr.depends_on(&0, &0));
+    assert!(!dr.depends_on(&0, &3));
+}
+
+fn assert_chain(dr: &DepRes<usize>, path: &[usize]) {
+    for hop in path.windows(2) {
+        assert!(
+            dr.resolved_deps_of(&hop[0]).contains(&hop[1]),
+            "{} -> {} is not an edge",
+            hop[0],
+            hop[1]
+        );
+    }
+}
+
+#[test]
+fn test_path_between() {
+    let dr = DepRes::new();
+    // long route 5 -> 4 -> 3 -> 2 -> 0 and short route 5 -> 1 -> 0
+    dr.add(&vec![
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[0]),
+        dep(3, &[2]),
+        dep(4, &[3]),
+        dep(5, &[4, 1]),
+        dep(6, &[]),
+    ]);
+    let path = dr.path_between(&5, &0).unwrap();
+    assert_chain(&dr, &path);
+    assert_eq!(path, vec![5, 1, 0]);
+    let path = dr.path_between(&4, &0).unwrap();
+    assert_chain(&dr, &path);
+    assert_eq!(path, vec![4, 3, 2, 0]);
+    assert_eq!(dr.path_between(&0, &5), None);
+    assert_eq!(dr.path_between(&5, &6), None);
+    // no cycle through 5, it is no path to itself
+    assert_eq!(dr.path_between(&5, &5), None);
+    assert_eq!(dr.path_between(&5, &42), None);
+
+    // borrowed forms of the ids are taken
+    let dr = DepRes::<String>::new();
+    dr.add_iter([
+        Dep::new("a".to_string()),
+        Dep::with_deps("b".into(), ["a".into()]),
+    ]);
+    assert_eq!(dr.path_between("b", "a"), Some(vec!["b".into(), "a".into()]));
+    assert_eq!(dr.path_between("a", "b"), None);
+}
+
+#[test]
+fn test_path_between_cycle() {
+    let dr = DepRes::new();
+    dr.add(&vec![
+        dep(0, &[2]),
+        dep(1, &[0]),
+        dep(2, &[1]),
+        dep(3, &[3]),
+        dep(4, &[0]),
+    ]);
+    assert_eq!(dr.path_between(&3, &3), Some(vec![3, 3]));
+    let path = dr.path_between(&0, &0).unwrap();
+    assert_chain(&dr, &path);
+    assert_eq!(path, vec![0, 2, 1, 0]);
+    assert_eq!(dr.path_between(&0, &4), None);
+}
+
+#[test]
+fn test_queries_through_aliases() {
+    let dr = DepRes::new();
+    dr.add(&vec![
+        dep(0, &[]),
+        dep(1, &[]),
+        dep(2, &[10]),
+        dep(3, &[11]),
+        dep(4, &[2]),
+    ]);
+    dr.add_alias(10, 0).unwrap();
+    dr.define_group(11, [1, 2]).unwrap();
+
+    assert_eq!(dr.dependents_of(&0), vec![2]);
+    assert_eq!(dr.dependents_of(&10), vec![2]);
+    assert_eq!(sorted(dr.dependents_of(&2)), vec![3, 4]);
+    assert_eq!(dr.transitive_dependents(&0), hset(&[2, 3, 4]));
+    let r = dr.impacted_levels(&[0]).unwrap();
+    assert_eq!(r.iter_level().count(), 3);
+    assert!(r.level(1).deps.contains(&2));
+    assert!(r.level(2).deps.contains(&3));
+
+    assert_eq!(dr.transitive_deps(&3).unwrap(), hset(&[0, 1, 2]));
+    assert!(dr.depends_on(&3, &0));
+    assert!(!dr.depends_on(&3, &11));
+    let path = dr.path_between(&3, &0).unwrap();
+    assert_chain(&dr, &path);
+    assert_eq!(path, vec![3, 2, 0]);
+}
+
+#[test]
+fn test_resolve_for_targets() {
+    let dr = DepRes::new();
+    let mut items = diamond();
+    // unrelated broken corner
+    items.extend([dep(10, &[11]), dep(11, &[10]), dep(12, &[])]);
+    dr.add(&items);
+
+    let r = dr.resolve_for_targets(&[1]).unwrap();
+    assert_eq!(r.sorted_by_level(), vec![0, 1]);
+    let r = dr.resolve_for_targets(&[3, 12]).unwrap();
+    assert_eq!(sorted(r.sorted_by_level()), vec![0, 1, 2, 3, 12]);
+    assert_eq!(r.iter_level().count(), 3);
+    assert!(r.level(2).deps.contains(&3));
+
+    assert_eq!(
+        dr.resolve_for_targets(&[3, 10]).unwrap_err(),
+        DepResolveError::IslandsOrCircular
+    );
+    assert_eq!(
+        dr.resolve_for_targets(&[3, 40, 41]).unwrap_err(),
+        DepResolveError::UnknownTargets(vec![40, 41])
+    );
+    assert_eq!(
+        dr.resolve_for_targets(&[40]).unwrap_err().to_string(),
+        "unknown target ids: [40]"
+    );
+
+    // deps through an alias and a group reach their targets and members
+    dr.add(&vec![dep(20, &[]), dep(21, &[]), dep(22, &[30, 31])]);
+    dr.add_alias(30, 20).unwrap();
+    dr.define_group(31, [21, 12]).unwrap();
+    let r = dr.resolve_for_targets(&[22]).unwrap();
+    assert_eq!(r.iter_level().count(), 2);
+    assert_eq!(sorted(r.level(0).deps.iter().copied().collect()), vec![12, 20, 21]);
+    assert!(r.level(1).deps.contains(&22));
+}
+
+#[test]
+fn test_subgraph() {
+    let mut dr = DepRes::new();
+    let mut items = diamond();
+    items.extend([dep(4, &[3]), dep(5, &[1, 9]), dep(6, &[]), dep(7, &[6])]);
+    dr.add(&items);
+
+    let mut sub = dr.subgraph(&[4]);
+    assert_eq!(sub.ids.len(), 5);
+    assert!(!sub.ids.contains(&5));
+    assert!(!sub.ids.contains(&6));
+    let full = dr.resolve().unwrap();
+    let r = sub.resolve().unwrap();
+    for level in r.iter_level() {
+        let full_level = full.level(level.level).deps;
+        let full_level = full_level
+            .iter()
+            .copied()
+            .filter(|id| sub.ids.contains(id))
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            level.deps.iter().map(|id| **id).collect::<HashSet<_>>(),
+            full_level
+        );
+    }
+
+    // the missing dep 9 stays dangling
+    let sub = dr.subgraph(&[5]);
+    assert!(!sub.ids.contains(&9));
+    assert!(sub.deps_of(&5).contains(&9));
+
+    // aliases and groups on the way are copied, the ones not reached are not
+    dr.add(&vec![dep(8, &[20, 21])]);
+    dr.add_alias(20, 21).unwrap();
+    dr.define_group(21, [6, 22]).unwrap();
+    dr.add_alias(22, 0).unwrap();
+    dr.add_alias(23, 7).unwrap();
+    let mut sub = dr.subgraph(&[8]);
+    assert_eq!(sorted(sub.ids.to_vec().iter().map(|id| **id).collect()), vec![0, 6, 8]);
+    assert_eq!(sub.group_members(&21), vec![6, 22]);
+    assert_eq!(sub.aliases.len(), 2);
+    assert_eq!(sorted(sub.resolved_deps_of(&8)), vec![0, 6]);
+    let r = sub.resolve().unwrap();
+    assert_eq!(r.iter_level().count(), 2);
+    assert!(r.level(1).deps.contains(&8));
+}
+
+fn fixture() -> Vec<Dep<usize>> {
+    vec![
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[]),
+        dep(3, &[]),
+        dep(4, &[3]),
+        dep(5, &[4]),
+    ]
+}
+
+#[test]
+fn test_counts() {
+    let dr = DepRes::<usize>::new();
+    assert!(dr.is_empty());
+    assert_eq!(dr.node_count(), 0);
+    assert_eq!(dr.edge_count(), 0);
+    assert!(!dr.contains(&0));
+    assert_eq!(dr.ids().count(), 0);
+
+    dr.add(&fixture());
+    assert!(!dr.is_empty());
+    assert_eq!(dr.node_count(), 6);
+    assert_eq!(dr.edge_count(), 3);
+    assert!(dr.contains(&5));
+    assert!(!dr.contains(&6));
+    assert_eq!(sorted(dr.ids().collect()), vec![0, 1, 2, 3, 4, 5]);
+
+    // duplicate edges are stored once, dep-only ids are not nodes
+    dr.add(&vec![dep(5, &[4, 4, 9])]);
+    assert_eq!(dr.node_count(), 6);
+    assert_eq!(dr.edge_count(), 4);
+    assert!(!dr.contains(&9));
+}
+
+#[test]
+fn test_counts_concurrent_add() {
+    let dr = DepRes::new();
+    std::thread::scope(|s| {
+        for t in 0..4 {
+            let dr = &dr;
+            s.spawn(move || {
+                let items = (0..500).map(|i| dep(t * 500 + i, &[i])).collect::<Vec<_>>();
+                dr.add(&items);
+            });
+        }
+    });
+    assert_eq!(dr.node_count(), 2000);
+    assert_eq!(dr.edge_count(), 2000);
+    assert_eq!(dr.ids().count(), 2000);
+}
+
+/// Deterministic xorshift so the tests don't need a rand dependency
+fn random_dag(n: usize, max_deps: usize, seed: u64) -> Vec<Dep<usize>> {
+    let mut state = seed.max(1);
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+    (0..n)
+        .map(|id| {
+            let deps = if id == 0 {
+                vec![]
+            } else {
+                (0..next() % (max_deps + 1)).map(|_| next() % id).collect()
+            };
+            Dep { id, deps }
+        })
+        .collect()
+}
+
+#[test]
+fn test_edges() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let edges = dr.edges().collect::<HashSet<_>>();
+    assert_eq!(edges, HashSet::from([(1, 0), (4, 3), (5, 4)]));
+    assert_eq!(dr.edges().count(), 3);
+    #[cfg(parallel)]
+    assert_eq!(dr.par_edges().collect::<HashSet<_>>(), edges);
+}
+
+#[cfg(parallel)]
+#[test]
+fn test_par_edges() {
+    let dr = DepRes::new();
+    dr.add(&random_dag(5000, 8, 42));
+    assert!(dr.edge_count() > 0);
+    assert_eq!(dr.par_edges().count(), dr.edge_count());
+    assert_eq!(dr.edges().count(), dr.edge_count());
+}
+
+#[test]
+fn test_edges_concurrent_add() {
+    let dr = DepRes::new();
+    dr.add(&random_dag(2000, 4, 7));
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for chunk in random_dag(4000, 4, 9)[2000..].chunks(100) {
+                dr.add(&chunk.iter().collect::<Vec<_>>());
+            }
+        });
+        for _ in 0..10 {
+            assert!(dr.edges().count() > 0);
+        }
+    });
+    assert_eq!(dr.edges().count(), dr.edge_count());
+}
+
+#[test]
+fn test_diff() {
+    let a = DepRes::new();
+    a.add(&fixture());
+    let b = DepRes::new();
+    b.add(&fixture().into_iter().rev().collect::<Vec<_>>());
+    assert!(a.diff(&b).is_empty());
+
+    let mut items = fixture();
+    items[5].deps.clear();
+    items.push(dep(6, &[]));
+    let c = DepRes::new();
+    c.add(&items);
+    let d = a.diff(&c);
+    assert!(!d.is_empty());
+    assert_eq!(d.added_nodes, vec![6]);
+    assert!(d.removed_nodes.is_empty());
+    assert!(d.added_edges.is_empty());
+    assert_eq!(d.removed_edges, vec![(5, 4)]);
+
+    let d = c.diff(&a);
+    assert_eq!(d.removed_nodes, vec![6]);
+    assert_eq!(d.added_edges, vec![(5, 4)]);
+}
+
+#[test]
+fn test_remove() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    assert!(dr.remove(&4));
+    assert!(!dr.remove(&4));
+    assert!(!dr.contains(&4));
+    assert_eq!(dr.edge_count(), 2);
+    assert_eq!(dr.dependents_of(&4), vec![5]);
+    assert!(dr.dependents_of(&3).is_empty());
+}
+
+#[test]
+fn test_clone() {
+    let mut a = DepRes::new();
+    a.add(&fixture());
+    let mut b = a.clone();
+    assert!(a.diff(&b).is_empty());
+
+    a.remove(&0);
+    a.remove(&1);
+    assert_eq!(b.node_count(), 6);
+    assert_eq!(b.edge_count(), 3);
+    assert_eq!(
+        sorted(a.resolve().unwrap().sorted_by_level()),
+        vec![2, 3, 4, 5]
+    );
+    assert_eq!(
+        sorted(b.resolve().unwrap().sorted_by_level()),
+        vec![0, 1, 2, 3, 4, 5]
+    );
+
+    b.add(&vec![dep(7, &[5])]);
+    assert!(!a.contains(&7));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_dep_res() {
+    let mut items = fixture();
+    // 9 only appears as a dep
+    items.push(dep(6, &[9, 5]));
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let json = serde_json::to_string(&dr).unwrap();
+    assert_eq!(
+        json,
+        r#"{"no_deps":[0,2,3],"deps":{"1":[0],"4":[3],"5":[4],"6":[5,9]}}"#
+    );
+    let mut back: DepRes<usize> = serde_json::from_str(&json).unwrap();
+    assert!(dr.diff(&back).is_empty());
+    assert!(!back.contains(&9));
+    assert_eq!(
+        sorted(back.resolve().unwrap().sorted_by_level()),
+        sorted(dr.resolve().unwrap().sorted_by_level())
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_dep_res_string() {
+    #[derive(Debug)]
+    struct StrDep(String, Vec<String>);
+    impl DepMeta for StrDep {
+        type Id = String;
+        fn get_id(&self) -> String {
+            self.0.clone()
+        }
+        fn get_deps(&self) -> &[String] {
+            &self.1
+        }
+    }
+    let dr = DepRes::new();
+    dr.add(&vec![
+        StrDep("app".into(), vec!["lib".into(), "missing".into()]),
+        StrDep("lib".into(), vec![]),
+    ]);
+    let json = serde_json::to_string(&dr).unwrap();
+    assert_eq!(
+        json,
+        r#"{"no_deps":["lib"],"deps":{"app":["lib","missing"]}}"#
+    );
+    let back: DepRes<String> = serde_json::from_str(&json).unwrap();
+    assert!(dr.diff(&back).is_empty());
+    assert!(!back.contains(&"missing".to_string()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_resolved() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+    let json = serde_json::to_string(&r).unwrap();
+    assert_eq!(json, "[[0,2,3],[1,4],[5]]");
+
+    let back: ResolvedDeps<usize> = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    assert_eq!(back.iter_level().count(), 3);
+    assert_eq!(sorted(back.sorted_by_level()), sorted(r.sorted_by_level()));
+    assert_eq!(
+        back.iter_level()
+            .map(|l| (l.level, l.deps.len()))
+            .collect::<HashSet<_>>(),
+        HashSet::from([(0, 3), (1, 2), (2, 1)])
+    );
+}
+
+fn assert_balanced(dot: &str) {
+    let mut depth = 0i32;
+    let mut in_str = false;
+    let mut escaped = false;
+    for c in dot.chars() {
+        match (in_str, escaped, c) {
+            (true, true, _) => escaped = false,
+            (true, false, '\\') => escaped = true,
+            (_, false, '"') => in_str = !in_str,
+            (false, _, '{') => depth += 1,
+            (false, _, '}') => {
+                depth -= 1;
+                assert!(depth >= 0);
+            }
+            _ => {}
+        }
+    }
+    assert!(!in_str);
+    assert_eq!(depth, 0);
+}
+
+#[test]
+fn test_to_dot() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let dot = dr.to_dot();
+    assert_balanced(&dot);
+    assert!(dot.starts_with("digraph {\n"));
+    let edges = dot.lines().filter(|l| l.contains("->")).collect::<Vec<_>>();
+    assert_eq!(edges.len(), dr.edge_count());
+    assert_eq!(
+        edges,
+        vec![
+            r#"    "1" -> "0";"#,
+            r#"    "4" -> "3";"#,
+            r#"    "5" -> "4";"#
+        ]
+    );
+    assert!(dot.contains("    \"2\";\n"));
+
+    let dot = dr.to_dot_with(&DotOptions {
+        direction: EdgeDirection::ToDependent,
+    });
+    assert!(dot.contains(r#"    "0" -> "1";"#));
+
+    let r = dr.resolve().unwrap();
+    let dot = r.to_dot(&dr);
+    assert_balanced(&dot);
+    assert!(dot.contains(r#"    { rank=same; "0"; "2"; "3"; }"#));
+    assert!(dot.contains(r#"    { rank=same; "5"; }"#));
+    assert_eq!(dot.lines().filter(|l| l.contains("->")).count(), 3);
+}
+
+#[test]
+fn test_to_dot_escaping() {
+    #[derive(Debug)]
+    struct StrDep(String, Vec<String>);
+    impl DepMeta for StrDep {
+        type Id = String;
+        fn get_id(&self) -> String {
+            self.0.clone()
+        }
+        fn get_deps(&self) -> &[String] {
+            &self.1
+        }
+    }
+    let dr = DepRes::new();
+    dr.add(&vec![
+        StrDep(r#"foo "bar"/baz"#.into(), vec!["a b\\c".into()]),
+        StrDep("a b\\c".into(), vec![]),
+        StrDep("日本".into(), vec![r#"foo "bar"/baz"#.into()]),
+    ]);
+    let dot = dr.to_dot();
+    assert_balanced(&dot);
+    assert!(dot.contains(r#"    "foo \"bar\"/baz" -> "a b\\c";"#));
+    assert!(dot.contains(r#"    "日本" -> "foo \"bar\"/baz";"#));
+    assert_eq!(dot.lines().filter(|l| l.contains("->")).count(), 2);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_adjacency_json() {
+    let mut items = fixture();
+    items.push(dep(6, &[5, 1]));
+    let dr = DepRes::new();
+    dr.add(&items);
+    let json = dr.to_adjacency_json().unwrap();
+    assert_eq!(
+        json,
+        r#"{"nodes":[0,1,2,3,4,5,6],"edges":{"1":[0],"4":[3],"5":[4],"6":[1,5]}}"#
+    );
+    let back = DepRes::<usize>::from_adjacency_json(&json).unwrap();
+    assert!(dr.diff(&back).is_empty());
+    assert_eq!(back.to_adjacency_json().unwrap(), json);
+
+    let back = DepRes::<String>::from_adjacency_json(r#"{"nodes":["a","b"],"edges":{"b":["a"]}}"#)
+        .unwrap();
+    assert_eq!(
+        back.to_adjacency_json().unwrap(),
+        r#"{"nodes":["a","b"],"edges":{"b":["a"]}}"#
+    );
+    let back = DepRes::<String>::from_adjacency_json(r#"{"nodes":["a"]}"#).unwrap();
+    assert_eq!(back.node_count(), 1);
+
+    // tuples are no JSON object keys, without edges there are no keys to write
+    let dr = DepRes::<(u8, u8)>::new();
+    dr.add_iter([Dep::new((0, 0))]);
+    assert_eq!(dr.to_adjacency_json().unwrap(), r#"{"nodes":[[0,0]],"edges":{}}"#);
+    dr.add_iter([Dep::with_deps((0, 1), [(0, 0)])]);
+    assert!(matches!(
+        dr.to_adjacency_json(),
+        Err(AdjacencyJsonError::Json(_))
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_adjacency_json_malformed() {
+    type Dr = DepRes<String>;
+    assert!(matches!(
+        Dr::from_adjacency_json(r#"{"nodes":["a","b"],"edges":{"b":["c"]}}"#),
+        Err(AdjacencyJsonError::DanglingEdge { id, dep }) if id == "b" && dep == "c"
+    ));
+    assert!(matches!(
+        Dr::from_adjacency_json(r#"{"nodes":["a","b","a"],"edges":{}}"#),
+        Err(AdjacencyJsonError::DuplicateNode(id)) if id == "a"
+    ));
+    assert!(matches!(
+        Dr::from_adjacency_json(r#"{"nodes":["a"],"edges":{"b":["a"]}}"#),
+        Err(AdjacencyJsonError::UnknownNode(id)) if id == "b"
+    ));
+    assert!(matches!(
+        Dr::from_adjacency_json(r#"{"nodes":["a"],"edges":"#),
+        Err(AdjacencyJsonError::Json(_))
+    ));
+}
+
+#[cfg(feature = "petgraph")]
+#[test]
+fn test_petgraph() {
+    let mut items = fixture();
+    items.push(dep(6, &[5]));
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let (graph, indices) = dr.to_petgraph();
+    assert_eq!(graph.node_count(), dr.node_count());
+    assert_eq!(graph.edge_count(), dr.edge_count());
+    assert!(graph.contains_edge(indices[&6], indices[&5]));
+    assert!(!graph.contains_edge(indices[&5], indices[&6]));
+
+    let back = DepRes::from_petgraph(&graph);
+    assert!(dr.diff(&back).is_empty());
+
+    let r = dr.resolve().unwrap();
+    let levels = r.to_petgraph_levels(&graph);
+    assert_eq!(levels[indices[&0]], (0, Some(0)));
+    assert_eq!(levels[indices[&6]], (6, Some(3)));
+    assert_eq!(levels.edge_count(), graph.edge_count());
+}
+
+#[test]
+fn test_display_resolved() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.to_string(), "level 0: 0, 2, 3\nlevel 1: 1, 4\nlevel 2: 5");
+    assert_eq!(
+        format!("{r:#}"),
+        "level 0:\n  0\n  2\n  3\nlevel 1:\n  1\n  4\nlevel 2:\n  5"
+    );
+    assert_eq!(
+        r.render().max_ids(2).to_string(),
+        "level 0: 0, 2 … (+1 more)\nlevel 1: 1, 4\nlevel 2: 5"
+    );
+    assert_eq!(ResolvedDeps::<usize>::default().to_string(), "");
+}
+
+#[test]
+fn test_display_resolved_wide() {
+    let mut dr = DepRes::new();
+    dr.add(&(0..10_000).map(|i| dep(i, &[])).collect::<Vec<_>>());
+    let r = dr.resolve().unwrap();
+    assert_eq!(
+        r.render().max_ids(3).to_string(),
+        "level 0: 0, 1, 2 … (+9997 more)"
+    );
+    assert_eq!(
+        format!("{:#}", r.render().max_ids(1)),
+        "level 0:\n  0\n  … (+9999 more)"
+    );
+    assert!(r.to_string().ends_with(", 31 … (+9968 more)"));
+}
+
+#[test]
+fn test_level_of() {
+    let mut items = fixture();
+    items.push(dep(6, &[5]));
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.level_of(&0), Some(0));
+    assert_eq!(r.level_of(&2), Some(0));
+    assert_eq!(r.level_of(&4), Some(1));
+    assert_eq!(r.level_of(&6), Some(3));
+    assert_eq!(r.level_of(&7), None);
+    assert_eq!(r.is_before(&0, &1), Some(true));
+    assert_eq!(r.is_before(&1, &4), Some(false));
+    assert_eq!(r.is_before(&6, &0), Some(false));
+    assert_eq!(r.is_before(&0, &7), None);
+
+    let map = r.to_level_map();
+    assert_eq!(map.len(), 7);
+    for level in r.iter_level() {
+        for id in level.deps.iter() {
+            assert_eq!(map[id], level.level);
+            assert_eq!(r.level_of(id), Some(level.level));
+        }
+    }
+    assert_eq!(r.clone().level_of(&6), Some(3));
+}
+
+#[test]
+fn test_level_accessors_empty() {
+    let mut dr = DepRes::<usize>::new();
+    let r = dr.resolve().unwrap();
+    assert!(r.is_empty());
+    assert_eq!(r.len(), 0);
+    assert_eq!(r.level_count(), 0);
+    assert_eq!(r.max_level(), None);
+    assert!(r.get_level(0).is_none());
+}
+
+#[test]
+fn test_into_levels() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let expected = vec![vec![0, 2, 3], vec![1, 4], vec![5]];
+
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.to_sorted_levels(), expected);
+    let shared = r.clone();
+    let levels = shared.into_levels();
+    assert_eq!(sorted_levels(levels), expected);
+    // still usable after the clone was consumed
+    assert_eq!(sorted_levels(r.to_levels()), expected);
+    assert_eq!(sorted_levels(r.into_levels()), expected);
+
+    let unique = dr.resolve().unwrap();
+    assert_eq!(sorted_levels(unique.into_levels()), expected);
+    assert!(ResolvedDeps::<usize>::default().into_levels().is_empty());
+}
+
+fn sorted_levels(levels: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+    levels.into_iter().map(sorted).collect()
+}
+
+#[test]
+fn test_into_iter() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+
+    let mut seen = vec![];
+    for DepLevel { level, deps } in &r {
+        seen.push((level, sorted(deps.iter().map(|id| **id).collect())));
+    }
+    assert_eq!(
+        seen,
+        vec![(0, vec![0, 2, 3]), (1, vec![1, 4]), (2, vec![5])]
+    );
+    // the same levels as sets, only built when asked for
+    for (level, sets) in (&r).into_iter().zip(r.iter_level_sets()) {
+        assert_eq!(level.level, sets.level);
+        assert!(level.deps.iter().all(|id| sets.deps.contains(&**id)));
+        assert_eq!(level.deps.len(), sets.deps.len());
+    }
+
+    let iter: IntoLevels<usize> = r.clone().into_iter();
+    assert_eq!(iter.len(), 3);
+    let mut seen = vec![];
+    for DepLevel { level, deps } in iter {
+        seen.push((level, sorted(deps)));
+    }
+    assert_eq!(
+        seen,
+        vec![(0, vec![0, 2, 3]), (1, vec![1, 4]), (2, vec![5])]
+    );
+
+    let flat = r
+        .into_iter()
+        .flat_map(|l| sorted(l.deps))
+        .collect::<Vec<_>>();
+    assert_eq!(flat, vec![0, 2, 3, 1, 4, 5]);
+}
+
+#[cfg(parallel)]
+#[test]
+fn test_par_iter_level() {
+    let mut dr = DepRes::new();
+    dr.add(&random_dag(10_000, 3, 1234));
+    let r = dr.resolve().unwrap();
+    let total = r.sorted_by_level().len();
+    assert_eq!(total, 10_000);
+    assert_eq!(
+        r.par_iter_level().map(|l| l.deps.len()).sum::<usize>(),
+        total
+    );
+    assert_eq!(
+        (&r).into_par_iter()
+            .map(|l| l.par_iter().count())
+            .sum::<usize>(),
+        total
+    );
+    assert_eq!(r.par_iter_level().count(), r.level_count());
+}
+
+#[test]
+fn test_level() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+    let level = r.level(1);
+    assert_eq!(level.level, 1);
+    assert_eq!(sorted(level.deps.iter().copied().collect()), vec![1, 4]);
+    assert!(r.get_level(3).is_none());
+}
+
+#[test]
+#[should_panic(expected = "level 3 is out of range, max level is Some(2)")]
+fn test_level_out_of_range() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    dr.resolve().unwrap().level(3);
+}
+
+#[test]
+fn test_split_at_level() {
+    let mut items = fixture();
+    items.push(dep(6, &[5]));
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve().unwrap();
+    let all = sorted(r.sorted_by_level());
+
+    let (head, tail) = r.clone().split_at_level(1);
+    assert_eq!(head.to_sorted_levels(), vec![vec![0, 2, 3]]);
+    assert_eq!(tail.to_sorted_levels(), vec![vec![1, 4], vec![5], vec![6]]);
+    assert_eq!(tail.level_of(&6), Some(2));
+    let mut joined = head.sorted_by_level();
+    joined.extend(tail.sorted_by_level());
+    assert_eq!(sorted(joined), all);
+
+    let (head, tail) = r.clone().split_at_level(0);
+    assert!(head.is_empty());
+    assert_eq!(tail.to_sorted_levels(), r.to_sorted_levels());
+    let (head, tail) = r.clone().split_at_level(4);
+    assert_eq!(head.to_sorted_levels(), r.to_sorted_levels());
+    assert!(tail.is_empty());
+    let (head, tail) = r.clone().split_at_level(100);
+    assert_eq!(head.len(), 7);
+    assert!(tail.is_empty());
+}
+
+#[test]
+fn test_slice_levels() {
+    let mut items = fixture();
+    items.push(dep(6, &[5]));
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve().unwrap();
+    assert_eq!(
+        r.slice_levels(1..3).to_sorted_levels(),
+        vec![vec![1, 4], vec![5]]
+    );
+    assert_eq!(
+        r.slice_levels(2..).to_sorted_levels(),
+        vec![vec![5], vec![6]]
+    );
+    assert_eq!(r.slice_levels(..=0).to_sorted_levels(), vec![vec![0, 2, 3]]);
+    assert_eq!(r.slice_levels(..).to_sorted_levels(), r.to_sorted_levels());
+    assert!(r.slice_levels(4..).is_empty());
+    assert!(r.slice_levels(1..1).is_empty());
+    assert_eq!(r.len(), 7);
+}
+
+#[test]
+fn test_filter() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+
+    let chain = r.filter(|id| [3, 4, 5].contains(id), false);
+    assert_eq!(chain.sorted_by_level(), vec![3, 4, 5]);
+    assert_eq!(chain.max_level(), Some(2));
+
+    let odd = r.filter(|id| id % 2 == 1, false);
+    assert_eq!(odd.level_of(&1), Some(1));
+    assert_eq!(odd.level_of(&5), Some(2));
+    assert_eq!(odd.level_count(), 3);
+
+    let sparse = r.filter(|id| *id == 0 || *id == 5, false);
+    assert_eq!(sparse.level_count(), 2);
+    assert_eq!(sparse.max_level(), Some(2));
+    assert_eq!(sparse.level_of(&5), Some(2));
+    assert!(sparse.get_level(1).is_none());
+    let compact = r.filter(|id| *id == 0 || *id == 5, true);
+    assert_eq!(compact.to_sorted_levels(), vec![vec![0], vec![5]]);
+    assert_eq!(compact.max_level(), Some(1));
+
+    let none = r.filter(|_| false, true);
+    assert!(none.is_empty());
+    assert_eq!(none.max_level(), None);
+    // the original is untouched
+    assert_eq!(r.len(), 6);
+    assert_eq!(r.level_count(), 3);
+}
+
+fn resolved(levels: &[&[usize]]) -> ResolvedDeps<usize> {
+    ResolvedDeps::from_levels(levels.iter().map(|ids| ids.to_vec()).collect())
+}
+
+#[test]
+fn test_check_resolution() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+    assert_eq!(check_resolution(&dr, &r), Ok(()));
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 2, 3], &[1, 4], &[5]])),
+        Ok(())
+    );
+    // later than necessary is still legal
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 3], &[4], &[1, 2, 5]])),
+        Ok(())
+    );
+    assert_eq!(
+        check_resolution(&DepRes::<usize>::new(), &ResolvedDeps::default()),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_check_resolution_violations() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+
+    let gap = r.filter(|id| *id != 1 && *id != 4, false);
+    assert_eq!(
+        check_resolution(&dr, &gap),
+        Err(ResolutionViolation::LevelGap(1))
+    );
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 2, 3], &[1, 4, 2], &[5]])),
+        Err(ResolutionViolation::Duplicate {
+            id: 2,
+            first: 0,
+            second: 1
+        })
+    );
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 2, 3], &[1, 4], &[5, 9]])),
+        Err(ResolutionViolation::Unknown { id: 9, level: 2 })
+    );
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 3], &[1, 4], &[5]])),
+        Err(ResolutionViolation::Missing(2))
+    );
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 2, 3], &[1, 4, 5]])),
+        Err(ResolutionViolation::DepNotBefore {
+            id: 5,
+            level: 1,
+            dep: 4,
+            dep_level: Some(1)
+        })
+    );
+
+    // a dep on no node doesn't hold a node back
+    dr.add(&vec![dep(6, &[9])]);
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 2, 3, 6], &[1, 4], &[5]])),
+        Ok(())
+    );
+
+    // deps are checked through aliases, soft deps aren't checked at all
+    let dr = DepRes::new();
+    dr.add(&vec![dep(0, &[]), dep(1, &[10]), dep(2, &[])]);
+    dr.add_alias(10, 0).unwrap();
+    dr.add_soft_edge(2, 1);
+    let r = dr.resolve_deterministic().unwrap();
+    assert_eq!(check_resolution(&dr, &r), Ok(()));
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 1, 2]])),
+        Err(ResolutionViolation::DepNotBefore {
+            id: 1,
+            level: 0,
+            dep: 0,
+            dep_level: Some(0)
+        })
+    );
+    assert_eq!(
+        check_resolution(&dr, &resolved(&[&[0, 2], &[1]])),
+        Ok(())
+    );
+}
+
+#[cfg(parallel)]
+#[test]
+fn test_resolve_deterministic() {
+    let items = random_dag(3000, 3, 99);
+    let mut outputs = HashSet::new();
+    for threads in 1..=10 {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let out = pool.install(|| {
+            let dr = DepRes::new();
+            dr.add(&items);
+            let r = dr.resolve_deterministic().unwrap();
+            let levels = r.iter_level().map(|l| l.level).collect::<Vec<_>>();
+            let consumed = r
+                .clone()
+                .into_iter()
+                .map(|l| (l.level, l.deps))
+                .collect::<Vec<_>>();
+            let split = r.clone().split_at_level(2).1.sorted_by_level();
+            format!(
+                "{:?}|{:?}|{:?}|{:?}|{:?}",
+                r.sorted_by_level(),
+                r.to_levels(),
+                levels,
+                consumed,
+                split
+            )
+        });
+        outputs.insert(out);
+    }
+    assert_eq!(outputs.len(), 1);
+}
+
+#[test]
+fn test_custom_hasher() {
+    let mut dr = DepRes::<usize, ahash::RandomState>::with_hasher(ahash::RandomState::new());
+    dr.add(&fixture());
+    let r: ResolvedDeps<usize, ahash::RandomState> = dr.resolve().unwrap();
+    assert_eq!(
+        r.to_sorted_levels(),
+        vec![vec![0, 2, 3], vec![1, 4], vec![5]]
+    );
+    assert_eq!(r.level_of(&5), Some(2));
+    assert_eq!(check_resolution(&dr, &r), Ok(()));
+
+    let sub = dr.subgraph(&[5]);
+    assert_eq!(sub.node_count(), 3);
+    let copy = dr.clone();
+    assert!(dr.diff(&copy).is_empty());
+    let r = DepRes::<usize, ahash::RandomState>::default()
+        .resolve_deterministic()
+        .unwrap();
+    assert!(r.is_empty());
+}
+
+#[test]
+fn test_resolve_mixed_depth() {
+    let mut dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[0]), dep(2, &[1]), dep(3, &[0, 2])]);
+    let r = dr.resolve().unwrap();
+    assert_eq!(
+        r.to_sorted_levels(),
+        vec![vec![0], vec![1], vec![2], vec![3]]
+    );
+    assert_eq!(check_resolution(&dr, &r), Ok(()));
+}
+
+#[test]
+fn test_resolve_random_dags() {
+    for seed in 1..20 {
+        let mut dr = DepRes::new();
+        dr.add(&random_dag(2000, 4, seed));
+        let r = dr.resolve().unwrap();
+        assert_eq!(check_resolution(&dr, &r), Ok(()));
+        // every node sits exactly one level after its deepest dep
+        for id in dr.ids() {
+            let deepest = dr
+                .direct_deps(&id)
+                .iter()
+                .map(|d| r.level_of(d).unwrap())
+                .max();
+            assert_eq!(r.level_of(&id), Some(deepest.map_or(0, |lv| lv + 1)));
+        }
+    }
+}
+
+/// `0 <- 1 <- .. <- n - 1`
+fn chain(n: usize) -> Vec<Dep<usize>> {
+    (0..n)
+        .map(|id| Dep {
+            id,
+            deps: id.checked_sub(1).into_iter().collect(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_resolve_long_chain() {
+    let n = 100_000;
+    let mut dr = DepRes::new();
+    dr.add(&chain(n));
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.level_count(), n);
+    assert_eq!(r.level_of(&(n - 1)), Some(n - 1));
+}
+
+/// Id that records the name of every thread it gets hashed on
+#[cfg(parallel)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Traced(usize);
+
+#[cfg(parallel)]
+static TRACED_THREADS: std::sync::Mutex<Vec<Option<String>>> = std::sync::Mutex::new(vec![]);
+
+#[cfg(parallel)]
+impl std::hash::Hash for Traced {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let name = std::thread::current().name().map(String::from);
+        TRACED_THREADS.lock().unwrap().push(name);
+        self.0.hash(state);
+    }
+}
+
+#[cfg(parallel)]
+struct TracedDep {
+    id: Traced,
+    deps: Vec<Traced>,
+}
+
+#[cfg(parallel)]
+impl DepMeta for TracedDep {
+    type Id = Traced;
+
+    fn get_id(&self) -> Self::Id {
+        self.id.clone()
+    }
+
+    fn get_deps(&self) -> &[Self::Id] {
+        &self.deps
+    }
+}
+
+#[cfg(parallel)]
+#[test]
+fn test_resolve_on() {
+    let items = random_dag(5000, 3, 31)
+        .into_iter()
+        .map(|d| TracedDep {
+            id: Traced(d.id),
+            deps: d.deps.into_iter().map(Traced).collect(),
+        })
+        .collect::<Vec<_>>();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .thread_name(|i| format!("dep-res-pool-{i}"))
+        .build()
+        .unwrap();
+    let on_pool = |threads: Vec<Option<String>>| {
+        !threads.is_empty()
+            && threads.iter().all(|name| {
+                name.as_deref()
+                    .is_some_and(|n| n.starts_with("dep-res-pool-"))
+            })
+    };
+
+    let dr = DepRes::new();
+    TRACED_THREADS.lock().unwrap().clear();
+    dr.add_on(&pool, &items);
+    assert!(on_pool(std::mem::take(
+        &mut *TRACED_THREADS.lock().unwrap()
+    )));
+
+    let r = dr.resolve_on(&pool).unwrap();
+    assert!(on_pool(std::mem::take(
+        &mut *TRACED_THREADS.lock().unwrap()
+    )));
+
+    let mut global = DepRes::new();
+    global.add(&items);
+    assert_eq!(
+        r.to_sorted_levels(),
+        global.resolve().unwrap().to_sorted_levels()
+    );
+}
+
+#[test]
+fn test_add_high_fanout() {
+    let dr = DepRes::new();
+    let wide = (1..5001).collect::<Vec<_>>();
+    dr.add(&[dep(0, &wide), dep(0, &[1, 2, 6000]), dep(7, &[0, 0, 1])]);
+    dr.add(&[dep(7, &[2]), dep(8, &[])]);
+    let mut expected = wide.iter().map(|&d| (0, d)).collect::<HashSet<_>>();
+    expected.extend([(0, 6000), (7, 0), (7, 1), (7, 2)]);
+    assert_eq!(dr.edges().collect::<HashSet<_>>(), expected);
+    assert_eq!(dr.edge_count(), expected.len());
+    assert_eq!(sorted(dr.ids().collect()), vec![0, 7, 8]);
+}
+
+static CANARY_CLONES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+/// Held by tests counting clones, so they don't see each other's
+static CANARY_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Id counting how often it gets cloned
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Canary(usize);
+
+impl Clone for Canary {
+    fn clone(&self) -> Self {
+        CANARY_CLONES.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Canary(self.0)
+    }
+}
+
+struct CanaryDep {
+    id: Canary,
+    deps: Vec<Canary>,
+}
+
+impl DepMeta for CanaryDep {
+    type Id = Canary;
+
+    fn get_id(&self) -> Self::Id {
+        Canary(self.id.0)
+    }
+
+    fn get_deps(&self) -> &[Self::Id] {
+        &self.deps
+    }
+}
+
+#[test]
+fn test_ids_stored_once() {
+    let _lock = CANARY_LOCK.lock().unwrap();
+    let node = |id: usize, deps: &[usize]| CanaryDep {
+        id: Canary(id),
+        deps: deps.iter().map(|&d| Canary(d)).collect(),
+    };
+    let mut dr = DepRes::new();
+    dr.add(&[node(0, &[]), node(1, &[0])]);
+    let before = CANARY_CLONES.load(std::sync::atomic::Ordering::Relaxed);
+    dr.add(&(2..100).map(|id| node(id, &[0, 1])).collect::<Vec<_>>());
+    let clones = CANARY_CLONES.load(std::sync::atomic::Ordering::Relaxed) - before;
+    // one allocation per new id, the shared deps 0 and 1 are never copied again
+    assert_eq!(clones, 98);
+    assert_eq!(dr.edge_count(), 1 + 98 * 2);
+    let levels = dr.resolve().unwrap().to_sorted_levels();
+    assert_eq!(levels[0], vec![Canary(0)]);
+    assert_eq!(levels[1], vec![Canary(1)]);
+    assert_eq!(levels[2].len(), 98);
+    assert!(dr.remove(&Canary(50)));
+    assert!(!dr.pool.contains_key(&Canary(50)));
+    assert!(dr.pool.contains_key(&Canary(0)));
+}
+
+#[test]
+fn test_critical_path() {
+    let dr = DepRes::new();
+    dr.add(&chain(10));
+    assert_eq!(dr.critical_path().unwrap(), (0..10).collect::<Vec<_>>());
+
+    // 0 -> 1 -> 2 -> 4 is longer than 0 -> 3 -> 4
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[1]),
+        dep(3, &[0]),
+        dep(4, &[2, 3]),
+    ]);
+    assert_eq!(dr.critical_path().unwrap(), vec![0, 1, 2, 4]);
+    assert_eq!(dr.critical_path_weighted(|_| 1).unwrap().1, 4);
+
+    // but the single heavy hop through 3 outweighs it
+    let cost = |id: &usize| if *id == 3 { 10 } else { 1 };
+    assert_eq!(
+        dr.critical_path_weighted(cost).unwrap(),
+        (vec![0, 3, 4], 12)
+    );
+
+    // ties go to the smallest id
+    let dr = DepRes::new();
+    dr.add(&diamond());
+    assert_eq!(dr.critical_path().unwrap(), vec![0, 1, 3]);
+    let dr = DepRes::new();
+    dr.add(&[dep(5, &[]), dep(2, &[])]);
+    assert_eq!(dr.critical_path().unwrap(), vec![2]);
+
+    // deps through an alias and kept soft deps are hops as well
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[10]), dep(2, &[])]);
+    dr.add_alias(10, 0).unwrap();
+    assert_eq!(dr.critical_path().unwrap(), vec![0, 1]);
+    dr.add_soft_edge(2, 1);
+    assert_eq!(dr.critical_path().unwrap(), vec![0, 1, 2]);
+
+    assert!(DepRes::<usize>::new().critical_path().unwrap().is_empty());
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[2]), dep(2, &[1])]);
+    assert_eq!(dr.critical_path(), Err(DepResolveError::IslandsOrCircular));
+}
+
+fn assert_schedule_respects_deps(dr: &DepRes<usize>, estimate: &ScheduleEstimate<usize>) {
+    for (id, node) in &estimate.nodes {
+        for dep in dr.leveled_deps(id, &[]) {
+            if !estimate.nodes.contains_key(&dep) {
+                continue;
+            }
+            assert!(
+                node.start >= estimate.nodes[&dep].finish,
+                "{id} starts before {dep}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_estimate_makespan() {
+    let mut dr = DepRes::new();
+    dr.add(&chain(5));
+    let r = dr.resolve().unwrap();
+    for workers in [1, 2, 8, usize::MAX] {
+        let estimate = r.estimate_makespan(&dr, |id| *id as u64 + 1, workers);
+        assert_eq!(estimate.makespan, 15);
+        assert_eq!(estimate.busy, vec![15]);
+        assert_schedule_respects_deps(&dr, &estimate);
+    }
+
+    let mut dr = DepRes::new();
+    dr.add(&(0..8).map(|id| dep(id, &[])).collect::<Vec<_>>());
+    let r = dr.resolve().unwrap();
+    let makespan = |workers| r.estimate_makespan(&dr, |_| 3, workers).makespan;
+    assert_eq!(makespan(1), 24);
+    assert_eq!(makespan(2), 12);
+    assert_eq!(makespan(3), 9);
+    assert_eq!(makespan(8), 3);
+    assert_eq!(makespan(usize::MAX), 3);
+    let estimate = r.estimate_makespan(&dr, |_| 3, 4);
+    assert_eq!(estimate.utilization(), vec![1.0; 4]);
+
+    // waits for deps through an alias and for kept soft deps
+    let mut dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[10]), dep(2, &[]), dep(3, &[])]);
+    dr.add_alias(10, 0).unwrap();
+    dr.add_soft_edge(3, 2);
+    let r = dr.resolve().unwrap();
+    let estimate = r.estimate_makespan(&dr, |_| 1, usize::MAX);
+    assert_eq!(estimate.makespan, 2);
+    assert_schedule_respects_deps(&dr, &estimate);
+    // a soft cycle doesn't leave its nodes waiting on each other
+    dr.add_soft_edge(2, 3);
+    let r = dr.resolve().unwrap();
+    let estimate = r.estimate_makespan(&dr, |_| 1, usize::MAX);
+    assert_eq!(estimate.nodes.len(), 4);
+    assert_eq!(estimate.makespan, 2);
+
+    // 4 only waits for its own deps, not for the long 1 that happens to share a level with 3
+    let mut dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[]),
+        dep(3, &[2]),
+        dep(4, &[3]),
+    ]);
+    let r = dr.resolve().unwrap();
+    let cost = |id: &usize| if *id == 1 { 10 } else { 1 };
+    let estimate = r.estimate_makespan(&dr, cost, 2);
+    assert_schedule_respects_deps(&dr, &estimate);
+    assert_eq!(estimate.nodes[&4].finish, 3);
+    assert_eq!(estimate.makespan, 11);
+
+    let mut dr = DepRes::new();
+    dr.add(&random_dag(500, 4, 5));
+    let r = dr.resolve().unwrap();
+    for workers in [1, 3, usize::MAX] {
+        let estimate = r.estimate_makespan(&dr, |id| (*id % 7) as u64, workers);
+        assert_eq!(estimate.nodes.len(), 500);
+        assert!(estimate.busy.len() <= workers);
+        assert_schedule_respects_deps(&dr, &estimate);
+    }
+}
+
+#[test]
+fn test_stats() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    assert_eq!(
+        dr.stats(),
+        GraphStats {
+            node_count: 6,
+            edge_count: 3,
+            max_out_degree: 1,
+            max_in_degree: 1,
+            out_degree_histogram: vec![3, 3],
+            in_degree_histogram: vec![3, 3],
+        }
+    );
+    assert_eq!(
+        dr.resolve().unwrap().stats(),
+        LevelStats {
+            level_count: 3,
+            max_width: 3,
+            mean_width: 2.0,
+            widest_level: Some(0),
+        }
+    );
+    assert_eq!(DepRes::<usize>::new().stats(), GraphStats::default());
+    assert_eq!(
+        ResolvedDeps::<usize>::default().stats(),
+        LevelStats::default()
+    );
+
+    let mut dr = DepRes::new();
+    dr.add(&random_dag(1000, 5, 77));
+    let stats = dr.stats();
+    assert_eq!(stats.node_count, 1000);
+    assert_eq!(stats.out_degree_histogram.iter().sum::<usize>(), 1000);
+    assert_eq!(stats.in_degree_histogram.iter().sum::<usize>(), 1000);
+    let weighted = |h: &[usize]| h.iter().enumerate().map(|(d, n)| d * n).sum::<usize>();
+    assert_eq!(weighted(&stats.out_degree_histogram), stats.edge_count);
+    assert_eq!(weighted(&stats.in_degree_histogram), stats.edge_count);
+    assert!(stats.max_out_degree <= 5);
+    let r = dr.resolve().unwrap().stats();
+    assert_eq!(r.mean_width * r.level_count as f64, 1000.0);
+    assert!(r.max_width as f64 >= r.mean_width);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_stats_serde() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let json = serde_json::to_string(&dr.stats()).unwrap();
+    assert_eq!(
+        serde_json::from_str::<GraphStats>(&json).unwrap(),
+        dr.stats()
+    );
+    let stats = dr.resolve().unwrap().stats();
+    let json = serde_json::to_string(&stats).unwrap();
+    assert_eq!(
+        json,
+        r#"{"level_count":3,"max_width":3,"mean_width":2.0,"widest_level":0}"#
+    );
+}
+
+#[test]
+fn test_chunked() {
+    let dr = DepRes::new();
+    let mut items = (0..10).map(|id| dep(id, &[])).collect::<Vec<_>>();
+    items.push(dep(10, &[0]));
+    dr.add(&items);
+    let r = dr.resolve_deterministic().unwrap();
+    let chunks = r.chunked(3).map(|c| (c.level, c.deps)).collect::<Vec<_>>();
+    assert_eq!(
+        chunks,
+        vec![
+            (0, vec![0, 1, 2]),
+            (0, vec![3, 4, 5]),
+            (0, vec![6, 7, 8]),
+            (0, vec![9]),
+            (1, vec![10]),
+        ]
+    );
+
+    let r = resolved(&[&[0, 2, 3], &[1, 4], &[5]]);
+    let chunks = r
+        .chunked(3)
+        .map(|c| (c.level, sorted(c.deps)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        chunks,
+        vec![(0, vec![0, 2, 3]), (1, vec![1, 4]), (2, vec![5])]
+    );
+    assert_eq!(r.chunked(1).count(), 6);
+    assert_eq!(ResolvedDeps::<usize>::default().chunked(4).count(), 0);
+}
+
+#[test]
+fn test_for_each_level() {
+    use std::sync::{atomic::AtomicUsize, Mutex};
+
+    let mut dr = DepRes::new();
+    dr.add(&random_dag(2000, 3, 8));
+    let r = dr.resolve().unwrap();
+    let clock = AtomicUsize::new(0);
+    let ticks = Mutex::new(HashMap::new());
+    r.for_each_level_ok(|id| {
+        let start = clock.fetch_add(1, Ordering::SeqCst);
+        let finish = clock.fetch_add(1, Ordering::SeqCst);
+        ticks.lock().unwrap().insert(*id, (start, finish));
+    });
+    let ticks = ticks.into_inner().unwrap();
+    assert_eq!(ticks.len(), 2000);
+    // every level starts only after the whole previous level finished
+    let levels = r.to_levels();
+    for pair in levels.windows(2) {
+        let finished = pair[0].iter().map(|id| ticks[id].1).max().unwrap();
+        let started = pair[1].iter().map(|id| ticks[id].0).min().unwrap();
+        assert!(finished < started);
+    }
+
+    let r = resolved(&[&[0, 2, 3], &[1, 4, 6], &[5]]);
+    let ran = Mutex::new(vec![]);
+    let err = r
+        .for_each_level(|id| {
+            ran.lock().unwrap().push(*id);
+            match id {
+                1 | 6 => Err(format!("{id} broke")),
+                _ => Ok(()),
+            }
+        })
+        .unwrap_err();
+    assert_eq!(err.level, 1);
+    let mut failures = err.failures.clone();
+    failures.sort();
+    assert_eq!(
+        failures,
+        vec![(1, "1 broke".to_string()), (6, "6 broke".to_string())]
+    );
+    assert_eq!(err.to_string(), "2 node(s) failed at level 1");
+    // the rest of level 1 still ran, level 2 never started
+    assert_eq!(sorted(ran.into_inner().unwrap()), vec![0, 1, 2, 3, 4, 6]);
+    assert_eq!(
+        ResolvedDeps::<usize>::default().for_each_level(|_| Err(())),
+        Ok(())
+    );
+}
+
+fn drive_plan(dr: &DepRes<usize>, threads: usize) -> Vec<usize> {
+    use std::sync::Mutex;
+
+    let plan = dr.execution_plan().unwrap();
+    let completed = Mutex::new(vec![]);
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for id in plan.iter() {
+                    {
+                        let completed = completed.lock().unwrap();
+                        for dep in dr.leveled_deps(&id, &[]) {
+                            let missing = !dr.contains(&dep) || completed.contains(&dep);
+                            assert!(missing, "{id} yielded before {dep}");
+                        }
+                    }
+                    completed.lock().unwrap().push(id);
+                    plan.complete(&id);
+                }
+            });
+        }
+    });
+    assert!(plan.is_finished());
+    completed.into_inner().unwrap()
+}
+
+#[test]
+fn test_execution_plan() {
+    let dr = DepRes::new();
+    dr.add(&diamond());
+    for threads in [1, 4] {
+        assert_eq!(sorted(drive_plan(&dr, threads)), vec![0, 1, 2, 3]);
+    }
+    let dr = DepRes::new();
+    dr.add(&random_dag(1000, 4, 3));
+    assert_eq!(drive_plan(&dr, 8).len(), 1000);
+
+    // 2 is ready without waiting for the level 0 sibling 1
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[]), dep(2, &[0])]);
+    let plan = dr.execution_plan().unwrap();
+    let first = plan.next_ready().unwrap();
+    let second = plan.next_ready().unwrap();
+    assert_eq!(plan.next_ready(), None);
+    let zero = if first == 0 { first } else { second };
+    plan.complete(&zero);
+    assert_eq!(plan.next_ready(), Some(2));
+    assert!(!plan.is_finished());
+    plan.complete(&2);
+    plan.complete(&(first + second - zero));
+    assert!(plan.is_finished());
+    assert_eq!(plan.wait_ready(), None);
+
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[1]), dep(1, &[0])]);
+    assert!(dr.execution_plan().is_err());
+
+    // 1 waits for 0 behind the alias 10 and 3 for its soft dep 2, of the soft cycle of 4
+    // and 5 the edge resolving dropped isn't waited for
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[10]), dep(2, &[]), dep(3, &[]), dep(4, &[]), dep(5, &[])]);
+    dr.add_alias(10, 0).unwrap();
+    dr.add_soft_edge(3, 2);
+    assert_eq!(sorted(drive_plan(&dr, 4)), vec![0, 1, 2, 3, 4, 5]);
+    dr.add_soft_edge(4, 5);
+    dr.add_soft_edge(5, 4);
+    let plan = dr.execution_plan().unwrap();
+    let ready = std::iter::from_fn(|| plan.next_ready()).collect::<Vec<_>>();
+    assert_eq!(ready.len(), 3, "{ready:?}");
+    assert!(ready.contains(&0) && ready.contains(&2), "{ready:?}");
+    for id in &ready {
+        plan.complete(id);
+    }
+    let ready = std::iter::from_fn(|| plan.next_ready()).collect::<Vec<_>>();
+    assert_eq!(ready.len(), 3, "{ready:?}");
+    assert!(ready.contains(&1) && ready.contains(&3), "{ready:?}");
+}
+
+#[test]
+#[should_panic(expected = "not handed out")]
+fn test_execution_plan_complete_unstarted() {
+    let dr = DepRes::new();
+    dr.add(&diamond());
+    dr.execution_plan().unwrap().complete(&3);
+}
+
+#[test]
+fn test_execute_threaded() {
+    use std::sync::Mutex;
+
+    let dr = DepRes::new();
+    dr.add(&random_dag(500, 4, 7));
+    for threads in [1, 4] {
+        // the timeline is appended when an id finishes, so every dep is in front of its dependents
+        let timeline = Mutex::new(vec![]);
+        dr.execute_threaded(threads, |&id| {
+            let mut timeline = timeline.lock().unwrap();
+            for dep in dr.direct_deps(&id) {
+                assert!(timeline.contains(&dep), "{id} ran before {dep}");
+            }
+            timeline.push(id);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        let timeline = timeline.into_inner().unwrap();
+        assert_eq!(sorted(timeline.clone()), (0..500).collect::<Vec<_>>());
+        for (i, id) in timeline.iter().enumerate() {
+            for dep in dr.direct_deps(id) {
+                assert!(timeline[..i].contains(&dep));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_execute_threaded_failures() {
+    // 1 panics and 2 fails, so 3 never runs while the unrelated 4 still does
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[0]),
+        dep(3, &[1, 2]),
+        dep(4, &[]),
+    ]);
+    let ran = std::sync::Mutex::new(vec![]);
+    let r = dr.execute_threaded(2, |&id| {
+        ran.lock().unwrap().push(id);
+        match id {
+            1 => panic!("node 1 exploded"),
+            2 => Err("node 2 failed"),
+            _ => Ok(()),
+        }
+    });
+    let Err(ExecuteError::Failed { failures, skipped }) = r else {
+        panic!("expected failures, got {r:?}");
+    };
+    let mut failures = failures;
+    failures.sort_by_key(|(id, _)| *id);
+    assert_eq!(
+        failures,
+        vec![
+            (1, NodeFailure::Panic("node 1 exploded".to_string())),
+            (2, NodeFailure::Err("node 2 failed")),
+        ]
+    );
+    assert_eq!(skipped, vec![3]);
+    assert_eq!(sorted(ran.into_inner().unwrap()), vec![0, 1, 2, 4]);
+}
+
+#[test]
+fn test_resolve_with_progress() {
+    use std::sync::Mutex;
+
+    let dr = DepRes::new();
+    dr.add(&random_dag(1000, 4, 11));
+    let reports = Mutex::new(vec![]);
+    let r = dr
+        .resolve_with_progress(|p| reports.lock().unwrap().push(p))
+        .unwrap();
+    let reports = reports.into_inner().unwrap();
+    assert_eq!(reports.len(), r.level_count() + 1);
+    assert_eq!(
+        reports[0],
+        ResolveProgress {
+            level: None,
+            placed: 0,
+            remaining: 1000
+        }
+    );
+    for (i, pair) in reports.windows(2).enumerate() {
+        assert_eq!(pair[1].level, Some(i));
+        assert!(pair[1].remaining <= pair[0].remaining);
+        assert_eq!(pair[1].placed + pair[1].remaining, 1000);
+    }
+    assert_eq!(reports.last().unwrap().remaining, 0);
+
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[2]), dep(2, &[1])]);
+    let last = Mutex::new(None);
+    assert!(dr
+        .resolve_with_progress(|p| *last.lock().unwrap() = Some(p))
+        .is_err());
+    assert_eq!(last.into_inner().unwrap().unwrap().remaining, 2);
+}
+
+#[test]
+fn test_resolve_cancelable() {
+    let dr = DepRes::new();
+    dr.add(&chain(100_000));
+    let cancel = AtomicBool::new(true);
+    assert_eq!(
+        dr.resolve_cancelable(&cancel).err(),
+        Some(DepResolveError::Cancelled)
+    );
+
+    let cancel = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            cancel.store(true, Ordering::Relaxed);
+        });
+        assert_eq!(
+            dr.resolve_cancelable(&cancel).err(),
+            Some(DepResolveError::Cancelled)
+        );
+    });
+
+    cancel.store(false, Ordering::Relaxed);
+    assert_eq!(
+        dr.resolve_cancelable(&cancel).unwrap().level_count(),
+        100_000
+    );
+}
+
+#[test]
+fn test_max_levels() {
+    let dr = DepRes::new();
+    dr.add(&chain(10));
+    assert_eq!(dr.max_levels(), usize::MAX);
+    dr.set_max_levels(10);
+    assert_eq!(dr.resolve_nodes(&dr.ids, false).unwrap().level_count(), 10);
+    dr.set_max_levels(9);
+    assert_eq!(
+        dr.resolve_nodes(&dr.ids, false).err(),
+        Some(DepResolveError::DepthLimitExceeded {
+            limit: 9,
+            placed: 9
+        })
+    );
+    dr.set_max_levels(usize::MAX);
+    assert!(dr.resolve_nodes(&dr.ids, false).is_ok());
+}
+
+#[test]
+fn test_sorted_by_level_with() {
+    use std::cmp::Reverse;
+
+    let dr = DepRes::new();
+    dr.add(&random_dag(300, 3, 5));
+    let r = dr
+        .resolve_nodes(&dr.ids, false)
+        .unwrap()
+        .into_deterministic();
+    let cost = |id: &usize| id * 7 % 11;
+    let sorted = r.sorted_by_level_with(|id| Reverse(cost(id)));
+    let levels = r.level_map();
+    // levels never interleave, inside one the cost only falls and ties keep ascending ids
+    for pair in sorted.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        assert!(levels[a] <= levels[b]);
+        if levels[a] == levels[b] {
+            assert!(cost(a) > cost(b) || (cost(a) == cost(b) && a < b));
+        }
+    }
+    assert_eq!(sorted.len(), 300);
+
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[]),
+        dep(2, &[]),
+        dep(3, &[0]),
+        dep(4, &[2]),
+    ]);
+    let order = std::sync::Mutex::new(vec![]);
+    dr.execute_threaded_with(
+        1,
+        |id| Reverse(*id),
+        |&id| {
+            order.lock().unwrap().push(id);
+            Ok::<_, ()>(())
+        },
+    )
+    .unwrap();
+    assert_eq!(order.into_inner().unwrap(), vec![2, 1, 0, 4, 3]);
+}
+
+#[test]
+fn test_pin_min_level() {
+    // 0 -> 1 -> 2 and 3 -> 4, with 0 pinned to level 2
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[1]),
+        dep(3, &[]),
+        dep(4, &[3]),
+    ]);
+    dr.pin_min_level(0, 2).unwrap();
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(
+        r.to_sorted_levels(),
+        vec![vec![3], vec![4], vec![0], vec![1], vec![2]]
+    );
+
+    // a pin below the computed level changes nothing
+    dr.pin_min_level(0, 0).unwrap();
+    dr.pin_min_level(2, 1).unwrap();
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0, 3], vec![1, 4], vec![2]]);
+
+    // gaps stay as empty levels, which are legal
+    dr.pin_min_level(4, 4).unwrap();
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.level_of(&4), Some(4));
+    assert!(r.to_levels()[3].is_empty());
+    assert_eq!(check_resolution(&dr, &r), Ok(()));
+
+    assert_eq!(
+        dr.pin_min_level(9, 1),
+        Err(DepResolveError::UnknownTargets(vec![9]))
+    );
+    assert_eq!(dr.unpin(&4), Some(4));
+    dr.remove(&2);
+    assert!(!dr.pins.contains_key(&2));
+    // the resolutions above still share the id, the pool only lets go of unshared ones
+    assert!(dr.pool.contains_key(&2));
+}
+
+struct SoftDep {
+    id: usize,
+    deps: Vec<usize>,
+    soft: Vec<usize>,
+}
+
+impl DepMeta for SoftDep {
+    type Id = usize;
+
+    fn get_id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn get_deps(&self) -> &[Self::Id] {
+        &self.deps
+    }
+
+    fn get_soft_deps(&self) -> &[Self::Id] {
+        &self.soft
+    }
+}
+
+#[test]
+fn test_soft_deps() {
+    let soft = |id, deps: &[usize], soft: &[usize]| SoftDep {
+        id,
+        deps: deps.to_vec(),
+        soft: soft.to_vec(),
+    };
+
+    // 9 is absent, so the soft dep on it is ignored and 1 stays a root
+    let dr = DepRes::new();
+    dr.add(&[soft(0, &[], &[]), soft(1, &[], &[9]), soft(2, &[0], &[])]);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0, 1], vec![2]]);
+
+    // present soft deps order like hard ones
+    dr.add_soft_edge(1, 2);
+    let (r, dropped) = dr.resolve_with_dropped().unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0], vec![2], vec![1]]);
+    assert!(dropped.is_empty());
+
+    // 3 -> 4 -> 5 -> 3 only through soft edges loses exactly one of them
+    dr.add(&[soft(3, &[], &[4]), soft(4, &[], &[5]), soft(5, &[], &[3])]);
+    let (r, dropped) = dr.resolve_with_dropped().unwrap();
+    assert_eq!(dropped.len(), 1);
+    let (id, dep) = dropped[0];
+    for (a, b) in [(3, 4), (4, 5), (5, 3)] {
+        if (a, b) != (id, dep) {
+            assert!(r.level_of(&a) > r.level_of(&b));
+        }
+    }
+    assert_eq!(r.level_of(&1), Some(2));
+
+    // a cycle with a hard edge in it isn't broken, even if it's mostly soft
+    let dr = DepRes::new();
+    dr.add(&[soft(0, &[1], &[]), soft(1, &[], &[2]), soft(2, &[], &[0])]);
+    assert_eq!(
+        dr.resolve_with_dropped().err(),
+        Some(DepResolveError::IslandsOrCircular)
+    );
+    // the same cycle with the hard edge made soft gets broken
+    let dr = DepRes::new();
+    dr.add(&[soft(0, &[], &[1]), soft(1, &[], &[2]), soft(2, &[], &[0])]);
+    let (r, dropped) = dr.resolve_with_dropped().unwrap();
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(r.len(), 3);
+
+    // hard cycles still fail
+    let dr = DepRes::new();
+    dr.add(&[soft(0, &[1], &[]), soft(1, &[], &[])]);
+    dr.add(&[soft(1, &[0], &[])]);
+    assert_eq!(
+        dr.resolve_with_dropped().err(),
+        Some(DepResolveError::IslandsOrCircular)
+    );
+}
+
+#[test]
+fn test_edge_kinds() {
+    // 1 runs on 0, 2 builds with 1, 3 tests with 2 and plainly needs 0
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(3, &[0])]);
+    dr.add_edge_kind(1, 0, EdgeKind::Run);
+    dr.add_edge_kind(2, 1, EdgeKind::Build);
+    dr.add_edge_kind(3, 2, EdgeKind::Dev);
+    dr.add_edge_kind(3, 0, EdgeKind::Build);
+    assert_eq!(
+        dr.edge_kinds(&3, &0),
+        vec![EdgeKind::Normal, EdgeKind::Build]
+    );
+    assert_eq!(dr.edge_kinds(&1, &0), vec![EdgeKind::Run]);
+    assert!(dr.edge_kinds(&0, &1).is_empty());
+
+    let everything = dr.resolve_filtered(|_| true).unwrap();
+    assert_eq!(
+        everything.to_sorted_levels(),
+        dr.resolve_nodes(&dr.ids, false).unwrap().to_sorted_levels()
+    );
+    assert_eq!(
+        everything.to_sorted_levels(),
+        vec![vec![0], vec![1], vec![2], vec![3]]
+    );
+
+    let run = dr.resolve_filtered(|kind| *kind == EdgeKind::Run).unwrap();
+    assert_eq!(run.to_sorted_levels(), vec![vec![0, 2, 3], vec![1]]);
+
+    let ci = dr
+        .resolve_filtered(|kind| matches!(kind, EdgeKind::Build | EdgeKind::Dev))
+        .unwrap();
+    assert_eq!(ci.to_sorted_levels(), vec![vec![0, 1], vec![2], vec![3]]);
+
+    let mut edges = dr.edges_with_kind().collect::<Vec<_>>();
+    edges.sort();
+    assert_eq!(
+        edges,
+        vec![
+            (1, 0, EdgeKind::Run),
+            (2, 1, EdgeKind::Build),
+            (3, 0, EdgeKind::Normal),
+            (3, 0, EdgeKind::Build),
+            (3, 2, EdgeKind::Dev),
+        ]
+    );
+
+    // a plain dep added after the label keeps both
+    dr.add(&[dep(1, &[0])]);
+    assert_eq!(dr.edge_kinds(&1, &0), vec![EdgeKind::Normal, EdgeKind::Run]);
+}
+
+#[test]
+fn test_alias() {
+    // 1 needs the capability 10 that 0 provides, 11 is provided by nothing
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[10]), dep(2, &[11])]);
+    assert!(dr.resolve_nodes(&dr.ids, false).is_err());
+    dr.add_alias(10, 0).unwrap();
+    dr.remove(&2);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0], vec![1]]);
+    assert_eq!(dr.deps_of(&1), vec![10]);
+    assert_eq!(dr.resolved_deps_of(&1), vec![0]);
+
+    // chains resolve, an unresolved alias is a missing dep like any other
+    dr.add_alias(20, 10).unwrap();
+    dr.add(&[dep(3, &[20])]);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.level_of(&3), Some(1));
+    assert_eq!(r.level_of(&20), None);
+    dr.add_alias(30, 31).unwrap();
+    dr.add(&[dep(4, &[30])]);
+    assert_eq!(
+        dr.resolve_nodes(&dr.ids, false).err(),
+        Some(DepResolveError::IslandsOrCircular)
+    );
+
+    assert_eq!(
+        dr.add_alias(10, 1),
+        Err(DepResolveError::AliasConflict {
+            alias: 10,
+            existing: 0
+        })
+    );
+    assert_eq!(dr.add_alias(10, 0), Ok(()));
+    assert_eq!(dr.add_alias(31, 20), Ok(()));
+    assert_eq!(dr.add_alias(0, 30), Err(DepResolveError::AliasCycle(0)));
+    assert_eq!(dr.remove_alias(&30), Some(31));
+}
+
+#[test]
+fn test_groups() {
+    // "all" = "core" + 3, "core" = 0 + 1 and 1 sits on top of 2, 4 needs everything
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[2]),
+        dep(2, &[]),
+        dep(3, &[]),
+        dep(4, &[100]),
+    ]);
+    dr.define_group(101, [0, 1]).unwrap();
+    dr.define_group(100, [101, 3]).unwrap();
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0, 2, 3], vec![1], vec![4]]);
+    assert_eq!(r.level_of(&100), None);
+    assert_eq!(sorted(dr.resolved_deps_of(&4)), vec![0, 1, 3]);
+
+    // depending on a member directly as well changes nothing
+    dr.add(&[dep(5, &[101, 1])]);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.level_of(&5), Some(2));
+
+    assert_eq!(
+        dr.define_group(101, [100]),
+        Err(DepResolveError::GroupCycle(101))
+    );
+    assert_eq!(
+        dr.define_group(102, [102]),
+        Err(DepResolveError::GroupCycle(102))
+    );
+    assert_eq!(dr.group_members(&101), vec![0, 1]);
+    assert!(dr.remove_group(&100));
+    assert!(dr.pool.contains_key(&100));
+    assert!(dr.resolve_nodes(&dr.ids, false).is_err());
+}
+
+#[test]
+fn test_resolve_items() {
+    use std::sync::Mutex;
+
+    let items = [dep(0, &[]), dep(1, &[0]), dep(2, &[0]), dep(3, &[1, 2])];
+    let dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve_items(&items).unwrap();
+    assert_eq!(r.level_count(), 3);
+    assert_eq!(r.len(), 4);
+    let seen = Mutex::new(vec![]);
+    r.for_each_level_ok(|item| {
+        let seen = &mut *seen.lock().unwrap();
+        assert!(item.deps.iter().all(|dep| seen.contains(dep)));
+        seen.push(item.id);
+    });
+    assert_eq!(sorted(seen.into_inner().unwrap()), vec![0, 1, 2, 3]);
+    let levels = r
+        .iter_level()
+        .map(|level| sorted(level.deps.iter().map(|item| item.id).collect()))
+        .collect::<Vec<_>>();
+    assert_eq!(levels, vec![vec![0], vec![1, 2], vec![3]]);
+    assert_eq!(r.sorted_by_level()[0].id, 0);
+
+    let e = r.for_each_level(|item| match item.deps.len() {
+        2 => Err(item.id),
+        _ => Ok(()),
+    });
+    assert_eq!(
+        e,
+        Err(LevelError {
+            level: 2,
+            failures: vec![(3, 3)]
+        })
+    );
+
+    let partial = [dep(0, &[]), dep(1, &[0]), dep(7, &[])];
+    let e = dr.resolve_items(&partial);
+    let Err(DepResolveError::ItemsMismatch {
+        unknown_items,
+        missing_items,
+    }) = e
+    else {
+        panic!("expected a mismatch, got {e:?}");
+    };
+    assert_eq!(unknown_items, vec![7]);
+    assert_eq!(sorted(missing_items), vec![2, 3]);
+}
+
+#[test]
+fn test_resolve_isolated() {
+    // the test_1 fixture, 2 has neither deps nor dependents
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[]),
+        dep(3, &[]),
+        dep(4, &[3]),
+        dep(5, &[4]),
+    ]);
+
+    let r = dr.resolve_isolated(IsolatedPolicy::Level0).unwrap();
+    assert_eq!(
+        r.to_sorted_levels(),
+        vec![vec![0, 2, 3], vec![1, 4], vec![5]]
+    );
+    assert!(r.isolated().is_empty());
+
+    let r = dr.resolve_isolated(IsolatedPolicy::Separate).unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0, 3], vec![1, 4], vec![5]]);
+    assert_eq!(r.isolated(), &[2]);
+    assert_eq!(r.level_of(&2), None);
+
+    let r = dr.resolve_isolated(IsolatedPolicy::LastLevel).unwrap();
+    assert_eq!(
+        r.to_sorted_levels(),
+        vec![vec![0, 3], vec![1, 4], vec![5], vec![2]]
+    );
+
+    // nothing but isolated nodes
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[])]);
+    let r = dr.resolve_isolated(IsolatedPolicy::Separate).unwrap();
+    assert_eq!(r.level_count(), 0);
+    assert_eq!(sorted(r.isolated().to_vec()), vec![0, 1]);
+    let r = dr.resolve_isolated(IsolatedPolicy::LastLevel).unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0, 1]]);
+}
+
+/// A manifest borrowing its names out of the text it was parsed from
+struct Manifest<'a> {
+    name: &'a str,
+    deps: Vec<&'a str>,
+}
+
+impl<'a> DepMeta for Manifest<'a> {
+    type Id = &'a str;
+
+    fn get_id(&self) -> Self::Id {
+        self.name
+    }
+
+    fn get_deps(&self) -> &[Self::Id] {
+        &self.deps
+    }
+}
+
+#[test]
+fn test_str_ids() {
+    // the caller owns the text, ids are slices of it and copying one never allocates
+    let text = "\
+        app: http json log\n\
+        http: net log\n\
+        json:\n\
+        net: log\n\
+        log:\n";
+    let manifests = text
+        .lines()
+        .map(|line| {
+            let (name, deps) = line.split_once(':').unwrap();
+            Manifest {
+                name: name.trim(),
+                deps: deps.split_whitespace().collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut dr = DepRes::<&str>::new();
+    dr.add(&manifests);
+    let r = dr.resolve().unwrap().into_deterministic();
+    assert_eq!(
+        r.sorted_by_level(),
+        vec!["json", "log", "net", "http", "app"]
+    );
+    assert_eq!(r.level_of(&"app"), Some(3));
+    let mut deps = dr.deps_of(&"http");
+    deps.sort();
+    assert_eq!(deps, vec!["log", "net"]);
+}
+
+#[test]
+fn test_resolve_with_report() {
+    let dr = DepRes::<usize>::new();
+    dr.add(&[
+        // cycle 1 -> 2 -> 3 -> 1
+        dep(1, &[2]),
+        dep(2, &[3]),
+        dep(3, &[1]),
+        // cycle 4 <-> 5, 6 only depends on it
+        dep(4, &[5]),
+        dep(5, &[4]),
+        dep(6, &[4]),
+        // 7 misses both its deps, 8 misses one and is leveled after 0 all the same
+        dep(7, &[100, 101]),
+        dep(8, &[0, 102]),
+        dep(0, &[]),
+    ]);
+    // a soft edge cycle 9 <-> 10 is broken by resolving and is no problem
+    dr.add(&[dep(9, &[]), dep(10, &[])]);
+    dr.add_soft_edge(9, 10);
+    dr.add_soft_edge(10, 9);
+    let report = dr.resolve_with_report().unwrap_err();
+    assert_eq!(report.problems.len(), 5);
+    let mut missing = vec![];
+    let mut cycles = vec![];
+    for problem in &report.problems {
+        match problem {
+            ResolveProblem::MissingDep { id, dep } => missing.push((*id, *dep)),
+            ResolveProblem::Cycle(cycle) => cycles.push(sorted(cycle.clone())),
+            p => panic!("unexpected {p:?}"),
+        }
+    }
+    missing.sort();
+    cycles.sort();
+    assert_eq!(missing, vec![(7, 100), (7, 101), (8, 102)]);
+    assert_eq!(cycles, vec![vec![1, 2, 3], vec![4, 5]]);
+    assert_eq!(sorted(report.unleveled.clone()), vec![1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(
+        report.to_string(),
+        "5 problem(s) found, 7 node(s) left unleveled"
+    );
+
+    let dr = DepRes::<usize>::new();
+    dr.add(&[dep(1, &[1]), dep(2, &[])]);
+    let report = dr.resolve_with_report().unwrap_err();
+    assert_eq!(report.problems, vec![ResolveProblem::SelfLoop(1)]);
+    assert_eq!(report.unleveled, vec![1]);
+    assert_eq!(report.problems[0].to_string(), "self loop: 1 depends on itself");
+    assert_eq!(
+        ResolveProblem::Cycle(vec![4, 5]).to_string(),
+        "cycle: 4 -> 5 -> 4"
+    );
+    assert_eq!(
+        ResolveProblem::MissingDep { id: 7, dep: 100 }.to_string(),
+        "missing dep: 7 depends on 100, which is no node"
+    );
+
+    // no problems, no report
+    let dr = DepRes::<usize>::new();
+    dr.add(&diamond());
+    assert!(dr.resolve_with_report().is_ok());
+}
+
+#[cfg(feature = "build-files")]
+#[test]
+fn test_to_makefile() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let make = dr.to_makefile(|id| format!("echo {id} $HOME\necho done"));
+    assert!(make.contains(".PHONY: all\nall: 1 2 5\n"));
+    assert!(make.contains("\n# \"5\"\n5: 4\n\techo 5 $$HOME\n\techo done\n"));
+    // every prerequisite line is exactly one node's deps
+    let mut prerequisites = make
+        .lines()
+        .filter(|line| !line.starts_with(['#', '\t', '.']) && line.contains(':'))
+        .filter(|line| !line.starts_with("all:"))
+        .flat_map(|line| {
+            let (id, deps) = line.split_once(':').unwrap();
+            let id = id.parse::<usize>().unwrap();
+            deps.split_whitespace()
+                .map(move |dep| (id, dep.parse::<usize>().unwrap()))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    prerequisites.sort();
+    let mut edges = dr.edges().collect::<Vec<_>>();
+    edges.sort();
+    assert_eq!(prerequisites, edges);
+
+    let ninja = dr.to_ninja(|id| format!("echo {id} $HOME\necho done"));
+    assert!(ninja.contains("rule cmd5\n  command = echo 5 $$HOME && echo done\nbuild 5: cmd5 4\n"));
+    assert!(ninja.ends_with("\nbuild all: phony 1 2 5\ndefault all\n"));
+}
+
+#[cfg(feature = "build-files")]
+#[test]
+fn test_build_files_names() {
+    #[derive(Debug)]
+    struct StrDep(&'static str, Vec<&'static str>);
+    impl DepMeta for StrDep {
+        type Id = &'static str;
+        fn get_id(&self) -> &'static str {
+            self.0
+        }
+        fn get_deps(&self) -> &[&'static str] {
+            &self.1
+        }
+    }
+    let dr = DepRes::new();
+    dr.add(&vec![
+        StrDep("my app", vec!["lib:core", "$HOME", "a_b"]),
+        StrDep("lib:core", vec![".PHONY"]),
+        StrDep(".PHONY", vec![]),
+        StrDep("$HOME", vec![]),
+        StrDep("a_b", vec![]),
+        StrDep("a b", vec![]),
+        StrDep("all", vec!["gone"]),
+    ]);
+    dr.add_alias("core", "lib:core").unwrap();
+    let make = dr.to_makefile(|id| format!("build '{id}'"));
+    assert!(make.contains("\n# \"my app\"\nmy_app: _HOME a_b_2 lib_core\n\tbuild 'my app'\n"));
+    assert!(make.contains("\n# \"$HOME\"\n_HOME:\n\tbuild '$$HOME'\n"));
+    // a leading dot would make a special target
+    assert!(make.contains("\n# \"lib:core\"\nlib_core: _.PHONY\n"));
+    assert!(make.contains("\n# \".PHONY\"\n_.PHONY:\n"));
+    assert!(!make.contains("\n.PHONY:\n"));
+    // "a b" sorts before "a_b", so it gets the plain name even though the other one is
+    // already valid, and "all" is taken by the aggregate target
+    assert!(make.contains("\n# \"a b\"\na_b:\n"));
+    assert!(make.contains("\n# \"a_b\"\na_b_2:\n"));
+    assert!(make.contains("\n# \"all\"\nall_2: gone\n"));
+    assert!(make.contains("all: a_b all_2 my_app\n"));
+    assert!(!make.contains("\ngone:"));
+    // generating again gives the same names
+    assert_eq!(make, dr.to_makefile(|id| format!("build '{id}'")));
+
+    dr.add(&vec![StrDep("tool", vec!["core"])]);
+    let make = dr.to_makefile(|id| format!("build '{id}'"));
+    assert!(make.contains(".PHONY: all core\n"));
+    assert!(make.contains("\n# \"core\"\ncore: lib_core\n\n"));
+    let ninja = dr.to_ninja(|id| format!("build '{id}'"));
+    assert!(ninja.contains("\n# \"core\"\nbuild core: phony lib_core\n"));
+}
+
+#[test]
+fn test_resolve_shares_ids() {
+    let _lock = CANARY_LOCK.lock().unwrap();
+    let node = |id: usize, deps: &[usize]| CanaryDep {
+        id: Canary(id),
+        deps: deps.iter().map(|&d| Canary(d)).collect(),
+    };
+    let clones = || CANARY_CLONES.load(std::sync::atomic::Ordering::Relaxed);
+    let mut dr = DepRes::new();
+    let items = (0..100)
+        .map(|id| node(id, &(0..id % 7).collect::<Vec<_>>()))
+        .collect::<Vec<_>>();
+    let before = clones();
+    dr.add(&items);
+    // once per id on the way in, deps are ids of the batch and get the same allocation
+    assert_eq!(clones() - before, 100);
+
+    let before = clones();
+    let r = dr.resolve().unwrap().into_deterministic();
+    assert_eq!(r.level_of(&Canary(6)), Some(6));
+    assert!(r.is_before(&Canary(0), &Canary(1)).unwrap());
+    let shared = r.sorted_by_level_shared();
+    let levels = r.to_levels_shared();
+    let left = r.filter(|id| id.0 % 2 == 0, true);
+    let (head, tail) = left.split_at_level(2);
+    assert_eq!(head.len() + tail.len(), 50);
+    assert_eq!(clones() - before, 0);
+    assert_eq!(shared.len(), 100);
+    assert_eq!(levels.concat(), shared);
+    assert!(Arc::ptr_eq(&shared[0], &dr.pool.key_of(&Canary(0)).unwrap()));
+
+    // the copying variants copy exactly what they hand out
+    let before = clones();
+    assert_eq!(r.sorted_by_level().len(), 100);
+    assert_eq!(clones() - before, 100);
+}
+
+#[test]
+fn test_retain() {
+    let mut dr = DepRes::new();
+    dr.add(&diamond());
+    dr.add(&[dep(4, &[9])]);
+    // drop the left half and the bottom, 2 only depended on 0 and becomes a root
+    dr.retain(|&id| id != 0 && id != 1);
+    assert_eq!(sorted(dr.ids().collect()), vec![2, 3, 4]);
+    let mut edges = dr.edges().collect::<Vec<_>>();
+    edges.sort();
+    // the dep on 9 never pointed at a node, so it stays
+    assert_eq!(edges, vec![(3, 2), (4, 9)]);
+    assert!(!dr.deps.contains_key(&2));
+    assert!(!dr.pool.contains_key(&0));
+    assert!(!dr.pool.contains_key(&1));
+    dr.retain(|&id| id != 4);
+    assert!(!dr.pool.contains_key(&9));
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![2], vec![3]]);
+
+    let mut dr = DepRes::new();
+    dr.add(&diamond());
+    dr.add(&[dep(5, &[]), dep(6, &[5])]);
+    dr.add_soft_edge(5, 1);
+    dr.retain_with_dependents(|&id| id != 1);
+    // 3 needed 1, 5 only softly depended on it
+    assert_eq!(sorted(dr.ids().collect()), vec![0, 2, 5, 6]);
+    let mut edges = dr.edges().collect::<Vec<_>>();
+    edges.sort();
+    assert_eq!(edges, vec![(2, 0), (6, 5)]);
+    assert!(!dr.soft.contains_key(&5));
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0, 5], vec![2, 6]]);
+}
+
+#[test]
+fn test_resolution_diff() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let before = dr.resolve().unwrap();
+    let diff = before.diff(&dr.resolve().unwrap());
+    assert!(diff.is_empty());
+
+    // 2 had no deps, depending on 0 pushes it down one level and nothing else moves
+    dr.add(&[dep(2, &[0])]);
+    let after = dr.resolve().unwrap();
+    let diff = before.diff(&after);
+    assert_eq!(
+        diff,
+        ResolutionDiff {
+            added: vec![],
+            removed: vec![],
+            moved: vec![(2, 0, 1)],
+        }
+    );
+
+    dr.remove(&5);
+    dr.add(&[dep(7, &[]), dep(6, &[])]);
+    let diff = after.diff(&dr.resolve().unwrap()).into_deterministic();
+    assert_eq!(diff.added, vec![6, 7]);
+    assert_eq!(diff.removed, vec![5]);
+    assert!(diff.moved.is_empty());
+    #[cfg(feature = "serde")]
+    assert_eq!(
+        serde_json::to_string(&diff).unwrap(),
+        r#"{"added":[6,7],"removed":[5],"moved":[]}"#
+    );
+}
+
+#[test]
+fn test_fingerprint() {
+    let items = random_dag(200, 4, 7);
+    let a = DepRes::new();
+    a.add(&items);
+    let b = DepRes::new();
+    for chunk in items.chunks(13).rev() {
+        b.add(&chunk.iter().rev().map(|d| dep(d.id, &d.deps)).collect::<Vec<_>>());
+    }
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.fingerprint(), a.clone().fingerprint());
+
+    b.add(&[dep(199, &[0])]);
+    let with_edge = b.fingerprint();
+    assert_ne!(a.fingerprint(), with_edge);
+    // the same pair as a soft edge or an alias is something else again
+    let c = DepRes::new();
+    c.add(&items);
+    c.add_soft_edge(199, 0);
+    assert_ne!(c.fingerprint(), with_edge);
+    assert_ne!(c.fingerprint(), a.fingerprint());
+
+    let r = a.resolve_deterministic().unwrap();
+    assert_eq!(r.fingerprint(), a.resolve_nodes(&a.ids, false).unwrap().fingerprint());
+    assert_ne!(r.fingerprint(), a.fingerprint());
+    // the extra edge didn't move 199, so only the graph hash changed
+    assert_eq!(r.fingerprint(), b.resolve_nodes(&b.ids, false).unwrap().fingerprint());
+    let d = DepRes::new();
+    d.add(&diamond());
+    let before = d.resolve_nodes(&d.ids, false).unwrap().fingerprint();
+    d.add(&[dep(2, &[1])]);
+    assert_ne!(d.resolve_nodes(&d.ids, false).unwrap().fingerprint(), before);
+    assert_ne!(
+        DepRes::<usize>::new().fingerprint(),
+        ResolvedDeps::<usize>::default().fingerprint()
+    );
+}
+
+#[test]
+fn test_resolve_cached() {
+    let dr = DepRes::new();
+    dr.add(&diamond());
+    let a = dr.resolve_cached().unwrap();
+    let b = dr.resolve_cached().unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+    assert_eq!(a.to_sorted_levels(), vec![vec![0], vec![1, 2], vec![3]]);
+
+    dr.add(&[dep(4, &[3])]);
+    let c = dr.resolve_cached().unwrap();
+    assert!(!Arc::ptr_eq(&a, &c));
+    assert_eq!(c.level_of(&4), Some(3));
+    assert!(Arc::ptr_eq(&c, &dr.resolve_cached().unwrap()));
+    dr.set_max_levels(2);
+    assert!(dr.resolve_cached().is_err());
+    dr.set_max_levels(usize::MAX);
+    assert!(!Arc::ptr_eq(&c, &dr.resolve_cached().unwrap()));
+
+    // every thread gets the one computed result
+    let dr = DepRes::new();
+    dr.add(&random_dag(2000, 4, 3));
+    let results = std::thread::scope(|s| {
+        let handles = (0..8)
+            .map(|_| s.spawn(|| dr.resolve_cached().unwrap()))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    let computed = results
+        .iter()
+        .map(Arc::as_ptr)
+        .collect::<HashSet<_>>()
+        .len();
+    assert_eq!(computed, 1);
+}
+
+#[cfg(feature = "cargo-metadata")]
+#[test]
+fn test_from_cargo_metadata() {
+    let json = include_str!("../tests/data/cargo-metadata.json");
+    let levels = |dr: &DepRes<String>| dr.resolve_deterministic().unwrap().to_levels();
+    let names = |levels: &[&[&str]]| {
+        (levels.iter())
+            .map(|level| level.iter().map(|id| id.to_string()).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+    let dr = DepRes::from_cargo_metadata(json, &CargoMetadataOptions::default()).unwrap();
+    assert_eq!(
+        levels(&dr),
+        names(&[&["core", "macros"], &["util"], &["app", "testkit"]])
+    );
+    assert_eq!(
+        dr.edge_kinds(&"util".into(), &"macros".into()),
+        vec![EdgeKind::Build]
+    );
+    assert!(!dr.contains("itoa"));
+
+    // the dev dep of app on testkit now counts
+    let with_dev = CargoMetadataOptions {
+        kinds: vec![EdgeKind::Normal, EdgeKind::Build, EdgeKind::Dev],
+        ..CargoMetadataOptions::default()
+    };
+    let dr = DepRes::from_cargo_metadata(json, &with_dev).unwrap();
+    assert_eq!(
+        levels(&dr),
+        names(&[&["core", "macros"], &["util"], &["testkit"], &["app"]])
+    );
+
+    let external = CargoMetadataOptions {
+        include_external: true,
+        ..CargoMetadataOptions::default()
+    };
+    let dr = DepRes::from_cargo_metadata(json, &external).unwrap();
+    assert_eq!(
+        levels(&dr),
+        names(&[
+            &["itoa", "macros"],
+            &["core"],
+            &["util"],
+            &["app", "testkit"]
+        ])
+    );
+
+    let by_id = CargoMetadataOptions {
+        by_package_id: true,
+        ..CargoMetadataOptions::default()
+    };
+    let dr = DepRes::from_cargo_metadata(json, &by_id).unwrap();
+    assert!(dr.depends_on(
+        "path+file:///work/ws/util#0.1.0",
+        "path+file:///work/ws/core#0.1.0"
+    ));
+
+    // --no-deps output has no resolve graph, deps are then matched by name
+    let mut value = serde_json::from_str::<serde_json::Value>(json).unwrap();
+    value["resolve"] = serde_json::Value::Null;
+    let no_deps = value.to_string();
+    let dr = DepRes::from_cargo_metadata(&no_deps, &CargoMetadataOptions::default()).unwrap();
+    assert_eq!(
+        levels(&dr),
+        names(&[&["core", "macros"], &["util"], &["app", "testkit"]])
+    );
+    assert!(matches!(
+        DepRes::from_cargo_metadata(&no_deps, &external),
+        Err(CargoMetadataError::MissingResolve)
+    ));
+    assert!(matches!(
+        DepRes::from_cargo_metadata("{", &external),
+        Err(CargoMetadataError::Json(_))
+    ));
+}
+
+#[test]
+fn test_iter_topo() {
+    let mut dr = DepRes::new();
+    dr.add(&random_dag(300, 3, 11));
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.iter_topo().collect::<Vec<_>>(), r.sorted_by_level());
+    let mut rev = r.sorted_by_level();
+    rev.reverse();
+    assert_eq!(r.iter_topo_rev().collect::<Vec<_>>(), rev);
+    assert_eq!(
+        r.iter_topo_sorted().collect::<Vec<_>>(),
+        r.to_sorted_levels().concat()
+    );
+
+    let _lock = CANARY_LOCK.lock().unwrap();
+    let node = |id: usize, deps: &[usize]| CanaryDep {
+        id: Canary(id),
+        deps: deps.iter().map(|&d| Canary(d)).collect(),
+    };
+    let mut dr = DepRes::new();
+    dr.add(&(0..1000).map(|id| node(id, &[id / 2])).skip(1).collect::<Vec<_>>());
+    dr.add(&[node(0, &[])]);
+    let r = dr.resolve().unwrap();
+    let clones = || CANARY_CLONES.load(std::sync::atomic::Ordering::Relaxed);
+    let before = clones();
+    let first = r.iter_topo().take(3).collect::<Vec<_>>();
+    assert_eq!(first[0], Canary(0));
+    assert_eq!(r.iter_topo_sorted().take(3).collect::<Vec<_>>(), [0, 1, 2].map(Canary));
+    assert_eq!(r.iter_topo_rev().take(3).count(), 3);
+    // nothing past what was taken got copied
+    assert_eq!(clones() - before, 9);
+}
+
+#[test]
+fn test_eq() {
+    let items = random_dag(300, 4, 5);
+    let a = DepRes::new();
+    a.add(&items);
+    let b = DepRes::new();
+    for item in items.iter().rev() {
+        b.add(&[dep(item.id, &item.deps)]);
+    }
+    assert_eq!(a, b);
+    assert_eq!(a, a.clone());
+
+    b.add(&[dep(299, &[0])]);
+    assert!(a.deps.read(&299, |_, deps| deps.contains(&0)) != Some(true));
+    assert_ne!(a, b);
+    assert_ne!(b, a);
+    let c = a.clone();
+    c.add_soft_edge(299, 0);
+    assert_ne!(a, c);
+    let c = a.clone();
+    c.define_group(1000, [1, 2]).unwrap();
+    let d = a.clone();
+    d.define_group(1000, [2, 1]).unwrap();
+    assert_eq!(c, d);
+    assert_ne!(a, c);
+
+    let r = a.resolve_deterministic().unwrap();
+    assert_eq!(r, a.resolve_nodes(&a.ids, false).unwrap());
+    #[cfg(parallel)]
+    for threads in [1, 2, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        assert_eq!(a.resolve_on(&pool).unwrap(), r);
+    }
+    assert_ne!(r.filter(|&id| id != 0, false), r);
+    assert_eq!(r.filter(|_| true, false), r);
+}
+
+#[test]
+fn test_add_iter() {
+    let items = random_dag(500, 5, 9);
+    // one line per item, some repeated with other deps so the dep sets have to be merged
+    let text = items
+        .iter()
+        .chain(&[dep(3, &[1]), dep(3, &[2, 2]), dep(7, &[])])
+        .map(|d| {
+            let deps = d.deps.iter().map(|d| d.to_string()).collect::<Vec<_>>();
+            format!("{}:{}", d.id, deps.join(","))
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let parse = |line: &str| {
+        let (id, deps) = line.split_once(':').unwrap();
+        let deps = (deps.split(','))
+            .filter(|d| !d.is_empty())
+            .map(|d| d.parse().unwrap())
+            .collect::<Vec<_>>();
+        dep(id.parse().unwrap(), &deps)
+    };
+    let streamed = DepRes::new();
+    streamed.add_iter(text.lines().map(parse));
+    let collected = DepRes::new();
+    collected.add(&text.lines().map(parse).collect::<Vec<_>>());
+    assert_eq!(streamed, collected);
+    assert_eq!(streamed.fingerprint(), collected.fingerprint());
+
+    let by_id = items
+        .iter()
+        .map(|d| (d.id, d.deps.clone()))
+        .collect::<BTreeMap<_, _>>();
+    let from_map = DepRes::new();
+    from_map.add_iter(by_id.iter().map(|(&id, deps)| dep(id, deps)));
+    let from_items = DepRes::new();
+    from_items.add(&items);
+    assert_eq!(from_map, from_items);
+}
+
+#[test]
+fn test_dyn_items() {
+    struct Plugin {
+        name: usize,
+        needs: Vec<usize>,
+    }
+    impl DepMeta for Plugin {
+        type Id = usize;
+        fn get_id(&self) -> usize {
+            self.name
+        }
+        fn get_deps(&self) -> &[usize] {
+            &self.needs
+        }
+    }
+    let items: Vec<Box<dyn DepMeta<Id = usize> + Send + Sync>> = vec![
+        Box::new(dep(0, &[])),
+        Box::new(Plugin {
+            name: 1,
+            needs: vec![0],
+        }),
+        Box::new(SoftDep {
+            id: 2,
+            deps: vec![1],
+            soft: vec![3],
+        }),
+        Box::new(dep(3, &[0])),
+    ];
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0], vec![1, 3], vec![2]]);
+
+    let borrowed = items
+        .iter()
+        .map(|item| &**item as &dyn DepMeta<Id = usize>)
+        .collect::<Vec<_>>();
+    let other = DepRes::new();
+    other.add_iter(borrowed);
+    assert_eq!(other, dr);
+}
+
+#[test]
+fn test_resolve_ext() {
+    let items = diamond();
+    let r = items.iter().resolve_deps().unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0], vec![1, 2], vec![3]]);
+    let dr = items.iter().collect_dep_res();
+    assert_eq!(dr.transitive_dependents(&1), hset(&[3]));
+
+    // entries of a map, turned into items on the fly
+    let manifest = BTreeMap::from([("c", vec!["a", "b"]), ("b", vec!["a"]), ("a", vec![])]);
+    let r = manifest
+        .iter()
+        .map(|(&id, deps)| Manifest {
+            name: id,
+            deps: deps.clone(),
+        })
+        .resolve_deps()
+        .unwrap();
+    assert_eq!(r.sorted_by_level(), vec!["a", "b", "c"]);
+    assert_eq!(
+        vec![dep(0, &[1])].resolve_deps().err(),
+        Some(DepResolveError::IslandsOrCircular)
+    );
+
+    #[cfg(parallel)]
+    {
+        let items = random_dag(1000, 4, 21);
+        let r = items.par_iter().resolve_deps().unwrap();
+        let dr = DepRes::new();
+        dr.add(&items);
+        assert_eq!(r, dr.resolve_deterministic().unwrap());
+        assert_eq!(items.par_iter().collect_dep_res(), dr);
+    }
+}
+
+#[test]
+fn test_dep() {
+    let items = vec![
+        Dep::new(0),
+        Dep::with_deps(1, [0]),
+        (2, vec![0, 1]).into(),
+    ];
+    assert_eq!(items[2], Dep::with_deps(2, vec![0, 1]));
+    let dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.to_sorted_levels(), vec![vec![0], vec![1], vec![2]]);
+
+    let dr = DepRes::new();
+    dr.add(&vec![Dep::new("a"), Dep::with_deps("b", ["a"])]);
+    assert_eq!(dr.transitive_dependents(&"a"), HashSet::from(["b"]));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serde_dep() {
+    let item = Dep::with_deps(1, [0]);
+    let json = serde_json::to_string(&item).unwrap();
+    assert_eq!(json, r#"{"id":1,"deps":[0]}"#);
+    assert_eq!(serde_json::from_str::<Dep<usize>>(&json).unwrap(), item);
+
+    let items: Vec<Dep<String>> =
+        serde_json::from_str(r#"[{"id":"a"},{"id":"b","deps":["a"]}]"#).unwrap();
+    assert_eq!(items[0], Dep::new("a".to_string()));
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    assert_eq!(dr.resolve().unwrap().sorted_by_level(), vec!["a", "b"]);
+}
+
+#[cfg(feature = "miette")]
+fn narrate(diagnostic: &dyn miette::Diagnostic) -> String {
+    let mut out = String::new();
+    miette::NarratableReportHandler::new()
+        .render_report(&mut out, diagnostic)
+        .unwrap();
+    out
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_miette_cycle() {
+    let manifests = [
+        ("a", "name = \"a\"\ndeps = [\"c\"]\n"),
+        ("b", "name = \"b\"\ndeps = [\"a\"]\n"),
+        ("c", "name = \"c\"\ndeps = [\"b\"]\n"),
+        ("d", "name = \"d\"\ndeps = [\"a\"]\n"),
+    ];
+    let dr = DepRes::new();
+    dr.add(&vec![
+        Dep::with_deps("a", ["c"]),
+        Dep::with_deps("b", ["a"]),
+        Dep::with_deps("c", ["b"]),
+        Dep::with_deps("d", ["a"]),
+    ]);
+    let mut report = dr.resolve_with_report().unwrap_err();
+    // where the cycle starts depends on iteration order
+    if let ResolveProblem::Cycle(ids) = &mut report.problems[0] {
+        let start = ids.iter().position(|&id| id == "a").unwrap();
+        ids.rotate_left(start);
+    }
+    assert_eq!(report.problems, vec![ResolveProblem::Cycle(vec!["a", "c", "b"])]);
+    assert_eq!(
+        narrate(&report),
+        r#"1 problem(s) found, 4 node(s) left unleveled
+    Diagnostic severity: error
+diagnostic code: dep_res::unresolvable
+
+Error: cycle: a -> c -> b -> a
+    Diagnostic severity: error
+
+diagnostic help: drop one of the edges, or turn it into a soft edge with DepRes::add_soft_edge
+diagnostic code: dep_res::cycle
+"#
+    );
+
+    let mut sources = DiagnosticSources::new();
+    for (id, text) in manifests {
+        sources.insert(id, format!("{id}/dep.toml"), text);
+    }
+    assert_eq!(
+        narrate(&report.with_sources(&sources)),
+        r#"1 problem(s) found, 4 node(s) left unleveled
+    Diagnostic severity: error
+diagnostic code: dep_res::unresolvable
+
+Error: cycle: a -> c -> b -> a
+    Diagnostic severity: error
+
+diagnostic help: drop one of the edges, or turn it into a soft edge with DepRes::add_soft_edge
+diagnostic code: dep_res::cycle
+
+Error: a declares a dep on c
+    Diagnostic severity: error
+
+Begin snippet for a/dep.toml starting at line 1, column 1
+
+snippet line 1: name = "a"
+snippet line 2: deps = ["c"]
+    label at line 2, column 10: a depends on c
+Error: c declares a dep on b
+    Diagnostic severity: error
+
+Begin snippet for c/dep.toml starting at line 1, column 1
+
+snippet line 1: name = "c"
+snippet line 2: deps = ["b"]
+    label at line 2, column 10: c depends on b
+Error: b declares a dep on a
+    Diagnostic severity: error
+
+Begin snippet for b/dep.toml starting at line 1, column 1
+
+snippet line 1: name = "b"
+snippet line 2: deps = ["a"]
+    label at line 2, column 10: b depends on a
+"#
+    );
+}
+
+#[cfg(feature = "miette")]
+#[test]
+fn test_miette_missing_dep() {
+    let dr = DepRes::new();
+    dr.add(&vec![Dep::new("a"), Dep::with_deps("b", ["x"])]);
+    let report = dr.resolve_with_report().unwrap_err();
+    assert_eq!(
+        narrate(&report),
+        r#"1 problem(s) found, 1 node(s) left unleveled
+    Diagnostic severity: error
+diagnostic code: dep_res::unresolvable
+
+Error: missing dep: b depends on x, which is no node
+    Diagnostic severity: error
+
+diagnostic help: add x to the graph or drop it from the deps of b
+diagnostic code: dep_res::missing_dep
+"#
+    );
+    let mut sources = DiagnosticSources::new();
+    sources.insert("b", "b/dep.toml", "name = \"b\"\ndeps = [\"x\"]\n");
+    assert_eq!(
+        narrate(&report.with_sources(&sources)),
+        r#"1 problem(s) found, 1 node(s) left unleveled
+    Diagnostic severity: error
+diagnostic code: dep_res::unresolvable
+
+Error: missing dep: b depends on x, which is no node
+    Diagnostic severity: error
+
+Begin snippet for b/dep.toml starting at line 1, column 1
+
+snippet line 1: name = "b"
+snippet line 2: deps = ["x"]
+    label at line 2, column 10: x is no node
+diagnostic help: add x to the graph or drop it from the deps of b
+diagnostic code: dep_res::missing_dep
+"#
+    );
+    // the label lands on the dep, not on the name
+    let dr = DepRes::new();
+    dr.add(&vec![Dep::with_deps("s", ["s"])]);
+    let mut sources = DiagnosticSources::new();
+    sources.insert("s", "s/dep.toml", "name = \"s\"\ndeps = [\"s\"]\n");
+    let report = dr.resolve_with_report().unwrap_err().with_sources(&sources);
+    assert!(narrate(&report).contains("label at line 2, column 10: s depends on s\n"));
+    assert_eq!(report.into_report().problems, vec![ResolveProblem::SelfLoop("s")]);
+    assert_eq!(
+        narrate(&DepResolveError::<&str>::IslandsOrCircular),
+        r#"There are islands or circular reference dependencies
+    Diagnostic severity: error
+diagnostic help: DepRes::resolve_with_report lists every cycle and missing dep
+diagnostic code: dep_res::islands_or_circular
+"#
+    );
+}
+
+/// Runs `f` with a subscriber for everything on this thread, returns what it printed
+#[cfg(feature = "tracing")]
+fn traced(f: impl FnOnce()) -> String {
+    use tracing_subscriber::fmt::format::FmtSpan;
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_writer(move || writer.clone())
+        .with_max_level(tracing::Level::TRACE)
+        .with_span_events(FmtSpan::CLOSE)
+        .without_time()
+        .finish();
+    tracing::subscriber::with_default(subscriber, f);
+    let out = buffer.0.lock().unwrap().clone();
+    String::from_utf8(out).unwrap()
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn test_tracing() {
+    let mut dr = DepRes::new();
+    let log = traced(|| {
+        dr.add(&fixture());
+        dr.resolve().unwrap();
+    });
+    let lines = log.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            "DEBUG add{items=6 edges=3}: dep_res::trace: close",
+            "TRACE resolve{nodes=6 edges=3}: dep_res::interned: level placed level=0 width=3",
+            "TRACE resolve{nodes=6 edges=3}: dep_res::interned: level placed level=1 width=2",
+            "TRACE resolve{nodes=6 edges=3}: dep_res::interned: level placed level=2 width=1",
+            "DEBUG resolve{nodes=6 edges=3}: dep_res: close",
+        ]
+    );
+
+    let mut dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[0, 2]), dep(2, &[1])]);
+    let log = traced(|| {
+        dr.resolve().unwrap_err();
+    });
+    assert!(log.contains(
+        "resolve failed error=\"IslandsOrCircular\" placed=1 unplaced=2\n"
+    ));
+    dr.set_max_levels(0);
+    let log = traced(|| {
+        dr.resolve().unwrap_err();
+    });
+    assert!(log.contains(
+        "resolve failed error=\"DepthLimitExceeded\" placed=0 unplaced=3\n"
+    ));
+}
+
+#[cfg(feature = "gen")]
+#[test]
+fn test_graph_gen() {
+    for seed in 0..32 {
+        let (density, depth) = [(0.0, 1), (0.05, 5), (0.2, 20), (1.0, 3)][seed as usize % 4];
+        let gen = GraphGen::new(200).edge_density(density).max_depth(depth).seed(seed);
+        assert_eq!(gen.items(), gen.items());
+        let mut dr = gen.build();
+        assert_eq!(dr.node_count(), 200);
+        let resolved = dr.resolve().unwrap();
+        assert_eq!(check_resolution(&dr, &resolved), Ok(()));
+        assert!(resolved.to_sorted_levels().len() <= depth as usize);
+    }
+    // without max_depth every node may depend on any lower id
+    let mut dr = GraphGen::new(300).edge_density(0.02).seed(7).build();
+    let resolved = dr.resolve().unwrap();
+    assert_eq!(check_resolution(&dr, &resolved), Ok(()));
+    assert_ne!(GraphGen::new(300).seed(1).items(), GraphGen::new(300).seed(2).items());
+    assert!(GraphGen::new(0).self_loops(3).missing_deps(3).build().is_empty());
+
+    let dr = GraphGen::new(100).seed(3).cycles(2).build();
+    let report = dr.resolve_with_report().unwrap_err();
+    assert!(report
+        .problems
+        .iter()
+        .any(|p| matches!(p, ResolveProblem::Cycle(_))));
+
+    let dr = GraphGen::new(100).edge_density(0.0).seed(3).self_loops(4).build();
+    let report = dr.resolve_with_report().unwrap_err();
+    assert_eq!(report.problems.len(), 4);
+    assert!(report
+        .problems
+        .iter()
+        .all(|p| matches!(p, ResolveProblem::SelfLoop(_))));
+
+    let gen = GraphGen::new(100).edge_density(0.0).seed(3).missing_deps(5);
+    let mut missing = gen.items().into_iter().flat_map(|item| item.deps).collect::<Vec<_>>();
+    missing.sort();
+    assert_eq!(missing, vec![100, 101, 102, 103, 104]);
+    let report = gen.build().resolve_with_report().unwrap_err();
+    assert_eq!(report.problems.len(), 5);
+}
+
+#[test]
+fn test_transpose() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let t = dr.transpose();
+    assert_eq!(t.node_count(), 6);
+    assert_eq!(t.edges().collect::<HashSet<_>>(), HashSet::from([(0, 1), (3, 4), (4, 5)]));
+    assert_eq!(t.transpose(), dr);
+    assert_eq!(t.transpose().fingerprint(), dr.fingerprint());
+
+    let dr = DepRes::new();
+    dr.add(&random_dag(500, 4, 9));
+    assert_eq!(dr.transpose().transpose(), dr);
+
+    // a chain levels back to front
+    let dr = DepRes::new();
+    dr.add(&chain(5));
+    let levels = dr.resolve_nodes(&dr.ids, false).unwrap().to_sorted_levels();
+    let t = dr.transpose();
+    let mut reversed = t.resolve_nodes(&t.ids, false).unwrap().to_sorted_levels();
+    reversed.reverse();
+    assert_eq!(reversed, levels);
+
+    // deps on ids that are no node are dropped, soft edges flip too
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[9]), dep(1, &[0])]);
+    dr.add_soft_edge(0, 1);
+    let t = dr.transpose();
+    assert_eq!(t.edges().collect::<Vec<_>>(), vec![(0, 1)]);
+    assert!(!t.contains(&9));
+    assert_eq!(t.transpose().edges().collect::<Vec<_>>(), vec![(1, 0)]);
+    // the flipped soft edge still closes a cycle with the hard one, so nothing is dropped
+    assert_eq!(t.resolve_with_dropped().err(), Some(DepResolveError::IslandsOrCircular));
+}
+
+#[test]
+fn test_connected_components() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    assert_eq!(dr.connected_components().len(), 3);
+    assert_eq!(
+        dr.connected_components_sorted(),
+        vec![vec![0, 1], vec![2], vec![3, 4, 5]]
+    );
+    // unsorted, each id still shows up exactly once
+    let mut all = dr.connected_components().concat();
+    all.sort();
+    assert_eq!(all, vec![0, 1, 2, 3, 4, 5]);
+
+    dr.add(&[dep(2, &[1])]);
+    assert_eq!(
+        dr.connected_components_sorted(),
+        vec![vec![0, 1, 2], vec![3, 4, 5]]
+    );
+    dr.add_soft_edge(0, 5);
+    assert_eq!(dr.connected_components_sorted(), vec![vec![0, 1, 2, 3, 4, 5]]);
+
+    // missing deps join nothing, aliases join what they stand for
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[9]), dep(1, &[9]), dep(2, &[8]), dep(3, &[])]);
+    assert_eq!(
+        dr.connected_components_sorted(),
+        vec![vec![0], vec![1], vec![2], vec![3]]
+    );
+    dr.add_alias(8, 3).unwrap();
+    assert_eq!(
+        dr.connected_components_sorted(),
+        vec![vec![0], vec![1], vec![2, 3]]
+    );
+    assert!(DepRes::<usize>::new().connected_components().is_empty());
+}
+
+#[test]
+fn test_resolve_per_component() {
+    let healthy = [dep(0, &[]), dep(1, &[0]), dep(2, &[1]), dep(10, &[]), dep(11, &[10])];
+    let dr = DepRes::new();
+    dr.add(&healthy);
+    dr.add(&[dep(20, &[22]), dep(21, &[20]), dep(22, &[21]), dep(23, &[22])]);
+    let results = dr.resolve_per_component();
+    assert_eq!(results.len(), 3);
+    let (ok, err): (Vec<_>, Vec<_>) = results.into_iter().partition(|(_, r)| r.is_ok());
+    assert_eq!(ok.len(), 2);
+    assert_eq!(err.len(), 1);
+    assert_eq!(sorted(err[0].0.clone()), vec![20, 21, 22, 23]);
+    assert_eq!(err[0].1, Err(DepResolveError::IslandsOrCircular));
+
+    let mut ok = ok.into_iter().map(|(ids, r)| (sorted(ids), r.unwrap())).collect::<Vec<_>>();
+    ok.sort_by_key(|(ids, _)| ids[0]);
+    assert_eq!(ok[0].0, vec![0, 1, 2]);
+    assert_eq!(ok[0].1.to_sorted_levels(), vec![vec![0], vec![1], vec![2]]);
+    assert_eq!(ok[1].1.to_sorted_levels(), vec![vec![10], vec![11]]);
+    let merged = ResolvedDeps::merge(ok.into_iter().map(|(_, r)| r));
+    let alone = DepRes::new();
+    alone.add(&healthy);
+    assert_eq!(merged, alone.resolve_nodes(&alone.ids, false).unwrap());
+    assert_eq!(merged.to_sorted_levels(), vec![vec![0, 10], vec![1, 11], vec![2]]);
+
+    // all healthy, merging gives the plain resolve
+    let dr = DepRes::new();
+    dr.add(&random_dag(300, 2, 4));
+    let parts = dr.resolve_per_component().into_iter().map(|(_, r)| r.unwrap());
+    let merged = ResolvedDeps::merge(parts);
+    assert_eq!(merged, dr.resolve_nodes(&dr.ids, false).unwrap());
+    assert_eq!(check_resolution(&dr, &merged), Ok(()));
+}
+
+#[test]
+fn test_freeze() {
+    fn shareable<T: Send + Sync + Clone>(_: &T) {}
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let frozen = dr.freeze();
+    shareable(&frozen);
+    assert_eq!(frozen.resolve(), dr.resolve_nodes(&dr.ids, false));
+    assert_eq!(frozen.node_count(), 6);
+    assert_eq!(frozen.edge_count(), dr.edge_count());
+    assert_eq!(frozen.edges().collect::<HashSet<_>>(), dr.edges().collect::<HashSet<_>>());
+    assert_eq!(frozen.to_dot(), dr.to_dot());
+    assert!(frozen.contains(&5) && !frozen.contains(&9));
+    assert_eq!(frozen.deps_of(&5), vec![4]);
+    assert_eq!(frozen.dependents_of(&3), vec![4]);
+    assert_eq!(frozen.transitive_dependents(&3), hset(&[4, 5]));
+    assert!(frozen.depends_on(&5, &3));
+    assert!(!frozen.depends_on(&3, &5));
+
+    // later changes leave the snapshot alone
+    dr.add(&[dep(6, &[5])]);
+    assert_eq!(frozen.clone().node_count(), 6);
+    assert_eq!(frozen.dependents_of(&5), Vec::<usize>::new());
+
+    let dr = DepRes::new();
+    dr.add(&random_dag(2000, 6, 3));
+    let frozen = dr.freeze();
+    assert_eq!(frozen.resolve(), dr.resolve_nodes(&dr.ids, false));
+    for id in [0, 999, 1999] {
+        assert_eq!(hset(&frozen.deps_of(&id)), hset(&dr.deps_of(&id)));
+        assert_eq!(frozen.transitive_dependents(&id), dr.transitive_dependents(&id));
+    }
+
+    // missing deps, aliases, pins and soft cycles resolve like the live graph
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[9]), dep(1, &[0, 9]), dep(2, &[7]), dep(3, &[])]);
+    dr.add_alias(7, 3).unwrap();
+    dr.add_soft_edge(3, 2);
+    dr.pin_min_level(0, 3).unwrap();
+    let frozen = dr.freeze();
+    assert_eq!(frozen.resolved_deps_of(&2), vec![3]);
+    assert_eq!(frozen.resolve(), dr.resolve_nodes(&dr.ids, false));
+    dr.add(&[dep(9, &[])]);
+    let frozen = dr.freeze();
+    // the hard edge 2 -> 3 keeps that cycle from being broken
+    assert_eq!(frozen.resolve_with_dropped(), dr.resolve_with_dropped());
+    assert_eq!(frozen.resolve_with_dropped().err(), Some(DepResolveError::IslandsOrCircular));
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[9]), dep(2, &[]), dep(3, &[]), dep(9, &[])]);
+    dr.add_soft_edge(2, 7);
+    dr.add_alias(7, 3).unwrap();
+    dr.add_soft_edge(3, 2);
+    let frozen = dr.freeze();
+    let results = [frozen.resolve_with_dropped(), dr.resolve_with_dropped()];
+    for (r, dropped) in results.map(Result::unwrap) {
+        assert!(dropped == [(3, 2)] || dropped == [(2, 3)], "{dropped:?}");
+        assert_eq!(r.len(), 4);
+    }
+}
+
+#[test]
+fn test_freeze_queries_through_aliases() {
+    let s = |id: &str| id.to_string();
+    let dr = DepRes::new();
+    dr.add(&[Dep::with_deps(s("app"), [s("log")]), Dep::new(s("impl"))]);
+    dr.add_alias(s("log"), s("impl")).unwrap();
+    let frozen = dr.freeze();
+    assert_eq!(frozen.dependents_of("impl"), vec![s("app")]);
+    assert_eq!(frozen.dependents_of("log"), vec![s("app")]);
+    assert_eq!(frozen.transitive_dependents("impl"), HashSet::from([s("app")]));
+    assert!(frozen.depends_on("app", "impl"));
+    assert!(!frozen.depends_on("app", "log"));
+    assert_eq!(frozen.transitive_deps("app").unwrap(), HashSet::from([s("impl")]));
+    assert_eq!(frozen.path_between("app", "impl"), Some(vec![s("app"), s("impl")]));
+    assert_eq!(frozen.path_between("app", "log"), None);
+
+    // frozen answers match the live ones on aliases, groups, missing deps and cycles
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[20]),
+        dep(1, &[0, 9]),
+        dep(2, &[21]),
+        dep(3, &[1]),
+        dep(4, &[]),
+        dep(5, &[5]),
+        dep(6, &[7]),
+        dep(7, &[6]),
+    ]);
+    dr.add_alias(20, 4).unwrap();
+    dr.define_group(21, [20, 3]).unwrap();
+    let frozen = dr.freeze();
+    for a in 0..22 {
+        assert_eq!(hset(&frozen.dependents_of(&a)), hset(&dr.dependents_of(&a)), "{a}");
+        assert_eq!(frozen.transitive_dependents(&a), dr.transitive_dependents(&a), "{a}");
+        assert_eq!(frozen.transitive_deps(&a), dr.transitive_deps(&a), "{a}");
+        for b in 0..22 {
+            assert_eq!(frozen.depends_on(&a, &b), dr.depends_on(&a, &b), "{a} {b}");
+            assert_eq!(frozen.path_between(&a, &b), dr.path_between(&a, &b), "{a} {b}");
+        }
+    }
+    assert_eq!(frozen.path_between(&5, &5), Some(vec![5, 5]));
+    assert_eq!(frozen.path_between(&2, &0), Some(vec![2, 3, 1, 0]));
+}
+
+#[test]
+fn test_freeze_concurrent_add() {
+    let n = 3000;
+    let items = (0..n)
+        .map(|i: usize| dep(i, &[i.wrapping_sub(1), i.wrapping_sub(2)][..i.min(2)]))
+        .collect::<Vec<_>>();
+    let dr = DepRes::new();
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for chunk in items.chunks(50) {
+                dr.add(&chunk.iter().collect::<Vec<_>>());
+            }
+        });
+        for _ in 0..20 {
+            let frozen = dr.freeze();
+            for id in frozen.ids() {
+                assert_eq!(sorted(frozen.deps_of(&id)), sorted(items[id].deps.clone()));
+            }
+        }
+    });
+    let frozen = dr.freeze();
+    assert_eq!(frozen.node_count(), n);
+    assert_eq!(frozen.resolve(), dr.resolve_nodes(&dr.ids, false));
+}
+
+#[test]
+fn test_normalizer() {
+    let s = |id: &str| id.to_string();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counted = calls.clone();
+    let dr = DepRes::with_normalizer(move |id: &String| {
+        counted.fetch_add(1, Ordering::Relaxed);
+        id.to_lowercase().replace('-', "_")
+    });
+    let items = vec![
+        Dep::new(s("Foo-Bar")),
+        Dep::with_deps(s("baz"), [s("foo_bar")]),
+        Dep::with_deps(s("Qux"), [s("FOO-BAR"), s("Baz")]),
+        Dep::new(s("foo_bar")),
+    ];
+    dr.add(&items);
+    dr.add(&items);
+    // once per distinct raw id, however often it shows up
+    assert_eq!(calls.load(Ordering::Relaxed), 6);
+    let mut ids = dr.ids().collect::<Vec<_>>();
+    ids.sort();
+    assert_eq!(ids, vec![s("baz"), s("foo_bar"), s("qux")]);
+    assert_eq!(dr.edge_count(), 3);
+
+    let resolved = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(
+        resolved.to_sorted_levels(),
+        vec![vec![s("foo_bar")], vec![s("baz")], vec![s("qux")]]
+    );
+    assert_eq!(resolved.level_of(&s("FOO-BAR")), Some(0));
+    assert_eq!(resolved.is_before(&s("Foo-Bar"), &s("QUX")), Some(true));
+
+    assert!(dr.contains(&s("Foo_Bar")));
+    assert_eq!(dr.normalize(&s("Foo-Bar")), s("foo_bar"));
+    let mut deps = dr.deps_of(&s("QUX"));
+    deps.sort();
+    assert_eq!(deps, vec![s("baz"), s("foo_bar")]);
+    let mut dependents = dr.dependents_of(&s("foo-bar"));
+    dependents.sort();
+    assert_eq!(dependents, vec![s("baz"), s("qux")]);
+    assert!(dr.depends_on(&s("Qux"), &s("FOO-BAR")));
+    assert_eq!(
+        dr.resolve_for_targets(&[s("BAZ")]).unwrap().to_sorted_levels(),
+        vec![vec![s("foo_bar")], vec![s("baz")]]
+    );
+    assert_eq!(dr.subgraph(&[s("Baz")]).node_count(), 2);
+    assert_eq!(dr.freeze().deps_of(&s("Baz")), vec![s("foo_bar")]);
+
+    // aliases and pins go through it as well
+    dr.add_alias(s("Old-Name"), s("BAZ")).unwrap();
+    dr.add(&[Dep::with_deps(s("zed"), [s("old_name")])]);
+    assert_eq!(dr.resolved_deps_of(&s("Zed")), vec![s("baz")]);
+    dr.pin_min_level(s("Foo-Bar"), 1).unwrap();
+    let resolved = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(resolved.level_of(&s("FOO_BAR")), Some(1));
+
+    // a plain edge added later labels the kinds of the canonical edge
+    dr.add_edge_kind(s("qux"), s("zed"), EdgeKind::Build);
+    dr.add(&[Dep::with_deps(s("QUX"), [s("Zed")])]);
+    assert_eq!(
+        dr.edge_kinds(&s("qux"), &s("zed")),
+        vec![EdgeKind::Normal, EdgeKind::Build]
+    );
+
+    // looking up ids that were never added doesn't remember them, each lookup maps anew
+    let before = calls.load(Ordering::Relaxed);
+    assert!(!dr.contains("Nope"));
+    assert!(!dr.contains("Nope"));
+    assert_eq!(resolved.level_of("Nope"), None);
+    assert_eq!(calls.load(Ordering::Relaxed), before + 3);
+}
+
+#[test]
+fn test_try_add_edge() {
+    let dr = DepRes::new();
+    dr.try_add_edge(0, 1).unwrap();
+    dr.try_add_edge(2, 3).unwrap();
+    dr.try_add_edge(3, 0).unwrap();
+    // already there
+    dr.try_add_edge(3, 0).unwrap();
+    assert_eq!(dr.edge_count(), 3);
+    assert_eq!(
+        dr.try_add_edge(1, 2),
+        Err(CycleError {
+            path: vec![1, 2, 3, 0, 1]
+        })
+    );
+    assert_eq!(dr.try_add_edge(1, 1), Err(CycleError { path: vec![1, 1] }));
+    assert_eq!(dr.edge_count(), 3);
+    assert!(!dr.contains(&1));
+
+    // edges against the order get it rearranged
+    let dr = DepRes::new();
+    dr.try_add_edge(0, 1).unwrap();
+    dr.try_add_edge(2, 3).unwrap();
+    dr.try_add_edge(1, 2).unwrap();
+    assert_eq!(dr.try_add_edge(3, 0).unwrap_err().path, vec![3, 0, 1, 2, 3]);
+    dr.add(&[dep(3, &[])]);
+    let resolved = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(check_resolution(&dr, &resolved), Ok(()));
+
+    // the order is rebuilt after unchecked changes
+    dr.add(&[dep(4, &[0])]);
+    assert_eq!(dr.try_add_edge(3, 4).unwrap_err().path, vec![3, 4, 0, 1, 2, 3]);
+    dr.add(&[dep(3, &[2])]);
+    dr.remove(&4);
+    dr.try_add_edge(3, 4).unwrap();
+}
+
+#[test]
+fn test_try_add_edge_matches_search() {
+    let mut state = 7u64;
+    let mut next = move |n: u64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n) as usize
+    };
+    let dr = DepRes::new();
+    dr.add(&(0..60).map(|i| dep(i, &[])).collect::<Vec<_>>());
+    for _ in 0..2000 {
+        let (id, dep) = (next(60), next(60));
+        let closes = id == dep || dr.path_between(&dep, &id).is_some();
+        match dr.try_add_edge(id, dep) {
+            Ok(()) => assert!(!closes),
+            Err(CycleError { path }) => {
+                assert!(closes);
+                assert_eq!((path[0], path[1], *path.last().unwrap()), (id, dep, id));
+                for hop in path[1..].windows(2) {
+                    assert!(dr.deps_of(&hop[0]).contains(&hop[1]));
+                }
+            }
+        }
+    }
+    let resolved = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(check_resolution(&dr, &resolved), Ok(()));
+}
+
+#[test]
+fn test_try_add_edge_chain() {
+    let n = 100_000;
+    let dr = DepRes::new();
+    for i in 1..n {
+        dr.try_add_edge(i, i - 1).unwrap();
+    }
+    // and growing the other way round
+    for i in n..2 * n {
+        dr.try_add_edge(i, i + 1).unwrap();
+    }
+    let err = dr.try_add_edge(0, n - 1).unwrap_err();
+    assert_eq!(err.path.len(), n + 1);
+    assert_eq!(err.path[..3], [0, n - 1, n - 2]);
+    assert_eq!(dr.edge_count(), 2 * n - 1);
+}
+
+#[test]
+fn test_walk() {
+    // 0 -> {1, 2}, 1 -> 3, 2 -> 4
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[1, 2]), dep(1, &[3]), dep(2, &[4]), dep(3, &[]), dep(4, &[])]);
+    let walked = |order| {
+        let mut seen = vec![];
+        dr.walk(&0, order, |&id, depth| {
+            seen.push((id, depth));
+            WalkControl::Continue
+        });
+        seen
+    };
+    let dfs = walked(Walk::Dfs);
+    assert!(
+        dfs == [(0, 0), (1, 1), (3, 2), (2, 1), (4, 2)]
+            || dfs == [(0, 0), (2, 1), (4, 2), (1, 1), (3, 2)],
+        "{dfs:?}"
+    );
+    let bfs = walked(Walk::Bfs);
+    assert_eq!(bfs[0], (0, 0));
+    assert_eq!(hset(&[bfs[1].0, bfs[2].0]), hset(&[1, 2]));
+    assert_eq!(hset(&[bfs[3].0, bfs[4].0]), hset(&[3, 4]));
+    assert_eq!(bfs.iter().map(|&(_, d)| d).collect::<Vec<_>>(), vec![0, 1, 1, 2, 2]);
+
+    // pruning and stopping
+    let mut seen = vec![];
+    dr.walk(&0, Walk::Bfs, |&id, _| {
+        seen.push(id);
+        match id {
+            1 => WalkControl::SkipSubtree,
+            _ => WalkControl::Continue,
+        }
+    });
+    assert_eq!(sorted(seen), vec![0, 1, 2, 4]);
+    let mut seen = vec![];
+    dr.walk(&0, Walk::Dfs, |&id, _| {
+        seen.push(id);
+        match id {
+            0 => WalkControl::Continue,
+            _ => WalkControl::Stop,
+        }
+    });
+    assert_eq!(seen.len(), 2);
+
+    // upwards, and depth limits
+    let mut seen = vec![];
+    dr.walk_dependents(&4, Walk::Dfs, |&id, depth| {
+        seen.push((id, depth));
+        WalkControl::Continue
+    });
+    assert_eq!(seen, vec![(4, 0), (2, 1), (0, 2)]);
+    let mut shallow = vec![];
+    dr.walk(&0, Walk::Bfs, |&id, depth| {
+        shallow.push(id);
+        match depth {
+            1 => WalkControl::SkipSubtree,
+            _ => WalkControl::Continue,
+        }
+    });
+    assert_eq!(sorted(shallow), vec![0, 1, 2]);
+
+    // every id once, even around cycles and on diamonds
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[1, 2]), dep(1, &[3]), dep(2, &[3]), dep(3, &[0])]);
+    for order in [Walk::Dfs, Walk::Bfs] {
+        let mut seen = vec![];
+        dr.walk(&0, order, |&id, _| {
+            seen.push(id);
+            WalkControl::Continue
+        });
+        assert_eq!(sorted(seen), vec![0, 1, 2, 3]);
+    }
+
+    // both directions see through aliases, the alias itself is never visited
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[7]), dep(1, &[]), dep(2, &[1])]);
+    dr.add_alias(7, 2).unwrap();
+    let mut down = vec![];
+    dr.walk(&0, Walk::Dfs, |&id, depth| {
+        down.push((id, depth));
+        WalkControl::Continue
+    });
+    assert_eq!(down, vec![(0, 0), (2, 1), (1, 2)]);
+    let mut up = vec![];
+    dr.walk_dependents(&1, Walk::Bfs, |&id, depth| {
+        up.push((id, depth));
+        WalkControl::Continue
+    });
+    assert_eq!(up, vec![(1, 0), (2, 1), (0, 2)]);
+}
+
+#[test]
+fn test_from_fn() {
+    let items = fixture();
+    let dr = DepRes::from_fn(0..6, |&id| items[id].deps.clone());
+    assert_eq!(dr.node_count(), 6);
+    let expected = DepRes::new();
+    expected.add(&items);
+    assert_eq!(dr, expected);
+    assert_eq!(
+        dr.resolve_nodes(&dr.ids, false).unwrap().to_sorted_levels(),
+        vec![vec![0, 2, 3], vec![1, 4], vec![5]]
+    );
+
+    // deps outside the ids are missing deps
+    let dr = DepRes::from_fn(0..2, |&id| vec![id + 1]);
+    assert!(dr.contains(&1) && !dr.contains(&2));
+    assert_eq!(
+        dr.resolve_with_report().unwrap_err().problems,
+        vec![ResolveProblem::MissingDep { id: 1, dep: 2 }]
+    );
+
+    let dr = DepRes::try_from_fn(0..6, |&id| Ok::<_, ()>(items[id].deps.clone())).unwrap();
+    assert_eq!(dr, expected);
+    let err = DepRes::try_from_fn(0..6, |&id| match id % 2 {
+        0 => Ok(items[id].deps.clone()),
+        _ => Err(format!("no entry for {id}")),
+    })
+    .unwrap_err();
+    let mut failures = err.failures;
+    failures.sort();
+    assert_eq!(
+        failures,
+        [1, 3, 5].map(|id| (id, format!("no entry for {id}")))
+    );
+}
+
+#[test]
+fn test_resolve_excluding() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let checked = |dr: &DepRes<usize>, excluded: &ExcludedResolution<usize>| {
+        let rest = dr.clone();
+        rest.retain(|id| !excluded.skipped.contains_key(id));
+        assert_eq!(check_resolution(&rest, &excluded.resolved), Ok(()));
+    };
+
+    let leaf = dr.resolve_excluding(&HashSet::from([5])).unwrap();
+    assert_eq!(leaf.skipped, HashMap::from([(5, 5)]));
+    assert_eq!(leaf.resolved.to_sorted_levels(), vec![vec![0, 2, 3], vec![1, 4]]);
+    checked(&dr, &leaf);
+
+    let deep = dr.resolve_excluding(&HashSet::from([3, 0])).unwrap();
+    assert_eq!(
+        deep.skipped,
+        HashMap::from([(0, 0), (1, 0), (3, 3), (4, 3), (5, 3)])
+    );
+    assert_eq!(deep.resolved.to_sorted_levels(), vec![vec![2]]);
+    checked(&dr, &deep);
+
+    let nothing = dr.resolve_excluding(&HashSet::new()).unwrap();
+    assert!(nothing.skipped.is_empty());
+    assert_eq!(nothing.resolved, dr.resolve_nodes(&dr.ids, false).unwrap());
+
+    assert_eq!(
+        dr.resolve_excluding(&HashSet::from([3, 9])).unwrap_err(),
+        DepResolveError::UnknownTargets(vec![9])
+    );
+
+    // a cone over a bigger graph, through an alias, nearest cause wins
+    let dr = DepRes::new();
+    dr.add(&random_dag(400, 3, 5));
+    dr.add(&[dep(1000, &[]), dep(1001, &[999]), dep(1002, &[1001, 1000])]);
+    dr.add_alias(999, 1000).unwrap();
+    let excluded = dr.resolve_excluding(&HashSet::from([7, 1000])).unwrap();
+    assert_eq!(excluded.skipped[&1002], 1000);
+    assert_eq!(excluded.skipped[&1001], 1000);
+    for id in dr.transitive_dependents(&7) {
+        assert!(excluded.skipped.contains_key(&id));
+    }
+    checked(&dr, &excluded);
+}
+
+#[test]
+fn test_rebalance() {
+    let widths = |r: &ResolvedDeps<usize>| {
+        r.iter_level().map(|l| l.deps.len()).collect::<Vec<_>>()
+    };
+    let never_earlier = |before: &ResolvedDeps<usize>, after: &ResolvedDeps<usize>| {
+        for id in before.sorted_by_level() {
+            assert!(after.level_of(&id).unwrap() >= before.level_of(&id).unwrap());
+        }
+    };
+
+    // 20 roots with slack next to a chain of 4
+    let dr = DepRes::new();
+    dr.add(&(0..20).map(|i| dep(i, &[])).collect::<Vec<_>>());
+    dr.add(&[dep(100, &[]), dep(101, &[100]), dep(102, &[101]), dep(103, &[102])]);
+    dr.add(&[dep(200, &[0])]);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(widths(&r), vec![21, 2, 1, 1]);
+    let balanced = r.rebalance(&dr, 7);
+    assert_eq!(check_resolution(&dr, &balanced), Ok(()));
+    assert_eq!(balanced.iter_level().count(), 4);
+    assert!(widths(&balanced).iter().all(|&w| w <= 7));
+    never_earlier(&r, &balanced);
+    // the chain has no slack
+    for (i, id) in (100..104).enumerate() {
+        assert_eq!(balanced.level_of(&id), Some(i));
+    }
+
+    // a bound wide enough changes nothing
+    assert_eq!(r.rebalance(&dr, 21), r);
+
+    // too many roots for the levels there are, more levels instead
+    let dr = DepRes::new();
+    dr.add(&(0..12).map(|i| dep(i, &[])).collect::<Vec<_>>());
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    let balanced = r.rebalance(&dr, 5);
+    assert_eq!(check_resolution(&dr, &balanced), Ok(()));
+    assert_eq!(widths(&balanced), vec![5, 5, 2]);
+    assert_eq!(widths(&r.rebalance(&dr, 0)), vec![1; 12]);
+
+    let dr = DepRes::new();
+    dr.add(&random_dag(2000, 3, 9));
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    let width = 2000 / r.iter_level().count() + 1;
+    let balanced = r.rebalance(&dr, width);
+    assert_eq!(check_resolution(&dr, &balanced), Ok(()));
+    assert!(widths(&balanced).iter().all(|&w| w <= width));
+    never_earlier(&r, &balanced);
+    assert!(balanced.iter_level().count() >= r.iter_level().count());
+
+    // the empty levels in front of a pinned node stay
+    let dr = DepRes::new();
+    dr.add(&[dep(0, &[]), dep(1, &[]), dep(2, &[])]);
+    dr.pin_min_level(2, 3).unwrap();
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    let balanced = r.rebalance(&dr, 1);
+    assert_eq!(check_resolution(&dr, &balanced), Ok(()));
+    assert_eq!(widths(&balanced), vec![1, 1, 0, 1]);
+    assert_eq!(balanced.level_of(&2), Some(3));
+
+    let empty = ResolvedDeps::default();
+    assert_eq!(empty.rebalance(&DepRes::<usize>::new(), 3), empty);
+}
+
+#[test]
+fn test_explain() {
+    // 4 is reached through 1 directly and through 2 -> 3, only the longer branch explains it
+    let dr = DepRes::new();
+    dr.add(&[
+        dep(0, &[]),
+        dep(1, &[0]),
+        dep(2, &[0]),
+        dep(3, &[2]),
+        dep(4, &[1, 3]),
+        dep(5, &[]),
+    ]);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    let explained = r.explain(&dr, &4).unwrap();
+    assert_eq!(explained.level, 3);
+    assert_eq!(explained.chain, vec![(0, 0), (2, 1), (3, 2), (4, 3)]);
+    assert_eq!(explained.to_string(), "0(0) -> 2(1) -> 3(2) -> 4(3)");
+    for hop in explained.chain.windows(2) {
+        assert!(dr.deps_of(&hop[1].0).contains(&hop[0].0));
+    }
+
+    // ties go to the smallest id
+    dr.add(&[dep(6, &[0]), dep(7, &[6, 1])]);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.explain(&dr, &7).unwrap().chain, vec![(0, 0), (1, 1), (7, 2)]);
+
+    // level 0 explains itself
+    let explained = r.explain(&dr, &5).unwrap();
+    assert_eq!(explained, Explanation { level: 0, chain: vec![(5, 0)] });
+    assert_eq!(explained.to_string(), "5(0)");
+    assert_eq!(r.explain(&dr, &99), None);
+}
+
+#[test]
+fn test_parallel_threshold() {
+    let items = random_dag(5000, 4, 17);
+    let build = |threshold| {
+        let dr = DepRes::new();
+        dr.set_parallel_threshold(threshold);
+        dr.add(&items);
+        dr
+    };
+    let (par, seq) = (build(0), build(usize::MAX));
+    assert_eq!(seq.parallel_threshold(), usize::MAX);
+    assert_eq!(par, seq);
+    let expected = par.resolve_nodes(&par.ids, false).unwrap();
+    assert_eq!(seq.resolve_nodes(&seq.ids, false).unwrap(), expected);
+    assert_eq!(check_resolution(&seq, &expected), Ok(()));
+
+    // switching the path of one graph back and forth changes nothing either
+    let dr = build(PARALLEL_THRESHOLD);
+    for threshold in [0, 100, usize::MAX] {
+        dr.set_parallel_threshold(threshold);
+        assert_eq!(dr.resolve_nodes(&dr.ids, false).unwrap(), expected);
+    }
+    assert_eq!(dr.clone().parallel_threshold(), usize::MAX);
+    assert_eq!(DepRes::<usize>::new().parallel_threshold(), PARALLEL_THRESHOLD);
+}
+
+#[test]
+fn test_level_slices() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve_deterministic().unwrap();
+    let ids = |ids: &[Arc<usize>]| ids.iter().map(|id| **id).collect::<Vec<_>>();
+    assert_eq!(ids(&r[0]), vec![0, 2, 3]);
+    assert_eq!(r.get_level_shared(2).map(ids), Some(vec![5]));
+    assert_eq!(r.get_level_shared(3), None);
+    let levels = r.iter_level_shared().map(|l| (l.level, ids(l.deps)));
+    assert_eq!(
+        levels.collect::<Vec<_>>(),
+        vec![(0, vec![0, 2, 3]), (1, vec![1, 4]), (2, vec![5])]
+    );
+    // the slices hand out what the graph stores
+    assert!(Arc::ptr_eq(&r[1][0], &dr.intern(&1)));
+
+    let gapped = r.filter(|&id| id != 1 && id != 4, false);
+    assert_eq!(ids(&gapped[2]), vec![5]);
+    assert_eq!(gapped.get_level_shared(1), None);
+}
+
+#[test]
+#[should_panic(expected = "level 3 is out of range")]
+fn test_level_index_out_of_range() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let _ = &dr.resolve().unwrap()[3];
+}
+
+#[test]
+fn test_borrowed_queries() {
+    let s = |id: &str| id.to_string();
+    let mut dr = DepRes::new();
+    dr.add(&[
+        Dep::new(s("core")),
+        Dep::with_deps(s("serde"), [s("core")]),
+        Dep::with_deps(s("app"), [s("serde"), s("missing")]),
+    ]);
+    assert!(dr.contains("serde"));
+    assert!(!dr.contains("missing"));
+    assert!(dr.contains(&s("app")));
+    assert_eq!(dr.deps_of("serde"), vec![s("core")]);
+    assert_eq!(dr.resolved_deps_of("serde"), vec![s("core")]);
+    assert_eq!(dr.dependents_of("core"), vec![s("serde")]);
+    assert_eq!(dr.dependents_of("missing"), vec![s("app")]);
+    assert_eq!(dr.transitive_dependents("core"), HashSet::from([s("serde"), s("app")]));
+    assert_eq!(dr.transitive_deps("serde"), Ok(HashSet::from([s("core")])));
+    assert!(dr.depends_on("app", "core"));
+    assert!(!dr.depends_on("core", "app"));
+    assert!(!dr.depends_on("app", "unknown"));
+    assert!(dr.deps_of("unknown").is_empty());
+    assert_eq!(dr.transitive_deps("unknown"), Ok(HashSet::new()));
+
+    dr.add(&[Dep::new(s("missing"))]);
+    let r = dr.resolve().unwrap();
+    assert_eq!(r.level_of("app"), Some(2));
+    assert!(r.contains("core"));
+    assert!(!r.contains("unknown"));
+
+    // borrowed ids go through the normalizer too
+    let dr = DepRes::with_normalizer(|id: &String| id.to_lowercase());
+    dr.add(&[Dep::new(s("Core")), Dep::with_deps(s("serde"), [s("CORE")])]);
+    assert!(dr.contains("core") && dr.contains("CoRe"));
+    assert_eq!(dr.deps_of("SERDE"), vec![s("core")]);
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert_eq!(r.level_of("Serde"), Some(1));
+
+    // plain ids need no annotations
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve_nodes(&dr.ids, false).unwrap();
+    assert!(dr.contains(&1) && !dr.contains(&9));
+    assert!(dr.depends_on(&5, &3));
+    assert_eq!(r.level_of(&5), Some(2));
+    assert!(r.contains(&0));
+}
+
+#[test]
+fn test_csv() {
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let r = dr.resolve().unwrap();
+    let mut out = vec![];
+    r.to_csv(&mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text, "level,id\n0,0\n0,2\n0,3\n1,1\n1,4\n2,5\n");
+    let back = ResolvedDeps::<usize>::from_csv(text.as_bytes()).unwrap();
+    assert_eq!(back, r);
+    assert_eq!(check_resolution(&dr, &back), Ok(()));
+
+    // quotes, commas and line breaks survive
+    let nasty = "a,\"b\"\nc".to_string();
+    let r = ResolvedDeps::<String>::from_levels(vec![
+        vec![nasty.clone(), "plain".into()],
+        vec!["".into(), "x\r\ny".into()],
+    ]);
+    let mut out = vec![];
+    r.to_csv(&mut out).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(
+        text,
+        "level,id\n0,\"a,\"\"b\"\"\nc\"\n0,plain\n1,\n1,\"x\r\ny\"\n"
+    );
+    let back = ResolvedDeps::<String>::from_csv(text.as_bytes()).unwrap();
+    assert_eq!(back, r);
+    assert_eq!(back.level_of(&nasty), Some(0));
+
+    // no header, any row order, CRLF rows and blank lines
+    let back = ResolvedDeps::<usize>::from_csv("1,7\r\n\r\n0,3\r\n0,9".as_bytes()).unwrap();
+    assert_eq!(back.to_levels(), vec![vec![3, 9], vec![7]]);
+    assert!(ResolvedDeps::<usize>::from_csv("".as_bytes()).unwrap().is_empty());
+}
+
+#[test]
+fn test_csv_malformed() {
+    let parse = |text: &str| ResolvedDeps::<usize>::from_csv(text.as_bytes()).unwrap_err();
+    let err = parse("level,id\n0,1\n2,3\n");
+    assert!(matches!(err, CsvError::LevelGap(1)), "{err:?}");
+    let err = parse("0,1\n0,2\n1,1\n");
+    assert!(matches!(err, CsvError::Duplicate { id: 1, line: 3, first: 1 }), "{err:?}");
+    assert_eq!(err.to_string(), "line 3: 1 is listed again, first on line 1");
+    let err = parse("0,1\n-1,2\n");
+    assert!(matches!(err, CsvError::Level { line: 2, .. }), "{err:?}");
+    assert_eq!(err.to_string(), "line 2: level \"-1\" is no non-negative integer");
+    let err = parse("0,1\n0,x\n");
+    assert!(matches!(err, CsvError::Id { line: 2, .. }), "{err:?}");
+    let err = parse("0,1,2\n");
+    assert_eq!(err.to_string(), "line 1: expected 2 fields, found 3");
+    let err = parse("0,1\n0\n");
+    assert_eq!(err.to_string(), "line 2: expected 2 fields, found 1");
+
+    let parse = |text: &str| ResolvedDeps::<String>::from_csv(text.as_bytes()).unwrap_err();
+    let err = parse("0,a\n0,\"b\nc\n");
+    assert_eq!(err.to_string(), "line 2: a quoted field is never closed");
+    let err = parse("0,\"b\"c\n");
+    assert_eq!(err.to_string(), "line 1: text after the closing quote of a field");
+    let err = parse("0,b\"c\n");
+    assert_eq!(err.to_string(), "line 1: a quote inside an unquoted field");
+    // lines after a quoted line break still count right
+    let err = parse("0,\"a\nb\"\n0,\"a\nb\"\n");
+    assert_eq!(err.to_string(), "line 3: \"a\\nb\" is listed again, first on line 1");
+}
+
+#[test]
+fn test_resolve_streaming() {
+    use std::ops::ControlFlow;
+    let mut dr = DepRes::new();
+    dr.add(&fixture());
+    let mut streamed = vec![];
+    let mut seen = vec![];
+    dr.resolve_streaming(|lv, ids| {
+        seen.push(lv);
+        streamed.extend(ids);
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(seen, [0, 1, 2]);
+    assert_eq!(streamed, dr.resolve().unwrap().sorted_by_level());
+
+    let mut calls = 0;
+    let r = dr.resolve_streaming(|_, _| {
+        calls += 1;
+        ControlFlow::Break(())
+    });
+    assert!(r.is_ok());
+    assert_eq!(calls, 1);
+
+    // the levels before a cycle are out by the time it is reported
+    let dr = DepRes::new();
+    dr.add(&vec![dep(0, &[]), dep(1, &[0]), dep(2, &[1, 3]), dep(3, &[2])]);
+    let mut streamed = vec![];
+    let r = dr.resolve_streaming(|_, ids| {
+        streamed.push(ids);
+        ControlFlow::Continue(())
+    });
+    assert!(matches!(r, Err(DepResolveError::IslandsOrCircular)));
+    assert_eq!(streamed, [vec![0], vec![1]]);
+}
+
+#[test]
+fn test_resolve_streaming_keeps_no_levels() {
+    use std::ops::ControlFlow;
+    let n = 2000;
+    let ids = (0..n).map(Arc::new).collect::<Vec<_>>();
+    let items = (0..n)
+        .map(|i| Dep {
+            id: ids[i].clone(),
+            deps: i.checked_sub(1).map(|d| ids[d].clone()).into_iter().collect(),
+        })
+        .collect::<Vec<_>>();
+    let dr = DepRes::new();
+    dr.add(&items);
+    drop(items);
+    let before = ids.iter().map(Arc::strong_count).collect::<Vec<_>>();
+    let mut levels = 0;
+    dr.resolve_streaming(|lv, level| {
+        assert_eq!(level, [ids[lv].clone()]);
+        // the previous level went to us and nowhere else
+        if lv > 0 {
+            assert_eq!(Arc::strong_count(&ids[lv - 1]), before[lv - 1]);
+        }
+        levels += 1;
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(levels, n);
+    assert!(ids.iter().map(Arc::strong_count).eq(before));
+}
+
+#[test]
+fn test_map_ids() {
+    let mut items = random_dag(300, 4, 21);
+    items.push(dep(1002, &[1001]));
+    let dr = DepRes::new();
+    dr.add(&items);
+    dr.add_soft_edge(250, 7);
+    dr.add_edge_kind(9, 3, EdgeKind::Build);
+    dr.add_alias(1000, 5).unwrap();
+    dr.define_group(1001, [1000, 6]).unwrap();
+    dr.pin_min_level(12, 7).unwrap();
+
+    let name = |id: &usize| format!("n{id}");
+    let mapped = dr.map_ids(name);
+    let expected = DepRes::<String>::new();
+    expected.add(
+        &(items.iter())
+            .map(|item| Dep {
+                id: name(&item.id),
+                deps: item.deps.iter().map(name).collect(),
+            })
+            .collect::<Vec<_>>(),
+    );
+    expected.add_soft_edge(name(&250), name(&7));
+    expected.add_edge_kind(name(&9), name(&3), EdgeKind::Build);
+    expected.add_alias(name(&1000), name(&5)).unwrap();
+    expected.define_group(name(&1001), [name(&1000), name(&6)]).unwrap();
+    expected.pin_min_level(name(&12), 7).unwrap();
+    assert!(mapped == expected);
+    assert_eq!(mapped.edge_kinds(&name(&9), &name(&3)), [EdgeKind::Build]);
+
+    // same levels under the other names
+    let (r, mapped_r) = (dr.resolve_cached().unwrap(), mapped.resolve_cached().unwrap());
+    assert_eq!(mapped_r.len(), r.len());
+    for id in dr.ids() {
+        assert_eq!(mapped_r.level_of(&name(&id)), r.level_of(&id), "{id}");
+    }
+    assert_eq!(dr.try_map_ids(|id| Ok::<_, ()>(name(id))).unwrap(), mapped);
+}
+
+#[test]
+fn test_map_ids_collisions() {
+    let dr = DepRes::new();
+    dr.add(&fixture());
+    let err = dr.try_map_ids(|&id| Ok::<_, ()>(id / 2)).unwrap_err();
+    let MapIdsError::Collision { first, second, mapped } = err else {
+        panic!("{err:?}");
+    };
+    assert_eq!((first / 2, second / 2, first != second), (mapped, mapped, true));
+
+    let err = (dr.try_map_ids(|&id| if id == 3 { Err("three") } else { Ok(id) })).unwrap_err();
+    assert!(matches!(err, MapIdsError::Map { id: 3, .. }), "{err:?}");
+    assert_eq!(err.to_string(), "mapping 3 failed: three");
+
+    // 0 and 1, 2 and 3, 4 and 5 merge, the deps inside each pair go away
+    dr.pin_min_level(4, 3).unwrap();
+    dr.pin_min_level(5, 1).unwrap();
+    dr.add_alias(10, 0).unwrap();
+    dr.add_alias(11, 2).unwrap();
+    let merged = dr.map_ids(|&id| id / 2);
+    assert_eq!(sorted(merged.ids().collect()), [0, 1, 2]);
+    assert_eq!(merged.edges().collect::<Vec<_>>(), [(2, 1)]);
+    assert_eq!(sorted(merged.group_members(&5)), [0, 1]);
+    // 2 takes the higher pin of 4 and 5
+    let levels = merged.resolve_nodes(&merged.ids, false).unwrap().to_sorted_levels();
+    assert_eq!(levels, [vec![0, 1], vec![], vec![], vec![2]]);
+}
+
+#[test]
+fn test_store() {
+    let map = Map::<Arc<usize>, Vec<usize>, RandomState>::with_hasher(RandomState::new());
+    for id in [1, 2, 1] {
+        map.upsert(Arc::new(id), vec![id], |known, new| known.extend(new));
+    }
+    assert_eq!(map.get_cloned(&1), Some(vec![1, 1]));
+    let first = map.key_of(&2).unwrap();
+    let stored = map.get_or_insert_with(Arc::new(2), Vec::new, |key, _| key.clone());
+    assert!(Arc::ptr_eq(&first, &stored));
+    assert_eq!(map.modify(&3, |_, deps| deps.push(3)), None);
+    map.modify(&2, |_, deps| deps.push(3));
+    let hash = map.hasher().hash_one(2usize);
+    assert_eq!(map.read_hashed(hash, |key| **key == 2, |_, deps| deps.len()), Some(2));
+    assert!(map.all(|key, deps| deps.contains(key)));
+    assert!(map.remove_if(&1, |_, deps| deps.len() > 2).is_none());
+    assert!(map.remove_if(&1, |_, deps| deps.len() == 2).is_some());
+    assert_eq!(map.keys(), [Arc::new(2)]);
+
+    let set = Set::<usize, RandomState>::with_hasher(RandomState::new());
+    set.insert_all(0..100);
+    assert!(!set.insert(7));
+    set.retain(|id| id % 2 == 0);
+    assert_eq!((set.len(), set.get_key(&8), set.contains(&7)), (50, Some(8), false));
+    let sum = AtomicUsize::new(0);
+    set.par_for_each(|id| {
+        sum.fetch_add(*id, Ordering::Relaxed);
+    });
+    assert_eq!(sum.into_inner(), (0..100).step_by(2).sum::<usize>());
+    assert_eq!(sorted(set.into_vec()), (0..100).step_by(2).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_level_set() {
+    let mut dr = DepRes::<String>::new();
+    dr.add_iter([Dep::new("a".to_string()), Dep::with_deps("b".into(), ["a".into()])]);
+    let r = dr.resolve().unwrap();
+    let level = r.level(1).deps;
+    assert!(level.contains("b") && !level.contains("a"));
+    assert_eq!((level.len(), level.is_empty()), (1, false));
+    assert_eq!((&*level).into_iter().collect::<Vec<_>>(), ["b"]);
+    assert_eq!(*level, *dr.resolve().unwrap().level(1).deps);
+    assert_ne!(*level, *r.level(0).deps);
+}
+
+#[test]
+fn test_alias_cycle_behind_node() {
+    // x shadows its alias to y, the alias back from y would close a cycle once x goes
+    let dr = DepRes::<String>::new();
+    dr.add_iter([Dep::new("x".to_string()), Dep::with_deps("app".into(), ["y".into()])]);
+    dr.add_alias("x".into(), "y".into()).unwrap();
+    assert_eq!(
+        dr.add_alias("y".into(), "x".into()),
+        Err(DepResolveError::AliasCycle("y".into()))
+    );
+    assert_eq!(
+        dr.define_group("y".into(), ["x".into()]),
+        Err(DepResolveError::GroupCycle("y".into()))
+    );
+
+    // one that got in anyway stands for nothing instead of recursing forever
+    let (x, y) = (dr.intern(&"x".into()), dr.intern(&"y".into()));
+    dr.aliases.insert(y, x);
+    dr.remove(&"x".into());
+    assert!(dr.resolved_deps_of("app").is_empty());
+    assert_eq!(
+        dr.resolve_deterministic().unwrap_err(),
+        DepResolveError::IslandsOrCircular
+    );
+}
+
+#[cfg(not(feature = "dashmap"))]
+#[test]
+fn test_locked_par_for_each_reads_in_place() {
+    static CLONES: AtomicUsize = AtomicUsize::new(0);
+    struct Counted(usize);
+    impl Clone for Counted {
+        fn clone(&self) -> Self {
+            CLONES.fetch_add(1, Ordering::Relaxed);
+            Counted(self.0)
+        }
+    }
+
+    // only the keys are copied, and reading the map again from inside is fine
+    let map = Map::<usize, Counted, RandomState>::with_hasher(RandomState::new());
+    for id in 0..100 {
+        map.insert(id, Counted(id));
+    }
+    let seen = Map::<usize, usize, RandomState>::with_hasher(RandomState::new());
+    map.par_for_each(|id, value| {
+        assert!(map.contains_key(id));
+        seen.insert(*id, value.0);
+    });
+    assert_eq!(seen.len(), 100);
+    assert_eq!(CLONES.load(Ordering::Relaxed), 0);
+
+    // a set hands out its copied keys with no lock held, so writing back is fine
+    let set = Set::<usize, RandomState>::with_hasher(RandomState::new());
+    set.insert_all(0..100);
+    set.par_for_each(|id| {
+        set.insert(id + 100);
+    });
+    assert_eq!(set.len(), 200);
 }
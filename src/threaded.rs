@@ -0,0 +1,120 @@
+use crate::*;
+use std::{
+    any::Any,
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Mutex},
+};
+
+/// Why a node failed in [`DepRes::execute_threaded`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeFailure<E> {
+    Err(E),
+    /// `f` panicked, with the panic message if it was a string
+    Panic(String),
+}
+
+/// Failure of [`DepRes::execute_threaded`]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ExecuteError<Id, E> {
+    #[error(transparent)]
+    Resolve(#[from] DepResolveError<Id>),
+    #[error("{} node(s) failed, {} skipped", .failures.len(), .skipped.len())]
+    Failed {
+        /// Every failed id, in the order the failures came in
+        failures: Vec<(Id, NodeFailure<E>)>,
+        /// Ids that never ran because a dep failed, in no particular order
+        skipped: Vec<Id>,
+    },
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Runs `f` on every id on `num_threads` plain threads, each id as soon as all its deps succeeded
+    ///
+    /// Ready ids go to the workers over a channel and results come back over another one, so
+    /// this needs neither rayon nor an async runtime. A failing or panicking id only stops its
+    /// dependents, everything else still runs and all failures are reported together.
+    ///
+    /// Panics if `num_threads` is 0
+    pub fn execute_threaded<E: Send>(
+        &self,
+        num_threads: usize,
+        f: impl Fn(&Id) -> Result<(), E> + Sync,
+    ) -> Result<(), ExecuteError<Id, E>> {
+        self.execute_plan(self.execution_plan()?, num_threads, f)
+    }
+
+    /// Like [`execute_threaded`](Self::execute_threaded), handing out ready ids in the order of
+    /// [`execution_plan_with`](Self::execution_plan_with)
+    pub fn execute_threaded_with<K: Ord, E: Send>(
+        &self,
+        num_threads: usize,
+        key: impl Fn(&Id) -> K,
+        f: impl Fn(&Id) -> Result<(), E> + Sync,
+    ) -> Result<(), ExecuteError<Id, E>> {
+        self.execute_plan(self.execution_plan_with(key)?, num_threads, f)
+    }
+
+    fn execute_plan<E: Send>(
+        &self,
+        plan: ExecutionPlan<Id>,
+        num_threads: usize,
+        f: impl Fn(&Id) -> Result<(), E> + Sync,
+    ) -> Result<(), ExecuteError<Id, E>> {
+        assert!(num_threads > 0, "num_threads must be at least 1");
+        let (job_tx, job_rx) = mpsc::channel::<Id>();
+        let job_rx = Mutex::new(job_rx);
+        let (done_tx, done_rx) = mpsc::channel();
+        let mut failures = vec![];
+        std::thread::scope(|scope| {
+            for _ in 0..num_threads {
+                let (job_rx, done_tx, f) = (&job_rx, done_tx.clone(), &f);
+                scope.spawn(move || loop {
+                    let Ok(id) = job_rx.lock().unwrap().recv() else {
+                        break;
+                    };
+                    let r = panic::catch_unwind(AssertUnwindSafe(|| f(&id)));
+                    if done_tx.send((id, r)).is_err() {
+                        break;
+                    }
+                });
+            }
+            let mut in_flight = 0usize;
+            loop {
+                while let Some(id) = plan.next_ready() {
+                    job_tx.send(id).unwrap();
+                    in_flight += 1;
+                }
+                if in_flight == 0 {
+                    break;
+                }
+                let (id, r) = done_rx.recv().unwrap();
+                in_flight -= 1;
+                match r {
+                    Ok(Ok(())) => plan.complete(&id),
+                    Ok(Err(e)) => failures.push((id, NodeFailure::Err(e))),
+                    Err(payload) => failures.push((id, NodeFailure::Panic(panic_message(payload)))),
+                }
+            }
+            // closes the job channel so the workers stop
+            drop(job_tx);
+        });
+        if failures.is_empty() {
+            return Ok(());
+        }
+        Err(ExecuteError::Failed {
+            failures,
+            skipped: plan.waiting(),
+        })
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(String::new, |msg| msg.to_string()),
+    }
+}
@@ -0,0 +1,69 @@
+//! Span helpers for the `tracing` feature, without it they are zero sized and do nothing
+
+use crate::*;
+
+/// The `add` span, counting the items and edges that went in while it is alive
+///
+/// Counting only happens with the feature on and a subscriber interested in the span
+pub(crate) struct AddSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+    #[cfg(feature = "tracing")]
+    items: AtomicUsize,
+    #[cfg(feature = "tracing")]
+    edges: AtomicUsize,
+}
+
+impl AddSpan {
+    #[inline]
+    pub fn enter() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "add",
+                items = tracing::field::Empty,
+                edges = tracing::field::Empty
+            )
+            .entered(),
+            #[cfg(feature = "tracing")]
+            items: AtomicUsize::new(0),
+            #[cfg(feature = "tracing")]
+            edges: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn count(&self, _item: &impl DepMeta) {
+        #[cfg(feature = "tracing")]
+        if !self.span.is_disabled() {
+            let edges = _item.get_deps().len() + _item.get_soft_deps().len();
+            self.items.fetch_add(1, Ordering::Relaxed);
+            self.edges.fetch_add(edges, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for AddSpan {
+    fn drop(&mut self) {
+        self.span.record("items", self.items.load(Ordering::Relaxed));
+        self.span.record("edges", self.edges.load(Ordering::Relaxed));
+    }
+}
+
+/// Hands `e` back after emitting it, `placed` of the `total` nodes made it into a level
+#[inline]
+pub(crate) fn failed<Id>(
+    e: DepResolveError<Id>,
+    _placed: usize,
+    _total: usize,
+) -> DepResolveError<Id> {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        error = e.name(),
+        placed = _placed,
+        unplaced = _total - _placed,
+        "resolve failed"
+    );
+    e
+}
@@ -0,0 +1,106 @@
+use crate::*;
+use std::collections::VecDeque;
+
+/// Order of [`DepRes::walk`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Walk {
+    /// Each subtree is done before its siblings, depths are those of the path taken
+    #[default]
+    Dfs,
+    /// Level by level away from the start, depths are shortest distances
+    Bfs,
+}
+
+/// What [`DepRes::walk`] does after visiting an id
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WalkControl {
+    #[default]
+    Continue,
+    /// Don't go past this id, ids also reachable another way are still visited that way
+    SkipSubtree,
+    /// End the walk
+    Stop,
+}
+
+impl<Id: Sync + Send + Eq + Hash + Clone, S: BuildHasher + Clone + Default + Send + Sync>
+    DepRes<Id, S>
+{
+    /// Visits `start` at depth 0 and then what it transitively depends on, every id at most
+    /// once so cycles end the walk too
+    ///
+    /// `visit` gets each id with its depth and steers the walk by what it returns. Deps are
+    /// followed through aliases and groups like [`resolved_deps_of`](Self::resolved_deps_of),
+    /// so an alias or group is never visited itself, deps of one id come in no particular
+    /// order. No lock is held while `visit` runs, it may even change the graph
+    pub fn walk(&self, start: &Id, order: Walk, visit: impl FnMut(&Id, usize) -> WalkControl) {
+        let next = |id: &Id| {
+            (self.target_deps(id).iter())
+                .map(|dep| self.shared(dep))
+                .collect()
+        };
+        self.walk_over(next, start, order, visit);
+    }
+
+    /// Like [`walk`](Self::walk) over dependents, through the reverse index
+    ///
+    /// Dependents are those of [`dependents_of`](Self::dependents_of), also the ones through
+    /// an alias or group
+    pub fn walk_dependents(
+        &self,
+        start: &Id,
+        order: Walk,
+        visit: impl FnMut(&Id, usize) -> WalkControl,
+    ) {
+        let rev = self.reverse_index();
+        let next = |id: &Id| rev.read(id, |_, next| next.to_vec()).unwrap_or_default();
+        self.walk_over(next, start, order, visit);
+    }
+
+    /// Iterative over a stack for [`Walk::Dfs`] and a queue for [`Walk::Bfs`]
+    fn walk_over(
+        &self,
+        next_of: impl Fn(&Id) -> Vec<Arc<Id>>,
+        start: &Id,
+        order: Walk,
+        mut visit: impl FnMut(&Id, usize) -> WalkControl,
+    ) {
+        let mut seen = HashSet::<Arc<Id>, S>::with_hasher(self.hasher.clone());
+        let start = self.shared(&self.canon(start));
+        let mut pending = VecDeque::from([(start.clone(), 0)]);
+        if order == Walk::Bfs {
+            seen.insert(start);
+        }
+        loop {
+            let next = match order {
+                Walk::Dfs => pending.pop_back(),
+                Walk::Bfs => pending.pop_front(),
+            };
+            let Some((id, depth)) = next else {
+                return;
+            };
+            // a stack may hold an id several times, only its first pop counts
+            if order == Walk::Dfs && !seen.insert(id.clone()) {
+                continue;
+            }
+            match visit(&id, depth) {
+                WalkControl::Continue => {}
+                WalkControl::SkipSubtree => continue,
+                WalkControl::Stop => return,
+            }
+            let mut next = next_of(&id);
+            if order == Walk::Dfs {
+                // popped in the order they were handed out
+                next.reverse();
+            }
+            for next in next {
+                let fresh = match order {
+                    Walk::Dfs => !seen.contains(&next),
+                    Walk::Bfs => seen.insert(next.clone()),
+                };
+                if fresh {
+                    pending.push_back((next, depth + 1));
+                }
+            }
+        }
+    }
+}
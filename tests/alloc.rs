@@ -0,0 +1,152 @@
+//! Allocation counts and live bytes of `resolve` and level reads, kept in their own test
+//! binary so the counting allocator sees no other test, and run one at a time
+
+use dep_res::{Dep, DepRes};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    ops::ControlFlow,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[test]
+fn resolve_allocations_per_level() {
+    let _serial = SERIAL.lock().unwrap();
+    let (levels, width) = (10_000, 4);
+    let items = (0..levels * width)
+        .map(|id| Dep {
+            id,
+            deps: match id / width {
+                0 => vec![],
+                lv => ((lv - 1) * width..lv * width).collect(),
+            },
+        })
+        .collect::<Vec<_>>();
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    // warm up lazily initialized globals such as the rayon pool
+    dr.resolve().unwrap();
+
+    let before = ALLOCS.load(Ordering::Relaxed);
+    let r = dr.resolve().unwrap();
+    let allocs = ALLOCS.load(Ordering::Relaxed) - before;
+    assert_eq!(r.level_count(), levels);
+    // the level sets themselves, nothing per level on top of that
+    assert!(
+        allocs < 12 * levels,
+        "{allocs} allocations for {levels} levels"
+    );
+}
+
+#[test]
+fn level_reads_allocate_nothing() {
+    let _serial = SERIAL.lock().unwrap();
+    let (levels, width) = (10, 10_000);
+    let items = (0..levels * width)
+        .map(|id| Dep {
+            id,
+            deps: match id / width {
+                0 => vec![],
+                lv => vec![(lv - 1) * width + id % width],
+            },
+        })
+        .collect::<Vec<_>>();
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve().unwrap();
+
+    let before = ALLOCS.load(Ordering::Relaxed);
+    let mut seen = 0;
+    for level in r.iter_level_shared() {
+        seen += level.deps.len();
+        assert_eq!(r[level.level].len(), width);
+        assert!(r.get_level_shared(level.level).is_some());
+    }
+    seen += r.iter_topo().count() + r.iter_topo_rev().count();
+    let allocs = ALLOCS.load(Ordering::Relaxed) - before;
+    assert_eq!(seen, 3 * levels * width);
+    assert_eq!(allocs, 0);
+}
+
+#[test]
+fn borrowed_lookups_allocate_nothing() {
+    let _serial = SERIAL.lock().unwrap();
+    let name = |i: usize| format!("registry/crates/crate-{i:04}");
+    let items = (0..1000)
+        .map(|i| Dep {
+            id: name(i),
+            deps: (i > 0).then(|| name(i - 1)).into_iter().collect(),
+        })
+        .collect::<Vec<_>>();
+    let mut dr = DepRes::new();
+    dr.add(&items);
+    let r = dr.resolve().unwrap();
+    let names = (0..1000).map(name).collect::<Vec<_>>();
+    // builds the id -> level map
+    r.level_of("");
+
+    let before = ALLOCS.load(Ordering::Relaxed);
+    for (i, id) in names.iter().enumerate() {
+        assert!(dr.contains(id.as_str()));
+        assert_eq!(r.level_of(id.as_str()), Some(i));
+    }
+    assert!(!dr.contains("registry/crates/unknown"));
+    assert_eq!(ALLOCS.load(Ordering::Relaxed) - before, 0);
+}
+
+#[test]
+fn streaming_memory_stays_flat() {
+    let _serial = SERIAL.lock().unwrap();
+    let (levels, width) = (10_000, 4);
+    let items = (0..levels * width)
+        .map(|id| Dep {
+            id,
+            deps: match id / width {
+                0 => vec![],
+                lv => ((lv - 1) * width..lv * width).collect(),
+            },
+        })
+        .collect::<Vec<_>>();
+    let dr = DepRes::new();
+    dr.add(&items);
+
+    // live bytes once leveling is under way against the most seen since
+    let (mut first, mut peak) = (None, 0);
+    let mut seen = 0;
+    dr.resolve_streaming(|_, ids| {
+        seen += ids.len();
+        let live = LIVE.load(Ordering::Relaxed);
+        first.get_or_insert(live);
+        peak = peak.max(live);
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(seen, levels * width);
+    let growth = peak - first.unwrap();
+    assert!(growth < 4096, "{growth} bytes more over {levels} levels");
+}
@@ -0,0 +1,128 @@
+//! The `dep-res` binary on the graphs in `tests/data/cli`
+#![cfg(all(feature = "cli", not(target_arch = "wasm32")))]
+
+use assert_cmd::Command;
+
+fn dep_res(args: &[&str]) -> assert_cmd::assert::Assert {
+    Command::cargo_bin("dep-res")
+        .unwrap()
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/cli"))
+        .args(args)
+        .assert()
+}
+
+fn stdout(args: &[&str]) -> String {
+    let out = dep_res(args).success().get_output().stdout.clone();
+    String::from_utf8(out).unwrap()
+}
+
+fn stderr(assert: assert_cmd::assert::Assert) -> String {
+    String::from_utf8(assert.get_output().stderr.clone()).unwrap()
+}
+
+const LEVELS: &str = "level 0: a, c, d\nlevel 1: b, e\nlevel 2: f\n";
+
+#[test]
+fn resolve_levels() {
+    assert_eq!(stdout(&["resolve", "graph.json"]), LEVELS);
+    assert_eq!(stdout(&["resolve", "deps.json"]), LEVELS);
+    assert_eq!(stdout(&["resolve", "items.json"]), LEVELS);
+    assert_eq!(stdout(&["resolve", "graph.txt"]), LEVELS);
+    assert_eq!(stdout(&["resolve", "graph.txt", "--input", "lines"]), LEVELS);
+}
+
+#[test]
+fn resolve_stdin() {
+    let out = Command::cargo_bin("dep-res")
+        .unwrap()
+        .args(["resolve", "-"])
+        .write_stdin("b: a\na\n")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    assert_eq!(String::from_utf8(out).unwrap(), "level 0: a\nlevel 1: b\n");
+}
+
+#[test]
+fn resolve_formats() {
+    assert_eq!(
+        stdout(&["resolve", "graph.json", "--format", "topo"]),
+        "a\nc\nd\nb\ne\nf\n"
+    );
+    assert_eq!(
+        stdout(&["resolve", "graph.json", "--format", "json"]),
+        "[[\"a\",\"c\",\"d\"],[\"b\",\"e\"],[\"f\"]]\n"
+    );
+    let dot = stdout(&["resolve", "graph.txt", "--format", "dot"]);
+    assert!(dot.starts_with("digraph {\n"));
+    assert!(dot.contains("{ rank=same; \"b\"; \"e\"; }"));
+    assert!(dot.contains("\"f\" -> \"e\";"));
+    let mermaid = stdout(&["resolve", "graph.txt", "--format", "mermaid"]);
+    assert!(mermaid.starts_with("flowchart TD\n    n0[\"a\"]\n"));
+    assert!(mermaid.contains("    n5[\"f\"]\n"));
+    assert!(mermaid.ends_with("    n3 --> n0\n    n4 --> n2\n    n5 --> n4\n"));
+}
+
+#[test]
+fn resolve_targets() {
+    assert_eq!(
+        stdout(&["resolve", "graph.txt", "--targets", "b,e", "--format", "topo"]),
+        "a\nd\nb\ne\n"
+    );
+    let err = stderr(dep_res(&["resolve", "graph.txt", "--targets", "b,z"]).code(1));
+    assert_eq!(err, "error: unknown targets: [\"z\"]\n");
+    // the cycle is outside of what d needs
+    assert_eq!(stdout(&["resolve", "cycle.txt", "--targets", "d"]), "level 0: d\n");
+}
+
+#[test]
+fn resolve_cycle() {
+    let err = stderr(dep_res(&["resolve", "cycle.txt"]).code(1));
+    let mut lines = err.lines();
+    assert_eq!(
+        lines.next(),
+        Some("error: 1 problem(s) found, 3 node(s) left unleveled")
+    );
+    // where the cycle starts depends on iteration order
+    let cycle = lines.next().unwrap();
+    assert!(
+        [
+            "  cycle: a -> c -> b -> a",
+            "  cycle: c -> b -> a -> c",
+            "  cycle: b -> a -> c -> b",
+        ]
+        .contains(&cycle),
+        "{cycle}"
+    );
+    assert_eq!(lines.next(), None);
+}
+
+#[test]
+fn resolve_missing_dep() {
+    let err = stderr(dep_res(&["resolve", "missing.txt", "--format", "topo"]).code(1));
+    assert_eq!(
+        err,
+        "error: 1 problem(s) found, 2 node(s) left unleveled\n  missing dep: b depends on x, which is no node\n"
+    );
+}
+
+#[test]
+fn resolve_bad_input() {
+    let err = stderr(dep_res(&["resolve", "bad.txt"]).code(2));
+    assert_eq!(
+        err,
+        "error: can't parse bad.txt: line 2: expected `id: dep1 dep2`, got `b c: a`\n"
+    );
+    let err = stderr(dep_res(&["resolve", "graph.txt", "--input", "json"]).code(2));
+    assert!(err.starts_with("error: can't parse graph.txt: "));
+    let err = stderr(dep_res(&["resolve", "dangling.json"]).code(2));
+    assert_eq!(
+        err,
+        "error: can't parse dangling.json: \"b\" depends on \"x\" which is not in nodes\n"
+    );
+    let err = stderr(dep_res(&["resolve", "nope.txt"]).code(2));
+    assert!(err.starts_with("error: can't read nope.txt: "));
+    dep_res(&["resolve", "graph.txt", "--format", "yaml"]).code(2);
+}
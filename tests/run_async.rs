@@ -0,0 +1,112 @@
+//! `DepRes::run_async` on tokio
+#![cfg(all(feature = "async", not(target_arch = "wasm32")))]
+
+use dep_res::{Dep, DepRes, OnError, RunAsyncError};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+fn graph(nodes: &[(usize, &[usize])]) -> DepRes<usize> {
+    let items = nodes
+        .iter()
+        .map(|&(id, deps)| Dep::with_deps(id, deps.iter().copied()))
+        .collect::<Vec<_>>();
+    let dr = DepRes::new();
+    dr.add(&items);
+    dr
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Start(usize),
+    Finish(usize),
+}
+
+#[tokio::test]
+async fn test_run_async_order() {
+    let nodes: &[(usize, &[usize])] = &[(0, &[]), (1, &[0]), (2, &[0]), (3, &[1, 2]), (4, &[])];
+    let dr = graph(nodes);
+    let log = Mutex::new(vec![]);
+    dr.run_async(4, OnError::Drain, |id| {
+        let log = &log;
+        async move {
+            log.lock().unwrap().push(Event::Start(id));
+            tokio::time::sleep(Duration::from_millis(5 * (id as u64 % 3))).await;
+            log.lock().unwrap().push(Event::Finish(id));
+            Ok::<_, ()>(())
+        }
+    })
+    .await
+    .unwrap();
+
+    let log = log.into_inner().unwrap();
+    assert_eq!(log.len(), 2 * nodes.len());
+    for &(id, deps) in nodes {
+        let start = log.iter().position(|e| *e == Event::Start(id)).unwrap();
+        for &dep in deps {
+            let finish = log.iter().position(|e| *e == Event::Finish(dep)).unwrap();
+            assert!(finish < start, "{id} started before {dep} finished");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_run_async_concurrency() {
+    let nodes = (0..20).map(|id| (id, &[][..])).collect::<Vec<_>>();
+    let dr = graph(&nodes);
+    let running = AtomicUsize::new(0);
+    let peak = AtomicUsize::new(0);
+    dr.run_async(3, OnError::Drain, |_| {
+        let (running, peak) = (&running, &peak);
+        async move {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            Ok::<_, ()>(())
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(peak.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_run_async_error() {
+    // 1 fails while 2 is still in flight, 3 depends on 1 and never starts
+    let dr = graph(&[(0, &[]), (1, &[0]), (2, &[0]), (3, &[1])]);
+    for (on_error, finished) in [(OnError::Drain, vec![0, 2]), (OnError::Abort, vec![0])] {
+        let done = Mutex::new(vec![]);
+        let r = dr
+            .run_async(4, on_error, |id| {
+                let done = &done;
+                async move {
+                    if id == 1 {
+                        return Err("boom");
+                    }
+                    if id == 2 {
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                    }
+                    done.lock().unwrap().push(id);
+                    Ok(())
+                }
+            })
+            .await;
+        assert_eq!(
+            r,
+            Err(RunAsyncError::Node {
+                id: 1,
+                error: "boom"
+            })
+        );
+        assert_eq!(done.into_inner().unwrap(), finished);
+    }
+
+    let dr = graph(&[(0, &[1]), (1, &[0])]);
+    let r = dr.run_async(1, OnError::Drain, |_| async { Ok::<_, ()>(()) });
+    assert!(matches!(r.await, Err(RunAsyncError::Resolve(_))));
+}